@@ -3,7 +3,6 @@ use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::Duration;
 use tokio::sync::mpsc;
 
 /// Client for interacting with Ollama API
@@ -20,9 +19,116 @@ pub struct Model {
     pub details: Option<ModelDetails>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct ModelDetails {
+    #[serde(default)]
     pub parameter_size: String,
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub family: String,
+    #[serde(default)]
+    pub families: Option<Vec<String>>,
+    #[serde(default)]
+    pub quantization_level: String,
+    #[serde(default)]
+    pub parent_model: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Completion,
+    Tools,
+    Vision,
+    Embedding,
+    Insert,
+    Thinking,
+    #[serde(other)]
+    Other,
+}
+
+/// Full model description returned by `/api/show`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelInfo {
+    #[serde(default)]
+    pub modelfile: String,
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub system: String,
+    #[serde(default)]
+    pub license: String,
+    #[serde(default)]
+    pub details: ModelDetails,
+    #[serde(default)]
+    pub model_info: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+impl ModelInfo {
+    pub fn architecture(&self) -> Option<&str> {
+        self.model_info
+            .get("general.architecture")
+            .and_then(|v| v.as_str())
+    }
+
+    /// Native context window, reported as `<architecture>.context_length`
+    pub fn context_length(&self) -> Option<u64> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+    }
+
+    /// `num_ctx` set by the Modelfile, which Ollama loads the model with
+    pub fn num_ctx(&self) -> Option<u64> {
+        self.parameter_list()
+            .into_iter()
+            .find(|(key, _)| key == "num_ctx")
+            .and_then(|(_, value)| value.parse().ok())
+    }
+
+    /// Parameters come back as a whitespace-aligned `key value` block
+    pub fn parameter_list(&self) -> Vec<(String, String)> {
+        self.parameters
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                let (key, value) = line.split_once(char::is_whitespace)?;
+                Some((key.to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    /// Capabilities as reported by the server, inferred from metadata on
+    /// older Ollama versions that don't send them.
+    pub fn effective_capabilities(&self) -> Vec<Capability> {
+        if !self.capabilities.is_empty() {
+            return self.capabilities.clone();
+        }
+
+        let mut capabilities = Vec::new();
+        let is_embedding = self
+            .architecture()
+            .is_some_and(|arch| arch.contains("bert") || arch.contains("embed"));
+
+        if is_embedding {
+            capabilities.push(Capability::Embedding);
+        } else {
+            capabilities.push(Capability::Completion);
+        }
+        if self.template.contains(".Tools") {
+            capabilities.push(Capability::Tools);
+        }
+        if self.model_info.keys().any(|k| k.contains(".vision.")) {
+            capabilities.push(Capability::Vision);
+        }
+        capabilities
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -53,6 +159,54 @@ struct PullRequest {
     stream: bool,
 }
 
+/// A single NDJSON status event streamed from `/api/pull`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    pub digest: Option<String>,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Body for `/api/create`.
+///
+/// Current Ollama builds models from the structured fields, older servers
+/// only understand the raw `modelfile`, so both are sent.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateModelRequest {
+    pub model: String,
+    pub name: String,
+    pub modelfile: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub parameters: serde_json::Map<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<serde_json::Value>,
+    pub stream: bool,
+}
+
+/// A single NDJSON status event streamed from `/api/create`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CreateProgress {
+    #[serde(default)]
+    pub status: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ShowRequest {
+    model: String,
+}
+
 #[derive(Debug, Serialize)]
 struct DeleteRequest {
     name: String,
@@ -142,10 +296,14 @@ impl OllamaClient {
         Ok(process_list.models)
     }
 
-    pub async fn pull_model(&self, model_name: &str) -> Result<()> {
+    pub async fn pull_model(
+        &self,
+        model_name: &str,
+        progress_sender: mpsc::Sender<PullProgress>,
+    ) -> Result<()> {
         let request = PullRequest {
             name: model_name.to_string(),
-            stream: false,
+            stream: true,
         };
 
         let response = self
@@ -159,6 +317,92 @@ impl OllamaClient {
             anyhow::bail!("Failed to pull model: {}", response.status());
         }
 
+        let mut stream = response.bytes_stream();
+        let mut buffer = NdjsonBuffer::default();
+        let mut succeeded = false;
+
+        while let Some(chunk) = stream.next().await {
+            for line in buffer.push(&chunk?) {
+                let Ok(progress) = serde_json::from_str::<PullProgress>(&line) else {
+                    continue;
+                };
+
+                if let Some(error) = &progress.error {
+                    anyhow::bail!("{error}");
+                }
+                succeeded |= progress.status == "success";
+
+                if progress_sender.send(progress).await.is_err() {
+                    // Receiver dropped, nobody is watching this pull anymore
+                    return Ok(());
+                }
+            }
+        }
+
+        // A dropped connection just ends the body, so only Ollama's final
+        // `success` line means the model is actually there
+        if !succeeded {
+            anyhow::bail!("pull ended before completion");
+        }
+        Ok(())
+    }
+
+    pub async fn show_model(&self, model_name: &str) -> Result<ModelInfo> {
+        let request = ShowRequest {
+            model: model_name.to_string(),
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to show model: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    pub async fn create_model(
+        &self,
+        request: CreateModelRequest,
+        progress_sender: mpsc::Sender<CreateProgress>,
+    ) -> Result<()> {
+        let response = self
+            .client
+            .post(format!("{}/api/create", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Failed to create model: {status} {}", body.trim());
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = NdjsonBuffer::default();
+
+        while let Some(chunk) = stream.next().await {
+            for line in buffer.push(&chunk?) {
+                let Ok(progress) = serde_json::from_str::<CreateProgress>(&line) else {
+                    continue;
+                };
+
+                if let Some(error) = &progress.error {
+                    anyhow::bail!("{error}");
+                }
+
+                if progress_sender.send(progress).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -247,12 +491,18 @@ impl OllamaClient {
         &self,
         model: &str,
         messages: Vec<serde_json::Value>,
+        options: GenerationOptions,
+        keep_alive: Option<String>,
+        tools: Vec<serde_json::Value>,
         response_sender: mpsc::Sender<ChatResponse>,
     ) -> Result<()> {
         let request = ChatRequest {
             model: model.to_string(),
             messages,
             stream: true,
+            options: (!options.is_empty()).then_some(options),
+            keep_alive: keep_alive.as_deref().map(keep_alive_value),
+            tools,
         };
 
         let response = self
@@ -263,47 +513,107 @@ impl OllamaClient {
             .await?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Chat request failed: {}", response.status());
+            anyhow::bail!("Chat request failed: {}", error_text(response).await);
         }
 
         let mut stream = response.bytes_stream();
+        let mut buffer = NdjsonBuffer::default();
 
         while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            let text = String::from_utf8_lossy(&chunk);
-
-            // Parse each line as a separate JSON response
-            for line in text.lines() {
-                if line.trim().is_empty() {
+            for line in buffer.push(&chunk?) {
+                let Ok(response) = serde_json::from_str::<ChatResponse>(&line) else {
                     continue;
-                }
-
-                if let Ok(response) = serde_json::from_str::<ChatResponse>(line) {
-                    let is_done = response.done.unwrap_or(false);
-
-                    // Use try_send for non-blocking send to avoid slowing down the stream
-                    match response_sender.try_send(response) {
-                        Ok(_) => {}
-                        Err(mpsc::error::TrySendError::Full(resp)) => {
-                            // If the channel is full, wait a tiny bit and try again
-                            tokio::time::sleep(Duration::from_micros(100)).await;
-                            let _ = response_sender.try_send(resp);
-                        }
-                        Err(mpsc::error::TrySendError::Closed(_)) => {
-                            // Receiver dropped, stop processing
-                            return Ok(());
-                        }
-                    }
+                };
+                let is_done = response.done.unwrap_or(false);
 
-                    if is_done {
-                        break;
-                    }
+                // Several sessions can stream at once, so wait for room rather than drop tokens
+                if response_sender.send(response).await.is_err() {
+                    // Receiver dropped, stop processing
+                    return Ok(());
+                }
+                if is_done {
+                    return Ok(());
                 }
             }
         }
 
         Ok(())
     }
+
+    /// Non-streaming chat request, returning the whole reply
+    pub async fn complete(
+        &self,
+        model: &str,
+        messages: Vec<serde_json::Value>,
+        options: GenerationOptions,
+        keep_alive: Option<String>,
+        tools: Vec<serde_json::Value>,
+    ) -> Result<ChatMessage> {
+        let request = ChatRequest {
+            model: model.to_string(),
+            messages,
+            stream: false,
+            options: (!options.is_empty()).then_some(options),
+            keep_alive: keep_alive.as_deref().map(keep_alive_value),
+            tools,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Chat request failed: {}", error_text(response).await);
+        }
+
+        let response: ChatResponse = response.json().await?;
+        response
+            .message
+            .ok_or_else(|| anyhow::anyhow!("The response had no message"))
+    }
+
+    /// One embedding vector per input, from `/api/embed`
+    pub async fn embed(&self, model: &str, input: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let count = input.len();
+        let request = EmbedRequest {
+            model: model.to_string(),
+            input,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Embed request failed: {}", error_text(response).await);
+        }
+
+        let response: EmbedResponse = response.json().await?;
+        if response.embeddings.len() != count {
+            anyhow::bail!(
+                "Asked for {count} embeddings but got {}",
+                response.embeddings.len()
+            );
+        }
+        Ok(response.embeddings)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -311,17 +621,231 @@ struct ChatRequest {
     model: String,
     messages: Vec<serde_json::Value>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<GenerationOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<serde_json::Value>,
+}
+
+/// Status plus Ollama's `{"error": ...}` message, which says why a request
+/// was refused (e.g. "model does not support tools")
+async fn error_text(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    match serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["error"].as_str().map(str::to_string))
+    {
+        Some(error) => format!("{status}: {error}"),
+        None => status.to_string(),
+    }
+}
+
+/// Plain numbers are seconds (`-1` keeps the model loaded forever);
+/// anything else is a duration string such as `10m`
+fn keep_alive_value(value: &str) -> serde_json::Value {
+    match value.parse::<i64>() {
+        Ok(seconds) => serde_json::json!(seconds),
+        Err(_) => serde_json::json!(value),
+    }
+}
+
+/// Sampling options sent as the `options` object of a chat request.
+/// Unset fields fall back to the model's Modelfile defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl GenerationOptions {
+    /// Option names in the order they are shown and exported
+    pub const KEYS: &'static [&'static str] = &[
+        "temperature",
+        "top_k",
+        "top_p",
+        "min_p",
+        "repeat_penalty",
+        "num_ctx",
+        "num_predict",
+        "seed",
+        "stop",
+    ];
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The value of an option as text, or `None` when it is unset
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "temperature" => self.temperature.map(|v| v.to_string()),
+            "top_k" => self.top_k.map(|v| v.to_string()),
+            "top_p" => self.top_p.map(|v| v.to_string()),
+            "min_p" => self.min_p.map(|v| v.to_string()),
+            "repeat_penalty" => self.repeat_penalty.map(|v| v.to_string()),
+            "num_ctx" => self.num_ctx.map(|v| v.to_string()),
+            "num_predict" => self.num_predict.map(|v| v.to_string()),
+            "seed" => self.seed.map(|v| v.to_string()),
+            "stop" => (!self.stop.is_empty()).then(|| self.stop.join(", ")),
+            _ => None,
+        }
+    }
+
+    /// Set an option from text; an empty value resets it to the model default.
+    /// Stop sequences are comma separated.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        match key {
+            "temperature" => {
+                self.temperature = parse_option(key, value, |v| (0.0..=2.0).contains(v), "0-2")?
+            }
+            "top_k" => self.top_k = parse_option(key, value, |v| *v >= 0, ">= 0")?,
+            "top_p" => self.top_p = parse_option(key, value, |v| (0.0..=1.0).contains(v), "0-1")?,
+            "min_p" => self.min_p = parse_option(key, value, |v| (0.0..=1.0).contains(v), "0-1")?,
+            "repeat_penalty" => {
+                self.repeat_penalty = parse_option(key, value, |v| *v >= 0.0, ">= 0")?
+            }
+            "num_ctx" => self.num_ctx = parse_option(key, value, |v| *v > 0, "> 0")?,
+            // -1 generates until the model stops, -2 until the context is full
+            "num_predict" => self.num_predict = parse_option(key, value, |v| *v >= -2, ">= -2")?,
+            "seed" => self.seed = parse_option(key, value, |_| true, "")?,
+            "stop" => {
+                self.stop = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            _ => anyhow::bail!("unknown option `{key}`"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_option<T: std::str::FromStr>(
+    key: &str,
+    value: &str,
+    valid: impl Fn(&T) -> bool,
+    expected: &str,
+) -> Result<Option<T>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse() {
+        Ok(parsed) if valid(&parsed) => Ok(Some(parsed)),
+        Ok(_) => anyhow::bail!("{key} must be {expected}, got `{value}`"),
+        Err(_) => anyhow::bail!("{key} expects a number, got `{value}`"),
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChatResponse {
     pub message: Option<ChatMessage>,
     pub done: Option<bool>,
+    #[serde(flatten)]
+    pub metrics: GenerationMetrics,
+}
+
+/// Token counts and timings sent with the last chunk of a reply.
+/// Durations are in nanoseconds, as Ollama reports them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationMetrics {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub eval_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_duration: Option<u64>,
+    /// Time from sending the request to the first token, measured locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token: Option<u64>,
+}
+
+impl GenerationMetrics {
+    /// Generation speed in tokens per second
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        rate(self.eval_count?, self.eval_duration?)
+    }
+
+    /// Prompt processing speed in tokens per second
+    pub fn prompt_tokens_per_second(&self) -> Option<f64> {
+        rate(self.prompt_eval_count?, self.prompt_eval_duration?)
+    }
+}
+
+fn rate(tokens: u32, nanos: u64) -> Option<f64> {
+    (nanos > 0).then(|| tokens as f64 / (nanos as f64 / 1e9))
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
+    #[serde(default)]
     pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// A function the model wants called, as sent in `message.tool_calls`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub function: ToolFunction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// Accumulates streamed bytes and yields complete NDJSON lines.
+///
+/// HTTP chunks don't respect line boundaries, so a JSON object can arrive
+/// split across two chunks.
+#[derive(Default)]
+struct NdjsonBuffer {
+    pending: Vec<u8>,
+}
+
+impl NdjsonBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
 }
@@ -1,10 +1,29 @@
-use crate::api::{ChatResponse, Model, ModelsDevModel, OllamaClient, RunningModel};
-use crate::chat::{ChatState, InputMode, MessageRole};
+use crate::api::{
+    Capability, ChatResponse, GenerationMetrics, GenerationOptions, Model, ModelInfo,
+    ModelsDevModel, OllamaClient, RunningModel, ToolCall,
+};
+use crate::chat::{ChatSession, ChatState, ContextSummary, InputMode, MessageRole};
+use crate::clipboard;
+use crate::compare::{CompareColumn, CompareState, ModelPicker};
+use crate::downloads::{DownloadManager, DownloadNotice};
+use crate::export::{self, ExportFormat};
+use crate::files::{self, FileContext, FileSearch};
+use crate::markdown::{self, CodeBlock};
+use crate::modelfile::Severity;
+use crate::modelfile_editor::{self, CreateStatus, EditorFocus, ModelfileEditor};
+use crate::presets::PresetStore;
+use crate::rag::{self, Index, IndexJob, RagSource, Retrieval};
+use crate::storage::{self, OpenSessions, SessionStore, SessionSummary};
 use crate::theme::Sparkle;
+use crate::tool_check::{self, ToolCheck};
+use crate::tools;
 use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
 use ratatui::widgets::ListState;
-use std::collections::VecDeque;
-use tokio::sync::mpsc;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
 
 /// Main application state for llamaloop TUI
 pub struct App {
@@ -19,6 +38,12 @@ pub struct App {
     pub models_list_state: ListState,
     pub show_pull_dialog: bool,
     pub pull_model_name: String,
+    pub downloads: DownloadManager,
+    pub model_inspector: ModelInspector,
+    pub model_info_cache: HashMap<String, ModelInfo>,
+    model_info_fetches: HashMap<String, ModelInfoFetch>, // `/api/show` requests in flight
+    vision_check: Option<(String, String)>, // Session and model to check for vision once loaded
+    pub modelfile_editor: Option<ModelfileEditor>,
     pub show_delete_confirmation: bool,
     pub model_to_delete: Option<String>,
     pub ollama_client: OllamaClient,
@@ -30,9 +55,23 @@ pub struct App {
     pub models_tab_view: ModelsTabView,
     pub api_explorer_state: ApiExplorerState,
     pub chat_state: ChatState,
-    pub chat_response_receiver: Option<mpsc::Receiver<ChatResponse>>,
-    last_status_logged: bool, // Track if we already logged the current status
-    last_model_count: usize,  // Track model count changes
+    pub chat_streams: HashMap<String, ChatStream>, // In-flight responses, keyed by session id
+    pub session_store: SessionStore,
+    pub session_browser: SessionBrowser,
+    pub presets: PresetStore,
+    pub preset_picker: PresetPicker,
+    pub options_panel: OptionsPanel,
+    pub code_block_picker: CodeBlockPicker,
+    pub image_picker: ImagePicker,
+    pub compare: CompareState,
+    pub tool_check: ToolCheck,
+    pub tool_batches: Vec<ToolBatch>, // Tool calls waiting for approval or still running
+    pub indexing: Option<IndexJob>,
+    pub retrievals: Vec<Retrieval>, // Questions being looked up before they're sent
+    pub file_search: Option<FileSearch>, // `/file` or `/dir` being walked and read
+    rag_indexes: HashMap<PathBuf, Arc<Index>>, // Loaded indexes, keyed by index file
+    last_status_logged: bool,       // Track if we already logged the current status
+    last_model_count: usize,        // Track model count changes
 }
 
 #[derive(Clone, PartialEq)]
@@ -42,6 +81,9 @@ pub enum CurrentScreen {
     Logs,
     Chat,
     Help,
+    ModelfileEditor,
+    Compare,
+    ToolCheck,
 }
 
 #[derive(Clone, PartialEq)]
@@ -91,6 +133,380 @@ pub struct ApiExplorerState {
     pub copied_url: Option<String>, // Track what was copied
 }
 
+/// Detail pane for an installed model, backed by `/api/show`
+#[derive(Default)]
+pub struct ModelInspector {
+    pub visible: bool,
+    pub model_name: String,
+    pub error: Option<String>,
+    pub scroll: u16,
+    loading: Option<ModelInfoFetch>, // `/api/show` for `model_name`, in flight
+}
+
+/// A model's details being loaded from `/api/show` off the UI task
+struct ModelInfoFetch {
+    receiver: oneshot::Receiver<anyhow::Result<ModelInfo>>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl ModelInfoFetch {
+    fn start(client: &OllamaClient, model_name: &str) -> Self {
+        let (tx, rx) = oneshot::channel();
+        let client = client.clone();
+        let model_name = model_name.to_string();
+        let handle = tokio::spawn(async move {
+            let _ = tx.send(client.show_model(&model_name).await);
+        });
+        Self {
+            receiver: rx,
+            handle,
+        }
+    }
+
+    /// The details, once `/api/show` has answered
+    fn poll(&mut self) -> Option<anyhow::Result<ModelInfo>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Some(Err(anyhow::anyhow!("the request was cancelled")))
+            }
+        }
+    }
+}
+
+/// Sent back in place of output when the user says no
+const TOOL_DECLINED: &str = "The user declined to run this tool call.";
+
+/// The tool calls from one assistant reply. Each is confirmed by the user
+/// before it runs; once every result is in, they go back to the model.
+pub struct ToolBatch {
+    pub session_id: String,
+    pub calls: Vec<ToolCall>,
+    pub next: usize, // First call still waiting for an answer
+    results: Vec<Option<String>>,
+    running: Vec<(usize, oneshot::Receiver<String>)>,
+}
+
+impl ToolBatch {
+    /// The call the user is being asked about
+    pub fn pending(&self) -> Option<&ToolCall> {
+        self.calls.get(self.next)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next == self.calls.len() && self.results.iter().all(Option::is_some)
+    }
+}
+
+/// A response being generated in the background for one chat session
+pub struct ChatStream {
+    receiver: mpsc::Receiver<ChatResponse>,
+    handle: tokio::task::JoinHandle<()>,
+    started: std::time::Instant,
+    first_token: Option<std::time::Duration>,
+    summary: Option<oneshot::Receiver<Result<ContextSummary, String>>>, // Set while summarizing older turns
+}
+
+enum StreamEnd {
+    Completed,
+    Failed(String),
+    Disconnected,
+}
+
+impl ChatStream {
+    /// Plan the session's next request against the context `window` and
+    /// start streaming the reply in the background
+    fn spawn(client: &OllamaClient, session: &mut ChatSession, window: Option<usize>) -> Self {
+        session.is_streaming = true;
+        session.current_response.clear();
+
+        let plan = session.plan_context(window);
+        session.context_estimate = plan.estimated_tokens;
+        session.context_dropped = plan.dropped;
+
+        let model = session.current_model.clone();
+        let options = session.options.clone();
+        let keep_alive = session.keep_alive.clone();
+        let summarizing = plan.summarize.is_some();
+        let tools = if session.tools {
+            tools::definitions()
+        } else {
+            Vec::new()
+        };
+
+        let (tx, rx) = mpsc::channel(100);
+        let (summary_tx, summary_rx) = oneshot::channel();
+
+        let ollama_client = client.clone();
+        let error_tx = tx.clone();
+
+        let handle = tokio::spawn(async move {
+            let mut messages = plan.messages;
+            if let Some(request) = plan.summarize {
+                let summary = ollama_client
+                    .complete(
+                        &model,
+                        request.prompt(),
+                        options.clone(),
+                        keep_alive.clone(),
+                        Vec::new(),
+                    )
+                    .await
+                    .map(|message| message.content)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| match text.trim() {
+                        "" => Err("the model returned an empty summary".to_string()),
+                        text => Ok(text.to_string()),
+                    });
+                if let Ok(text) = &summary {
+                    messages = request.messages_with(text);
+                }
+                let _ = summary_tx.send(summary.map(|text| ContextSummary {
+                    text,
+                    covers: request.covers,
+                }));
+            }
+
+            if let Err(e) = ollama_client
+                .chat(&model, messages, options, keep_alive, tools, tx)
+                .await
+            {
+                eprintln!("Chat error: {e}");
+                // Send error as a special response
+                let error_response = ChatResponse {
+                    message: Some(crate::api::ChatMessage {
+                        role: "system".to_string(),
+                        content: format!(
+                            "❌ Error: {e}. Make sure the model '{model}' is installed."
+                        ),
+                        tool_calls: Vec::new(),
+                    }),
+                    done: Some(true),
+                    metrics: GenerationMetrics::default(),
+                };
+                let _ = error_tx.send(error_response).await;
+            }
+        });
+
+        ChatStream {
+            receiver: rx,
+            handle,
+            started: std::time::Instant::now(),
+            first_token: None,
+            summary: summarizing.then_some(summary_rx),
+        }
+    }
+
+    /// Move what has arrived into `session`. When the stream has ended the
+    /// reply is finished and the reason returned, along with a log line if
+    /// the summary of older messages came back.
+    fn receive(
+        &mut self,
+        session: &mut ChatSession,
+    ) -> (Option<StreamEnd>, Option<(LogLevel, String)>) {
+        const MAX_MESSAGES_PER_BATCH: usize = 10; // Process up to 10 messages per call
+
+        let mut summary_log = None;
+        if let Some(receiver) = &mut self.summary {
+            match receiver.try_recv() {
+                Ok(Ok(summary)) => {
+                    summary_log = Some((
+                        LogLevel::Info,
+                        format!("🗜️ Summarized {} older message(s)", summary.covers),
+                    ));
+                    session.context_summary = Some(summary);
+                    session.context_dropped = 0;
+                    self.summary = None;
+                    // Time to first token shouldn't include the summary
+                    self.started = std::time::Instant::now();
+                }
+                Ok(Err(e)) => {
+                    summary_log = Some((
+                        LogLevel::Warning,
+                        format!("⚠️ Couldn't summarize older messages, left them out instead: {e}"),
+                    ));
+                    self.summary = None;
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.summary = None,
+            }
+        }
+
+        let mut end = None;
+        let mut metrics = None;
+        for _ in 0..MAX_MESSAGES_PER_BATCH {
+            match self.receiver.try_recv() {
+                Ok(response) => {
+                    if let Some(message) = response.message {
+                        match message.role.as_str() {
+                            "assistant" => {
+                                if self.first_token.is_none() && !message.content.is_empty() {
+                                    self.first_token = Some(self.started.elapsed());
+                                }
+                                session.current_response.push_str(&message.content);
+                                session.current_tool_calls.extend(message.tool_calls);
+                            }
+                            "system" => {
+                                // This is an error message
+                                end = Some(StreamEnd::Failed(message.content));
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    if response.done.unwrap_or(false) {
+                        let eval_count = response.metrics.eval_count;
+                        if let Some(eval_count) = eval_count {
+                            session.total_tokens += eval_count as usize;
+                        }
+                        if let Some(prompt_eval_count) = response.metrics.prompt_eval_count {
+                            session.context_used =
+                                (prompt_eval_count + eval_count.unwrap_or(0)) as usize;
+                        }
+                        metrics = Some(response.metrics);
+                        end = Some(StreamEnd::Completed);
+                        break;
+                    }
+                }
+                // No more messages available
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    end = Some(StreamEnd::Disconnected);
+                    break;
+                }
+            }
+        }
+
+        session.response_markdown.update(&session.current_response);
+
+        if let Some(end) = &end {
+            let metrics =
+                metrics.or_else(|| self.first_token.map(|_| GenerationMetrics::default()));
+            session.finish_response(
+                matches!(end, StreamEnd::Disconnected),
+                self.stamp_first_token(metrics),
+            );
+        }
+        (end, summary_log)
+    }
+
+    /// Stop the request; the partial reply stays in `session`
+    fn stop(self, session: &mut ChatSession) {
+        self.handle.abort();
+        // A stopped reply has no server metrics, but the first token may have come
+        let metrics = self.first_token.map(|_| GenerationMetrics::default());
+        session.finish_response(true, self.stamp_first_token(metrics));
+    }
+
+    fn stamp_first_token(&self, metrics: Option<GenerationMetrics>) -> Option<GenerationMetrics> {
+        metrics.map(|metrics| GenerationMetrics {
+            first_token: self.first_token.map(|d| d.as_nanos() as u64),
+            ..metrics
+        })
+    }
+}
+
+/// Overlay for applying and managing system prompt presets
+#[derive(Clone, Default)]
+pub struct PresetPicker {
+    pub visible: bool,
+    pub selected: usize,
+    pub name_buffer: Option<String>, // Naming a new preset saved from the session's prompt
+    pub confirm_delete: bool,
+}
+
+/// Overlay listing the fenced code blocks in the active session
+#[derive(Clone, Default)]
+pub struct CodeBlockPicker {
+    pub visible: bool,
+    pub blocks: Vec<CodeBlock>, // Numbered from 1 in transcript order
+    pub selected: usize,
+    pub path_buffer: Option<String>, // File path being typed for `w`
+}
+
+/// File browser for attaching an image to the next message
+#[derive(Clone, Default)]
+pub struct ImagePicker {
+    pub visible: bool,
+    pub dir: PathBuf,
+    pub entries: Vec<(String, bool)>, // File name, and whether it's a directory
+    pub selected: usize,
+}
+
+impl ImagePicker {
+    /// List `dir`: subdirectories first, then images, leaving out hidden files
+    fn load(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                entries.push((name, true));
+            } else if is_image(&path) {
+                entries.push((name, false));
+            }
+        }
+        entries.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase()))
+        });
+        self.dir = dir;
+        self.entries = entries;
+        self.selected = 0;
+        Ok(())
+    }
+}
+
+/// Image formats vision models accept
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// A warning for attaching images when `model` doesn't list vision
+fn vision_warning(model: &str, info: &ModelInfo) -> Option<String> {
+    (!info.effective_capabilities().contains(&Capability::Vision)).then(|| {
+        format!(
+            "⚠️ {model} doesn't list vision among its capabilities, so it may not see the image"
+        )
+    })
+}
+
+/// Overlay for editing the active session's generation options
+#[derive(Clone, Default)]
+pub struct OptionsPanel {
+    pub visible: bool,
+    pub selected: usize,
+    pub edit_buffer: Option<String>, // Value being typed for the selected option
+    pub error: Option<String>,
+}
+
+/// Overlay listing saved chat sessions
+#[derive(Clone, Default)]
+pub struct SessionBrowser {
+    pub visible: bool,
+    pub sessions: Vec<SessionSummary>,
+    pub selected: usize,
+    pub rename_buffer: Option<String>,
+    pub confirm_delete: bool,
+}
+
+impl SessionBrowser {
+    pub fn selected_session(&self) -> Option<&SessionSummary> {
+        self.sessions.get(self.selected)
+    }
+}
+
 #[derive(Clone)]
 pub struct ApiEndpoint {
     pub name: String,
@@ -115,6 +531,11 @@ impl App {
         let mut api_endpoints_state = ListState::default();
         api_endpoints_state.select(Some(0));
 
+        let data_dir = storage::data_dir();
+        let session_store = SessionStore::new(&data_dir);
+        let chat_state = restore_chat_state(&session_store);
+        let presets = PresetStore::load(&data_dir, &modelfile_editor::modelfiles_dir());
+
         Self {
             selected_tab: 3,
             current_screen: CurrentScreen::Chat,
@@ -127,6 +548,17 @@ impl App {
             models_list_state,
             show_pull_dialog: false,
             pull_model_name: String::new(),
+            model_inspector: ModelInspector::default(),
+            model_info_cache: HashMap::new(),
+            model_info_fetches: HashMap::new(),
+            vision_check: None,
+            modelfile_editor: None,
+            downloads: DownloadManager::new(
+                std::env::var("LLAMALOOP_MAX_DOWNLOADS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(2),
+            ),
             show_delete_confirmation: false,
             model_to_delete: None,
             ollama_client: OllamaClient::new("http://localhost:11434".to_string()),
@@ -150,8 +582,22 @@ impl App {
                 selected_url_index: 0,
                 copied_url: None,
             },
-            chat_state: ChatState::new(Vec::new()),
-            chat_response_receiver: None,
+            chat_state,
+            chat_streams: HashMap::new(),
+            session_store,
+            session_browser: SessionBrowser::default(),
+            presets,
+            preset_picker: PresetPicker::default(),
+            options_panel: OptionsPanel::default(),
+            code_block_picker: CodeBlockPicker::default(),
+            image_picker: ImagePicker::default(),
+            tool_batches: Vec::new(),
+            compare: CompareState::new(),
+            tool_check: ToolCheck::default(),
+            indexing: None,
+            retrievals: Vec::new(),
+            file_search: None,
+            rag_indexes: HashMap::new(),
             last_status_logged: false,
             last_model_count: 0,
         }
@@ -227,12 +673,159 @@ impl App {
         }
     }
 
-    pub async fn on_enter(&mut self) {
-        if self.show_pull_dialog && !self.pull_model_name.is_empty() {
-            self.start_pull_model().await;
+    pub fn on_enter(&mut self) {
+        if self.show_pull_dialog && !self.pull_model_name.trim().is_empty() {
+            self.start_pull_model();
+        } else if self.current_screen == CurrentScreen::Models
+            && self.models_tab_view == ModelsTabView::Library
+        {
+            self.toggle_model_inspector();
+        }
+    }
+
+    /// The installed model under the cursor, if the cursor is on one
+    pub fn selected_installed_model(&self) -> Option<&Model> {
+        match self.models_view_mode {
+            ModelsViewMode::Available => None,
+            ModelsViewMode::Installed | ModelsViewMode::All => {
+                self.models.get(self.selected_model_index)
+            }
+        }
+    }
+
+    pub fn toggle_model_inspector(&mut self) {
+        if self.model_inspector.visible {
+            self.model_inspector.visible = false;
+            return;
+        }
+        if self.selected_installed_model().is_some() {
+            self.model_inspector.visible = true;
+            self.sync_model_inspector();
+        }
+    }
+
+    /// Point the inspector at the current selection. Details not cached yet
+    /// are fetched in the background, so holding an arrow key never waits on
+    /// `/api/show`; a fetch for a model the cursor has left is dropped.
+    pub fn sync_model_inspector(&mut self) {
+        if !self.model_inspector.visible {
+            return;
+        }
+        let Some(model_name) = self.selected_installed_model().map(|m| m.name.clone()) else {
+            return;
+        };
+        let inspector = &mut self.model_inspector;
+        if model_name == inspector.model_name
+            && (inspector.error.is_none() || inspector.loading.is_some())
+        {
+            return;
+        }
+
+        inspector.model_name = model_name.clone();
+        inspector.scroll = 0;
+        inspector.error = None;
+        if let Some(fetch) = inspector.loading.take() {
+            fetch.handle.abort();
+        }
+        if self.model_info_cache.contains_key(&model_name) {
+            return;
+        }
+
+        self.model_inspector.loading =
+            Some(ModelInfoFetch::start(&self.ollama_client, &model_name));
+    }
+
+    /// Pick up the inspector's details once `/api/show` answers
+    pub fn process_model_inspector(&mut self) {
+        let Some(result) = self
+            .model_inspector
+            .loading
+            .as_mut()
+            .and_then(ModelInfoFetch::poll)
+        else {
+            return;
+        };
+        self.model_inspector.loading = None;
+
+        let model_name = self.model_inspector.model_name.clone();
+        match result {
+            Ok(info) => {
+                self.model_info_cache.insert(model_name, info);
+            }
+            Err(e) => {
+                self.add_log(
+                    LogLevel::Error,
+                    &format!("❌ Failed to load details for {model_name}: {e}"),
+                );
+                self.model_inspector.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// `/api/show` for a model, served from cache after the first call
+    /// Load a model's details in the background unless they're cached or
+    /// already on the way. Until they arrive, whatever needs them (context
+    /// length, capabilities) goes without.
+    pub fn request_model_info(&mut self, model_name: &str) {
+        if self.model_info_cache.contains_key(model_name)
+            || self.model_info_fetches.contains_key(model_name)
+        {
+            return;
+        }
+        self.model_info_fetches.insert(
+            model_name.to_string(),
+            ModelInfoFetch::start(&self.ollama_client, model_name),
+        );
+    }
+
+    /// Cache the details that have arrived. A failure is only logged; the
+    /// next request tries again.
+    pub fn process_model_info(&mut self) {
+        let finished: Vec<(String, anyhow::Result<ModelInfo>)> = self
+            .model_info_fetches
+            .iter_mut()
+            .filter_map(|(name, fetch)| fetch.poll().map(|result| (name.clone(), result)))
+            .collect();
+        for (model_name, result) in finished {
+            self.model_info_fetches.remove(&model_name);
+            let vision_check = self
+                .vision_check
+                .take_if(|(_, model)| *model == model_name)
+                .map(|(session_id, _)| session_id);
+            let editor = self
+                .modelfile_editor
+                .as_mut()
+                .filter(|e| e.loading && e.source_model.as_ref() == Some(&model_name));
+            if let Some(editor) = editor {
+                // Without the details, start from a plain Modelfile as for a new model
+                let content = match &result {
+                    Ok(info) => modelfile_of(&model_name, info),
+                    Err(_) => default_modelfile(&model_name),
+                };
+                editor.finish_loading(&content);
+            }
+            match result {
+                Ok(info) => {
+                    let warning = vision_warning(&model_name, &info);
+                    let session = vision_check.and_then(|id| self.chat_state.session_index(&id));
+                    if let (Some(warning), Some(index)) = (warning, session) {
+                        self.add_log(LogLevel::Warning, &warning);
+                        self.chat_state.sessions[index].add_message(MessageRole::Notice, warning);
+                    }
+                    self.model_info_cache.insert(model_name, info);
+                }
+                Err(e) => self.add_log(
+                    LogLevel::Warning,
+                    &format!("⚠️ Failed to load details for {model_name}: {e}"),
+                ),
+            }
         }
     }
 
+    pub fn scroll_model_inspector(&mut self, delta: i16) {
+        self.model_inspector.scroll = self.model_inspector.scroll.saturating_add_signed(delta);
+    }
+
     pub async fn on_tick(&mut self) {
         self.animation_tick = self.animation_tick.wrapping_add(1);
         self.sparkle.update();
@@ -293,6 +886,12 @@ impl App {
 
                         self.models = models;
                         self.status.models_loaded = self.models.len();
+
+                        // Drop cached details for models that are gone or re-pulled
+                        let names: Vec<&str> =
+                            self.models.iter().map(|m| m.name.as_str()).collect();
+                        self.model_info_cache
+                            .retain(|name, _| names.contains(&name.as_str()));
                     }
 
                     if let Ok(running) = self.ollama_client.list_running_models().await {
@@ -377,106 +976,389 @@ impl App {
         }
     }
 
-    pub async fn start_pull_model(&mut self) {
-        let model_name = self.pull_model_name.clone();
+    pub fn start_pull_model(&mut self) {
+        let model_name = self.pull_model_name.trim().to_string();
         self.show_pull_dialog = false;
-
-        self.add_log(
-            LogLevel::Info,
-            &format!("🔽 Starting download: {model_name}"),
-        );
-
-        match self.ollama_client.pull_model(&model_name).await {
-            Ok(_) => {
-                self.add_log(
-                    LogLevel::Info,
-                    &format!("✅ Successfully installed: {model_name}"),
-                );
-                self.refresh().await;
-            }
-            Err(e) => {
-                self.add_log(LogLevel::Error, &format!("❌ Download failed: {e}"));
-            }
-        }
+        self.pull_model(model_name);
     }
 
-    pub fn request_delete_model(&mut self) {
-        if let Some(model) = self.models.get(self.selected_model_index) {
-            self.model_to_delete = Some(model.name.clone());
-            self.show_delete_confirmation = true;
+    fn pull_model(&mut self, model_name: String) {
+        if self.downloads.enqueue(&model_name) {
+            self.add_log(LogLevel::Info, &format!("📥 Queued download: {model_name}"));
+        } else {
             self.add_log(
                 LogLevel::Warning,
-                &format!("⚠️ Delete requested: {}", model.name),
+                &format!("⏳ {model_name} is already in the download queue"),
             );
         }
     }
 
-    pub async fn confirm_delete_model(&mut self) {
-        if let Some(model_name) = self.model_to_delete.clone() {
-            self.add_log(LogLevel::Warning, &format!("🗑️ Deleting: {model_name}"));
-            match self.ollama_client.delete_model(&model_name).await {
-                Ok(_) => {
-                    self.add_log(LogLevel::Info, &format!("✅ Deleted: {model_name}"));
-                    self.refresh().await;
+    pub async fn process_downloads(&mut self) {
+        let notices = self.downloads.poll(&self.ollama_client);
+        let mut needs_refresh = false;
+
+        for notice in notices {
+            match notice {
+                DownloadNotice::Started(model) => {
+                    self.add_log(LogLevel::Info, &format!("🔽 Starting download: {model}"));
                 }
-                Err(e) => {
+                DownloadNotice::Completed(model) => {
+                    self.model_info_cache.remove(&model);
+                    self.add_log(
+                        LogLevel::Info,
+                        &format!("✅ Successfully installed: {model}"),
+                    );
+                    needs_refresh = true;
+                }
+                DownloadNotice::Retrying {
+                    model,
+                    attempt,
+                    delay,
+                    error,
+                } => {
+                    self.add_log(
+                        LogLevel::Warning,
+                        &format!(
+                            "🔁 Download of {model} failed ({error}), retry {attempt} in {}s",
+                            delay.as_secs()
+                        ),
+                    );
+                }
+                DownloadNotice::Failed(model, error) => {
                     self.add_log(
                         LogLevel::Error,
-                        &format!("❌ Delete failed for {model_name}: {e}"),
+                        &format!("❌ Download failed for {model}: {error}"),
                     );
                 }
             }
         }
-        self.show_delete_confirmation = false;
-        self.model_to_delete = None;
-    }
 
-    pub fn cancel_delete(&mut self) {
-        self.show_delete_confirmation = false;
-        self.model_to_delete = None;
-        self.add_log(LogLevel::Info, "🚫 Delete cancelled");
+        if needs_refresh {
+            self.refresh().await;
+        }
     }
 
-    fn add_log(&mut self, level: LogLevel, message: &str) {
-        self.logs.push_back(LogEntry {
-            timestamp: Local::now(),
-            level,
-            message: message.to_string(),
-        });
-
-        if self.logs.len() > 1000 {
-            self.logs.pop_front();
+    pub fn cancel_selected_download(&mut self) {
+        if let Some(model) = self.downloads.cancel_selected() {
+            self.add_log(
+                LogLevel::Warning,
+                &format!("🚫 Download cancelled: {model}"),
+            );
         }
     }
 
-    pub fn toggle_models_view(&mut self) {
-        self.models_view_mode = match self.models_view_mode {
-            ModelsViewMode::All => ModelsViewMode::Installed,
-            ModelsViewMode::Installed => ModelsViewMode::Available,
-            ModelsViewMode::Available => ModelsViewMode::All,
-        };
-        self.selected_model_index = 0;
-        self.models_list_state.select(Some(0));
+    pub fn toggle_pause_selected_download(&mut self) {
+        if let Some((model, paused)) = self.downloads.toggle_pause_selected() {
+            if paused {
+                self.add_log(LogLevel::Info, &format!("⏸️ Download paused: {model}"));
+            } else {
+                self.add_log(LogLevel::Info, &format!("▶️ Download resumed: {model}"));
+            }
+        }
     }
 
-    pub fn get_total_models_count(&self) -> usize {
-        match self.models_view_mode {
-            ModelsViewMode::Installed => self.models.len(),
-            ModelsViewMode::Available => self.available_models.len(),
-            ModelsViewMode::All => self.models.len() + self.available_models.len(),
+    pub fn retry_selected_download(&mut self) {
+        if let Some(model) = self.downloads.retry_selected() {
+            self.add_log(LogLevel::Info, &format!("🔁 Retrying download: {model}"));
         }
     }
 
-    pub fn get_api_endpoints() -> Vec<ApiEndpoint> {
-        vec![
-            ApiEndpoint {
-                name: "List Models".to_string(),
-                method: "GET".to_string(),
-                path: "/api/tags".to_string(),
-                description: "List available models".to_string(),
-                example_body: None,
-            },
-            ApiEndpoint {
+    pub fn change_download_concurrency(&mut self, delta: isize) {
+        let target = self.downloads.max_concurrent.saturating_add_signed(delta);
+        self.downloads.set_max_concurrent(target);
+        self.add_log(
+            LogLevel::Info,
+            &format!("⚙️ Parallel downloads: {}", self.downloads.max_concurrent),
+        );
+    }
+
+    /// Open the Modelfile editor, seeded from the selected installed model
+    pub fn open_modelfile_editor(&mut self) {
+        let selected = self.selected_installed_model().map(|m| m.name.clone());
+
+        let keep_existing = self
+            .modelfile_editor
+            .as_ref()
+            .is_some_and(|e| selected.is_none() || e.source_model == selected);
+
+        if !keep_existing {
+            let editor = match &selected {
+                Some(model_name) => {
+                    let base = model_name.split(':').next().unwrap_or(model_name);
+                    let name = format!("{base}-custom");
+                    match self.model_info_cache.get(model_name) {
+                        Some(info) => ModelfileEditor::new(
+                            &modelfile_of(model_name, info),
+                            name,
+                            selected.clone(),
+                        ),
+                        None => {
+                            self.request_model_info(model_name);
+                            ModelfileEditor::loading(name, model_name.clone())
+                        }
+                    }
+                }
+                None => ModelfileEditor::new(
+                    &default_modelfile("llama3.2"),
+                    "my-model".to_string(),
+                    None,
+                ),
+            };
+            self.modelfile_editor = Some(editor);
+        }
+
+        self.current_screen = CurrentScreen::ModelfileEditor;
+    }
+
+    pub fn close_modelfile_editor(&mut self) {
+        self.current_screen = CurrentScreen::Models;
+    }
+
+    pub fn handle_modelfile_editor_key(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+
+        match editor.focus {
+            EditorFocus::Name => match key.code {
+                KeyCode::Char(c) if !c.is_whitespace() => editor.model_name.push(c),
+                KeyCode::Backspace => {
+                    editor.model_name.pop();
+                }
+                KeyCode::Enter => editor.toggle_focus(),
+                _ => {}
+            },
+            // Nothing to edit until the Modelfile is in
+            EditorFocus::Content if editor.loading => {}
+            EditorFocus::Content => {
+                if editor.textarea.input(key) {
+                    editor.validate();
+                }
+            }
+        }
+    }
+
+    /// Open the picker over the repo's `modelfiles/` directory
+    pub fn open_modelfile_library(&mut self) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+        let dir = modelfile_editor::modelfiles_dir();
+        match editor.open_library(&dir) {
+            Ok(()) => {
+                let entries = editor.library.as_ref().map_or(&[][..], |l| &l.entries);
+                let (errors, warnings) = entries.iter().fold((0, 0), |(e, w), entry| {
+                    (
+                        e + entry.count(Severity::Error),
+                        w + entry.count(Severity::Warning),
+                    )
+                });
+                let message = format!(
+                    "📂 Linted {} Modelfile(s) in {}: {errors} error(s), {warnings} warning(s)",
+                    entries.len(),
+                    dir.display()
+                );
+                self.add_log(LogLevel::Info, &message);
+            }
+            Err(e) => {
+                self.add_log(
+                    LogLevel::Error,
+                    &format!("❌ Could not read {}: {e}", dir.display()),
+                );
+            }
+        }
+    }
+
+    pub fn handle_modelfile_library_key(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+        let Some(library) = &mut editor.library else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => library.select_prev(),
+            KeyCode::Down | KeyCode::Char('j') => library.select_next(),
+            KeyCode::Char('d') => library.show_diff = !library.show_diff,
+            KeyCode::Esc => editor.library = None,
+            KeyCode::Enter => {
+                if let Some(path) = editor.load_selected_library_file() {
+                    self.add_log(LogLevel::Info, &format!("📄 Loaded {path}"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn format_modelfile(&mut self) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+        if !editor.format() {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ Fix the Modelfile errors before formatting",
+            );
+        }
+    }
+
+    pub fn create_model_from_editor(&mut self) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+
+        if editor.create_status == CreateStatus::Running || editor.loading {
+            return;
+        }
+        editor.validate();
+        if editor.has_errors() {
+            self.add_log(
+                LogLevel::Error,
+                "❌ Fix the Modelfile errors before creating the model",
+            );
+            return;
+        }
+        if editor.model_name.trim().is_empty() {
+            if editor.focus != EditorFocus::Name {
+                editor.toggle_focus();
+            }
+            self.add_log(LogLevel::Warning, "⚠️ Enter a name for the new model");
+            return;
+        }
+
+        let request = match editor.build_request() {
+            Ok(request) => request,
+            Err(e) => {
+                let message = format!("❌ Can't create {}: {e}", editor.model_name.trim());
+                editor.create_log.clear();
+                editor.create_status = CreateStatus::Failed(e.to_string());
+                self.add_log(LogLevel::Error, &message);
+                return;
+            }
+        };
+        let model_name = request.model.clone();
+        let (tx, rx) = mpsc::channel(100);
+        editor.start_create(rx);
+
+        let ollama_client = self.ollama_client.clone();
+        let error_tx = tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ollama_client.create_model(request, tx).await {
+                let _ = error_tx
+                    .send(crate::api::CreateProgress {
+                        error: Some(e.to_string()),
+                        ..Default::default()
+                    })
+                    .await;
+            }
+        });
+
+        self.add_log(LogLevel::Info, &format!("🛠️ Creating model: {model_name}"));
+    }
+
+    pub async fn process_model_create(&mut self) {
+        let Some(editor) = &mut self.modelfile_editor else {
+            return;
+        };
+        let Some(status) = editor.poll_create() else {
+            return;
+        };
+        let model_name = editor.model_name.trim().to_string();
+
+        match status {
+            CreateStatus::Succeeded => {
+                self.model_info_cache.remove(&model_name);
+                self.add_log(LogLevel::Info, &format!("✅ Created model: {model_name}"));
+                self.refresh().await;
+            }
+            CreateStatus::Failed(e) => {
+                self.add_log(
+                    LogLevel::Error,
+                    &format!("❌ Failed to create {model_name}: {e}"),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    pub fn request_delete_model(&mut self) {
+        if let Some(model) = self.models.get(self.selected_model_index) {
+            self.model_to_delete = Some(model.name.clone());
+            self.show_delete_confirmation = true;
+            self.add_log(
+                LogLevel::Warning,
+                &format!("⚠️ Delete requested: {}", model.name),
+            );
+        }
+    }
+
+    pub async fn confirm_delete_model(&mut self) {
+        if let Some(model_name) = self.model_to_delete.clone() {
+            self.add_log(LogLevel::Warning, &format!("🗑️ Deleting: {model_name}"));
+            match self.ollama_client.delete_model(&model_name).await {
+                Ok(_) => {
+                    self.add_log(LogLevel::Info, &format!("✅ Deleted: {model_name}"));
+                    self.refresh().await;
+                }
+                Err(e) => {
+                    self.add_log(
+                        LogLevel::Error,
+                        &format!("❌ Delete failed for {model_name}: {e}"),
+                    );
+                }
+            }
+        }
+        self.show_delete_confirmation = false;
+        self.model_to_delete = None;
+    }
+
+    pub fn cancel_delete(&mut self) {
+        self.show_delete_confirmation = false;
+        self.model_to_delete = None;
+        self.add_log(LogLevel::Info, "🚫 Delete cancelled");
+    }
+
+    fn add_log(&mut self, level: LogLevel, message: &str) {
+        self.logs.push_back(LogEntry {
+            timestamp: Local::now(),
+            level,
+            message: message.to_string(),
+        });
+
+        if self.logs.len() > 1000 {
+            self.logs.pop_front();
+        }
+    }
+
+    pub fn toggle_models_view(&mut self) {
+        self.models_view_mode = match self.models_view_mode {
+            ModelsViewMode::All => ModelsViewMode::Installed,
+            ModelsViewMode::Installed => ModelsViewMode::Available,
+            ModelsViewMode::Available => ModelsViewMode::All,
+        };
+        self.selected_model_index = 0;
+        self.models_list_state.select(Some(0));
+    }
+
+    pub fn get_total_models_count(&self) -> usize {
+        match self.models_view_mode {
+            ModelsViewMode::Installed => self.models.len(),
+            ModelsViewMode::Available => self.available_models.len(),
+            ModelsViewMode::All => self.models.len() + self.available_models.len(),
+        }
+    }
+
+    pub fn get_api_endpoints() -> Vec<ApiEndpoint> {
+        vec![
+            ApiEndpoint {
+                name: "List Models".to_string(),
+                method: "GET".to_string(),
+                path: "/api/tags".to_string(),
+                description: "List available models".to_string(),
+                example_body: None,
+            },
+            ApiEndpoint {
                 name: "Generate Completion".to_string(),
                 method: "POST".to_string(),
                 path: "/api/generate".to_string(),
@@ -653,22 +1535,17 @@ impl App {
             .network_urls
             .get(self.api_explorer_state.selected_url_index)
         {
-            // Copy to clipboard (macOS)
-            let url_clone = url.clone();
-            tokio::spawn(async move {
-                if let Ok(mut child) = tokio::process::Command::new("pbcopy")
-                    .stdin(std::process::Stdio::piped())
-                    .spawn()
-                {
-                    if let Some(mut stdin) = child.stdin.take() {
-                        use tokio::io::AsyncWriteExt;
-                        let _ = stdin.write_all(url_clone.as_bytes()).await;
-                    }
+            let url = url.clone();
+            match clipboard::copy(&url) {
+                Ok(method) => {
+                    self.api_explorer_state.copied_url = Some(url.clone());
+                    self.add_log(
+                        LogLevel::Info,
+                        &format!("📋 Copied to clipboard ({method}): {url}"),
+                    );
                 }
-            });
-
-            self.api_explorer_state.copied_url = Some(url.clone());
-            self.add_log(LogLevel::Info, &format!("📋 Copied to clipboard: {url}"));
+                Err(e) => self.add_log(LogLevel::Error, &format!("❌ Failed to copy: {e}")),
+            }
         }
     }
 
@@ -762,7 +1639,7 @@ impl App {
         }
     }
 
-    pub async fn install_selected_available_model(&mut self) {
+    pub fn install_selected_available_model(&mut self) {
         if self.models_view_mode == ModelsViewMode::Available
             || self.models_view_mode == ModelsViewMode::All
         {
@@ -776,23 +1653,7 @@ impl App {
                 let available_index = self.selected_model_index - installed_count;
                 if let Some(model) = self.available_models.get(available_index) {
                     let model_name = model.id.clone();
-                    self.add_log(LogLevel::Info, &format!("Installing model: {model_name}"));
-
-                    match self.ollama_client.pull_model(&model_name).await {
-                        Ok(_) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                &format!("Successfully installed: {model_name}"),
-                            );
-                            self.refresh().await;
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                &format!("Failed to install {model_name}: {e}"),
-                            );
-                        }
-                    }
+                    self.pull_model(model_name);
                 }
             }
         }
@@ -800,149 +1661,1399 @@ impl App {
 
     // Chat interface methods
     pub async fn send_chat_message(&mut self) {
-        if self.chat_state.current_session().input_buffer.is_empty() {
+        let session = self.chat_state.current_session();
+        let input = session.input_text();
+        if input.trim().is_empty()
+            && session.attachments.is_empty()
+            && session.attached_files.is_empty()
+        {
             return;
         }
 
-        // Check if we have models available
-        if self.models.is_empty() {
-            self.add_log(
-                LogLevel::Error,
-                "No models available. Please pull a model first!",
-            );
-            self.chat_state.current_session().add_message(
-                MessageRole::System,
-                "❌ No models available. Please go to Models tab and pull a model first!"
-                    .to_string(),
-            );
+        let editing = session.editing_index;
+        if let Some(command) = input.strip_prefix('/').filter(|_| editing.is_none()) {
+            self.chat_state.current_session().set_input("");
+            self.run_chat_command(command.trim()).await;
             return;
         }
 
-        let message = self.chat_state.current_session().input_buffer.clone();
-        self.chat_state.current_session().input_buffer.clear();
-
-        // Add user message
-        self.chat_state
-            .current_session()
-            .add_message(MessageRole::User, message.clone());
-
-        // Start streaming response
-        self.chat_state.current_session().is_streaming = true;
-        self.chat_state.current_session().current_response.clear();
-
-        let model = self.chat_state.current_session().current_model.clone();
-        let model_name = model.clone();
-        let messages = self.chat_state.current_session().get_context_for_api();
-
-        let (tx, rx) = mpsc::channel(100);
-
-        let ollama_client = self.ollama_client.clone();
-        let error_tx = tx.clone();
-
-        // Store receiver
-        self.chat_response_receiver = Some(rx);
+        if !self.can_generate() {
+            return;
+        }
 
-        tokio::spawn(async move {
-            if let Err(e) = ollama_client.chat(&model, messages, tx).await {
-                eprintln!("Chat error: {e}");
-                // Send error as a special response
-                let error_response = ChatResponse {
-                    message: Some(crate::api::ChatMessage {
-                        role: "system".to_string(),
-                        content: format!(
-                            "❌ Error: {e}. Make sure the model '{model}' is installed."
-                        ),
-                    }),
-                    done: Some(true),
-                    eval_count: None,
-                };
-                let _ = error_tx.send(error_response).await;
+        let message = input;
+        let session = self.chat_state.current_session();
+        session.set_input("");
+        match session.editing_index.take() {
+            Some(index) if index < session.messages.len() => {
+                session.resend_edited(index, message);
             }
-        });
+            _ => session.add_message(MessageRole::User, message),
+        }
+        session.attach_pending();
+        let retrieving = self.start_retrieval();
+        self.save_current_session();
+        // Otherwise `process_retrievals` starts the reply once excerpts are in
+        if !retrieving {
+            self.start_generation().await;
+        }
+    }
 
-        self.add_log(LogLevel::Info, &format!("💬 Chatting with {model_name}"));
+    /// Ask the model for the last reply again, keeping the old one as a branch
+    pub async fn regenerate_reply(&mut self) {
+        if !self.can_generate() {
+            return;
+        }
+        if !self.chat_state.current_session().begin_regenerate() {
+            self.add_log(LogLevel::Warning, "⚠️ Nothing to regenerate yet");
+            return;
+        }
+        self.add_log(LogLevel::Info, "🔁 Regenerating the last reply");
+        self.start_generation().await;
     }
 
-    pub async fn process_chat_response(&mut self) {
-        if let Some(receiver) = &mut self.chat_response_receiver {
-            // Process all available messages for smooth streaming
-            let mut messages_processed = 0;
-            const MAX_MESSAGES_PER_BATCH: usize = 10; // Process up to 10 messages per call
+    /// Load the last user message in view into the input; sending it replaces
+    /// that message and starts a new branch from there
+    pub fn edit_message_in_view(&mut self) {
+        let bottom = self.chat_state.view.bottom();
+        let starts = self.chat_state.view.message_starts.clone();
+        let session = self.chat_state.current_session();
+        if session.is_streaming {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ This session is still generating a response",
+            );
+            return;
+        }
 
-            while messages_processed < MAX_MESSAGES_PER_BATCH {
-                match receiver.try_recv() {
-                    Ok(response) => {
-                        messages_processed += 1;
+        let Some(index) = (0..session.messages.len()).rev().find(|&i| {
+            session.messages[i].role == MessageRole::User
+                && starts.get(i).is_none_or(|start| *start < bottom)
+        }) else {
+            self.add_log(LogLevel::Warning, "⚠️ No message to edit");
+            return;
+        };
 
-                        if let Some(message) = response.message {
-                            match message.role.as_str() {
-                                "assistant" => {
-                                    self.chat_state
-                                        .current_session()
-                                        .current_response
-                                        .push_str(&message.content);
-                                }
-                                "system" => {
-                                    // This is an error message
-                                    self.chat_state
-                                        .current_session()
-                                        .add_message(MessageRole::System, message.content);
-                                    self.chat_state.current_session().is_streaming = false;
-                                    self.chat_response_receiver = None;
-                                    return;
-                                }
-                                _ => {}
-                            }
-                        }
+        let content = session.messages[index].content.clone();
+        session.set_input(&content);
+        session.attachments = session.messages[index].images.clone();
+        // Excerpts are looked up again for the edited question
+        session.attached_files = session.messages[index]
+            .files
+            .iter()
+            .filter(|f| f.lines.is_none())
+            .cloned()
+            .collect();
+        session.editing_index = Some(index);
+        self.chat_state.input_mode = InputMode::Editing;
+    }
 
-                        if response.done.unwrap_or(false) {
-                            // Finalize the response
-                            let final_response =
-                                self.chat_state.current_session().current_response.clone();
-                            if !final_response.is_empty() {
-                                self.chat_state
-                                    .current_session()
-                                    .add_message(MessageRole::Assistant, final_response);
-                            }
-                            self.chat_state.current_session().is_streaming = false;
-                            self.chat_state.current_session().current_response.clear();
+    /// Flip to another branch at the last fork in view
+    pub fn switch_branch_in_view(&mut self, delta: isize) {
+        let bottom = self.chat_state.view.bottom();
+        let starts = self.chat_state.view.message_starts.clone();
+        let session = self.chat_state.current_session();
 
-                            // Update token count
-                            if let Some(eval_count) = response.eval_count {
-                                self.chat_state.current_session().total_tokens +=
-                                    eval_count as usize;
-                            }
+        let Some(index) = (0..session.messages.len()).rev().find(|&i| {
+            session.branch_count(i) > 1 && starts.get(i).is_none_or(|start| *start < bottom)
+        }) else {
+            self.add_log(LogLevel::Warning, "⚠️ No branches in view");
+            return;
+        };
 
-                            self.chat_response_receiver = None;
-                            self.add_log(LogLevel::Info, "✅ Response completed");
-                            return;
-                        }
-                    }
-                    Err(mpsc::error::TryRecvError::Empty) => {
-                        // No more messages available
-                        break;
-                    }
-                    Err(mpsc::error::TryRecvError::Disconnected) => {
-                        // Channel disconnected
-                        self.chat_state.current_session().is_streaming = false;
-                        self.chat_response_receiver = None;
-                        self.add_log(LogLevel::Error, "❌ Chat stream disconnected");
-                        break;
-                    }
-                }
-            }
+        session.editing_index = None;
+        if let Some((position, count)) = session.switch_branch(index, delta) {
+            self.save_current_session();
+            self.add_log(LogLevel::Info, &format!("🌿 Branch {position}/{count}"));
         }
     }
 
-    pub fn handle_chat_input(&mut self, c: char) {
-        if self.chat_state.input_mode == InputMode::Editing {
-            self.chat_state.current_session().input_buffer.push(c);
+    /// Whether the active session can start a new response
+    fn can_generate(&mut self) -> bool {
+        if self.chat_state.current_session().is_streaming {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ This session is still generating a response",
+            );
+            return false;
+        }
+        let session_id = self.chat_state.current_session().id.clone();
+        if self.is_retrieving(&session_id) {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ This session is still looking up excerpts",
+            );
+            return false;
+        }
+        if self
+            .file_search
+            .as_ref()
+            .is_some_and(|search| search.session_id == session_id)
+        {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ This session is still reading files to attach",
+            );
+            return false;
+        }
+        if self.tool_batches.iter().any(|b| b.session_id == session_id) {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ This session is waiting on tool calls",
+            );
+            return false;
+        }
+
+        // Check if we have models available
+        if self.models.is_empty() {
+            self.add_log(
+                LogLevel::Error,
+                "No models available. Please pull a model first!",
+            );
+            self.chat_state.current_session().add_message(
+                MessageRole::Notice,
+                "❌ No models available. Please go to Models tab and pull a model first!"
+                    .to_string(),
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Tokens the session's model can attend to: the session's `num_ctx`,
+    /// then the Modelfile's, then the model's native context length
+    pub fn context_window(&self, session: &ChatSession) -> Option<usize> {
+        let info = self.model_info_cache.get(&session.current_model);
+        session
+            .options
+            .num_ctx
+            .and_then(|n| u64::try_from(n).ok())
+            .or_else(|| info.and_then(|i| i.num_ctx()))
+            .or_else(|| info.and_then(|i| i.context_length()))
+            .map(|tokens| tokens as usize)
+    }
+
+    /// Stream a reply to the active session's conversation so far
+    async fn start_generation(&mut self) {
+        self.start_generation_at(self.chat_state.active_session_index)
+            .await;
+    }
+
+    /// Stream a reply to the conversation in session `index`
+    async fn start_generation_at(&mut self, index: usize) {
+        // The context length comes from /api/show. Until it's loaded the
+        // conversation goes uncut, as it does for a model that doesn't say.
+        let model = self.chat_state.sessions[index].current_model.clone();
+        self.request_model_info(&model);
+        let window = self.context_window(&self.chat_state.sessions[index]);
+
+        let missing = self.chat_state.sessions[index].load_images();
+        if !missing.is_empty() {
+            let names: Vec<String> = missing.iter().map(|p| p.display().to_string()).collect();
+            let message = format!(
+                "❌ Can't read attached image(s): {}. Put them back, or edit the message (E) and /image clear to send it without them",
+                names.join(", ")
+            );
+            self.add_log(LogLevel::Error, &message);
+            let session = &mut self.chat_state.sessions[index];
+            session.add_message(MessageRole::Notice, message);
+            self.save_session(index);
+            return;
+        }
+
+        let session = &mut self.chat_state.sessions[index];
+        let stream = ChatStream::spawn(&self.ollama_client, session, window);
+        let session_id = session.id.clone();
+        let dropped = session.context_dropped;
+        let summarizing = stream.summary.is_some();
+        self.chat_streams.insert(session_id, stream);
+
+        if summarizing {
+            self.add_log(
+                LogLevel::Info,
+                "🗜️ Summarizing older messages to fit the context window",
+            );
+        } else if dropped > 0 {
+            self.add_log(
+                LogLevel::Warning,
+                &format!("✂️ Left out {dropped} older message(s) to fit the context window"),
+            );
+        }
+        self.add_log(LogLevel::Info, &format!("💬 Chatting with {model}"));
+    }
+
+    /// Slash commands typed into the chat input
+    async fn run_chat_command(&mut self, command: &str) {
+        let (name, args) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let args = args.trim();
+
+        let result = match name {
+            "export" => self.export_current_session(args),
+            "import" => self.import_sessions(args),
+            "system" => {
+                let prompt = (!args.is_empty()).then(|| args.to_string());
+                self.chat_state
+                    .current_session()
+                    .set_system_prompt(prompt, "/system");
+                self.save_current_session();
+                return;
+            }
+            "set" => self.set_session_option(args),
+            "image" => match args {
+                "" => {
+                    self.open_image_picker();
+                    return;
+                }
+                "clear" => {
+                    self.chat_state.current_session().attachments.clear();
+                    Ok("🖼️ Removed the attached images".to_string())
+                }
+                path => self.attach_image(expand_home(path)),
+            },
+            "file" => match args {
+                "" => Err(anyhow::anyhow!(
+                    "Usage: /file <path or glob>, e.g. /file src/**/*.rs"
+                )),
+                "clear" => {
+                    self.chat_state.current_session().attached_files.clear();
+                    Ok("📄 Removed the attached files".to_string())
+                }
+                pattern => {
+                    let pattern = expand_home(pattern).to_string_lossy().to_string();
+                    match self.start_file_search(move |limit| files::find_files(&pattern, limit)) {
+                        Ok(()) => return,
+                        Err(e) => Err(e),
+                    }
+                }
+            },
+            "dir" => {
+                let (dir, glob) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let dir = expand_home(if dir.is_empty() { "." } else { dir });
+                let glob = Some(glob.trim().to_string()).filter(|g| !g.is_empty());
+                match self.start_file_search(move |limit| {
+                    files::find_in_dir(&dir, glob.as_deref(), limit)
+                }) {
+                    Ok(()) => return,
+                    Err(e) => Err(e),
+                }
+            }
+            "index" if args == "stop" => match self.indexing.take() {
+                Some(job) => {
+                    let root = job.root.display().to_string();
+                    job.stop();
+                    Ok(format!("📚 Stopped indexing {root}"))
+                }
+                None => Err(anyhow::anyhow!("Nothing is being indexed")),
+            },
+            "index" => {
+                let (dir, model) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let dir = if dir.is_empty() { "." } else { dir };
+                self.start_indexing(&expand_home(dir), model.trim())
+            }
+            "rag" => self.set_rag(args),
+            _ => Err(anyhow::anyhow!(
+                "Unknown command /{name}. Try /export <md|json|jsonl> [path], /import <path>, /system [prompt], /set <option> [value], /image [path], /file <path>, /dir <path> [glob], /index <dir> [model] or /rag [dir|off]"
+            )),
+        };
+        self.add_command_notice(result);
+    }
+
+    /// Log a command's outcome and note it in the transcript
+    fn add_command_notice(&mut self, result: anyhow::Result<String>) {
+        self.add_command_notice_at(self.chat_state.active_session_index, result);
+    }
+
+    /// Log a command's outcome and note it in session `index`
+    fn add_command_notice_at(&mut self, index: usize, result: anyhow::Result<String>) {
+        let notice = match result {
+            Ok(message) => {
+                self.add_log(LogLevel::Info, &message);
+                message
+            }
+            Err(e) => {
+                let message = format!("❌ {e}");
+                self.add_log(LogLevel::Error, &message);
+                message
+            }
+        };
+        self.chat_state.sessions[index].add_message(MessageRole::Notice, notice);
+    }
+
+    /// Attach an image file to the next message
+    fn attach_image(&mut self, path: PathBuf) -> anyhow::Result<String> {
+        if !path.is_file() {
+            anyhow::bail!("{} is not a file", path.display());
+        }
+        if !is_image(&path) {
+            anyhow::bail!(
+                "{} is not an image ({})",
+                path.display(),
+                IMAGE_EXTENSIONS.join(", ")
+            );
+        }
+        // Saved sessions refer to the file, so keep a path that works from anywhere
+        let path = path.canonicalize()?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut message = format!("🖼️ Attached {name} to the next message");
+
+        let session = self.chat_state.current_session();
+        session.encode_image(&path)?;
+        if !session.attachments.contains(&path) {
+            session.attachments.push(path.clone());
+        }
+        let session_id = session.id.clone();
+        let model = session.current_model.clone();
+        match self.model_info_cache.get(&model) {
+            Some(info) => {
+                if let Some(warning) = vision_warning(&model, info) {
+                    message.push_str(&format!(". {warning}"));
+                }
+            }
+            // Warned about once the capabilities are in
+            None => {
+                self.request_model_info(&model);
+                self.vision_check = Some((session_id, model));
+            }
+        }
+        Ok(message)
+    }
+
+    /// Walk and read files for `/file` or `/dir` in the background;
+    /// `process_file_search` attaches them
+    fn start_file_search(
+        &mut self,
+        find: impl FnOnce(usize) -> anyhow::Result<files::Found> + Send + 'static,
+    ) -> anyhow::Result<()> {
+        if self.file_search.is_some() {
+            anyhow::bail!("Still reading the files from the last /file or /dir");
+        }
+        let session_id = self.chat_state.current_session().id.clone();
+        self.file_search = Some(FileSearch::start(session_id, find));
+        self.add_log(LogLevel::Info, "📄 Reading files to attach");
+        Ok(())
+    }
+
+    /// Attach what a `/file` or `/dir` search read, once it's done
+    pub fn process_file_search(&mut self) {
+        let Some(result) = self.file_search.as_mut().and_then(FileSearch::poll) else {
+            return;
+        };
+        let search = self.file_search.take().expect("polled search");
+        // Files for a deleted session have nowhere to go
+        let Some(index) = self.chat_state.session_index(&search.session_id) else {
+            return;
+        };
+        let result = result.and_then(|collected| self.attach_files(index, collected));
+        self.add_command_notice_at(index, result);
+    }
+
+    /// Attach files to session `index`'s next message, as long as they fit
+    /// in the part of the context window left for the prompt. Until the
+    /// model's context length is known they're attached unchecked.
+    fn attach_files(
+        &mut self,
+        index: usize,
+        collected: files::Collected,
+    ) -> anyhow::Result<String> {
+        let files::Collected {
+            files: found,
+            skipped,
+            unreadable,
+        } = collected;
+
+        let model = self.chat_state.sessions[index].current_model.clone();
+        self.request_model_info(&model);
+        let session = &self.chat_state.sessions[index];
+        let window = self.context_window(session);
+        let tokens: usize = found.iter().map(FileContext::tokens).sum();
+        let attached: usize = session
+            .attached_files
+            .iter()
+            .filter(|f| !found.iter().any(|new| new.path == f.path))
+            .map(FileContext::tokens)
+            .sum();
+        let conversation = session.plan_context(None).estimated_tokens;
+
+        let mut message = format!("📄 Attached {} file(s), ~{tokens} tokens", found.len());
+        if skipped > 0 {
+            message.push_str(&format!(" ({skipped} binary or oversized file(s) skipped)"));
+        }
+        if unreadable > 0 {
+            message.push_str(&format!(" ({unreadable} unreadable entries skipped)"));
+        }
+        if let Some(window) = window {
+            // The same budget as `plan_context`: a quarter is kept for the reply
+            let budget = window - window / 4;
+            if attached + tokens > budget {
+                anyhow::bail!(
+                    "{} file(s) need ~{tokens} tokens, but {model} has room for ~{} in its {window}-token context. Attach fewer files or raise num_ctx with /set",
+                    found.len(),
+                    budget.saturating_sub(attached)
+                );
+            }
+            if conversation + attached + tokens > budget {
+                message.push_str("; older messages will be left out to make room");
+            }
+        }
+
+        let session = &mut self.chat_state.sessions[index];
+        session
+            .attached_files
+            .retain(|f| !found.iter().any(|new| new.path == f.path));
+        session.attached_files.extend(found);
+        Ok(message)
+    }
+
+    /// Index `dir` with an embedding model in the background; the current
+    /// session answers from it once the index is ready
+    fn start_indexing(&mut self, dir: &Path, model: &str) -> anyhow::Result<String> {
+        if let Some(job) = &self.indexing {
+            anyhow::bail!(
+                "Already indexing {} ({}/{} chunks)",
+                job.root.display(),
+                job.done,
+                job.total
+            );
+        }
+        let root = rag::resolve_root(dir)?;
+        let model = Some(model.to_string()).filter(|m| !m.is_empty());
+        let message = match &model {
+            Some(model) => format!("📚 Indexing {} with {model}", root.display()),
+            None => format!(
+                "📚 Indexing {} with the first embedding model installed",
+                root.display()
+            ),
+        };
+        let session_id = self.chat_state.current_session().id.clone();
+        let installed = self.models.iter().map(|m| m.name.clone()).collect();
+        self.indexing = Some(IndexJob::start(
+            &self.ollama_client,
+            session_id,
+            root,
+            model,
+            installed,
+        ));
+        Ok(message)
+    }
+
+    /// `/rag`: show the session's indexed folder, or turn retrieval on or off
+    fn set_rag(&mut self, args: &str) -> anyhow::Result<String> {
+        let session = self.chat_state.current_session();
+        let result = match args {
+            "" => match &session.rag {
+                Some(source) => Ok(format!(
+                    "📚 Answering from {} ({}). /rag off to stop",
+                    source.root.display(),
+                    source.model
+                )),
+                None => Ok("📚 Retrieval is off. /index <dir> to index a folder".to_string()),
+            },
+            "off" => {
+                session.rag = None;
+                Ok("📚 Retrieval is off".to_string())
+            }
+            dir => {
+                // `/rag <dir> [model]` reuses an index made earlier
+                let (dir, model) = dir.split_once(char::is_whitespace).unwrap_or((dir, ""));
+                let root = rag::resolve_root(&expand_home(dir))?;
+                let model = model.trim();
+                let source = self.find_index(&root, model).ok_or_else(|| {
+                    anyhow::anyhow!("{} isn't indexed yet; use /index", root.display())
+                })?;
+                let message = format!(
+                    "📚 Answering from {} ({})",
+                    source.root.display(),
+                    source.model
+                );
+                self.chat_state.current_session().rag = Some(source);
+                Ok(message)
+            }
+        };
+        self.save_current_session();
+        result
+    }
+
+    /// An existing index of `root`, made with `model` or with any model
+    fn find_index(&self, root: &Path, model: &str) -> Option<RagSource> {
+        if !model.is_empty() {
+            let source = RagSource {
+                root: root.to_path_buf(),
+                model: model.to_string(),
+            };
+            return source.index_path().exists().then_some(source);
+        }
+        let mut models: Vec<String> = self.models.iter().map(|m| m.name.clone()).collect();
+        models.extend(
+            self.chat_state
+                .sessions
+                .iter()
+                .filter_map(|s| s.rag.as_ref())
+                .filter(|source| source.root == root)
+                .map(|source| source.model.clone()),
+        );
+        models.into_iter().find_map(|model| {
+            let source = RagSource {
+                root: root.to_path_buf(),
+                model,
+            };
+            source.index_path().exists().then_some(source)
+        })
+    }
+
+    /// Hand the finished index to the session that asked for it
+    pub fn process_indexing(&mut self) {
+        let Some(result) = self.indexing.as_mut().and_then(IndexJob::poll) else {
+            return;
+        };
+        let job = self.indexing.take().expect("polled job");
+        let message = match result {
+            Ok((index, stats)) => {
+                let mut message = format!(
+                    "📚 Indexed {} with {}: {} files, {} chunks ({} new or changed, {} unchanged, {} removed",
+                    job.root.display(),
+                    index.source.model,
+                    stats.files,
+                    index.chunk_count(),
+                    stats.embedded,
+                    stats.unchanged,
+                    stats.removed
+                );
+                if stats.skipped > 0 {
+                    message.push_str(&format!(
+                        ", {} binary, oversized or unreadable skipped",
+                        stats.skipped
+                    ));
+                }
+                message.push(')');
+                self.add_log(LogLevel::Info, &message);
+                let source = index.source.clone();
+                self.rag_indexes
+                    .insert(source.index_path(), Arc::new(index));
+                if let Some(session) = self
+                    .chat_state
+                    .sessions
+                    .iter_mut()
+                    .find(|s| s.id == job.session_id)
+                {
+                    session.rag = Some(source);
+                    message.push_str(". Questions in this chat are now looked up there");
+                }
+                message
+            }
+            Err(e) => {
+                let message = format!("❌ Indexing {} failed: {e}", job.root.display());
+                self.add_log(LogLevel::Error, &message);
+                message
+            }
+        };
+        if let Some(index) = self
+            .chat_state
+            .sessions
+            .iter()
+            .position(|s| s.id == job.session_id)
+        {
+            self.chat_state.sessions[index].add_message(MessageRole::Notice, message);
+            self.save_session(index);
+        }
+    }
+
+    /// Look the question just sent up in the session's index, if it has
+    /// one. Returns whether a lookup started.
+    fn start_retrieval(&mut self) -> bool {
+        let session = &self.chat_state.sessions[self.chat_state.active_session_index];
+        let Some(source) = session.rag.clone() else {
+            return false;
+        };
+        let Some(question) = session
+            .messages
+            .back()
+            .map(|m| m.content.clone())
+            .filter(|q| !q.trim().is_empty())
+        else {
+            return false;
+        };
+
+        let session_id = session.id.clone();
+        let cached = self.rag_indexes.get(&source.index_path()).cloned();
+        self.add_log(
+            LogLevel::Info,
+            &format!("📚 Looking the question up in {}", source.root.display()),
+        );
+        self.retrievals.push(Retrieval::start(
+            &self.ollama_client,
+            session_id,
+            source,
+            cached,
+            question,
+        ));
+        true
+    }
+
+    /// Attach the excerpts found for each question and send it. A failed
+    /// lookup is logged and the question goes without excerpts.
+    pub async fn process_retrievals(&mut self) {
+        let mut position = 0;
+        while position < self.retrievals.len() {
+            let Some(result) = self.retrievals[position].poll() else {
+                position += 1;
+                continue;
+            };
+            let retrieval = self.retrievals.remove(position);
+            // Excerpts for a deleted session have nowhere to go
+            let Some(index) = self.chat_state.session_index(&retrieval.session_id) else {
+                continue;
+            };
+
+            match result {
+                Ok((rag_index, excerpts)) => {
+                    self.rag_indexes
+                        .insert(rag_index.source.index_path(), rag_index);
+                    let count = excerpts.len();
+                    let session = &mut self.chat_state.sessions[index];
+                    if let Some(message) = session
+                        .messages
+                        .back_mut()
+                        .filter(|m| m.role == MessageRole::User)
+                    {
+                        message.files.extend(excerpts);
+                    }
+                    self.add_log(
+                        LogLevel::Info,
+                        &format!("📚 Found {count} excerpt(s) for the question"),
+                    );
+                }
+                Err(e) => self.add_log(
+                    LogLevel::Warning,
+                    &format!("⚠️ Retrieval failed, sending without excerpts: {e:#}"),
+                ),
+            }
+            self.save_session(index);
+            self.start_generation_at(index).await;
+        }
+    }
+
+    /// Whether the session's question is still being looked up
+    pub fn is_retrieving(&self, session_id: &str) -> bool {
+        self.retrievals.iter().any(|r| r.session_id == session_id)
+    }
+
+    pub fn open_image_picker(&mut self) {
+        let picker = &mut self.image_picker;
+        let dir = if picker.dir.as_os_str().is_empty() {
+            std::env::current_dir().unwrap_or_default()
+        } else {
+            picker.dir.clone()
+        };
+        match picker.load(dir) {
+            Ok(()) => picker.visible = true,
+            Err(e) => self.add_log(LogLevel::Error, &format!("❌ Can't list images: {e}")),
+        }
+    }
+
+    pub fn handle_image_picker_key(&mut self, key: KeyEvent) {
+        let picker = &mut self.image_picker;
+        match key.code {
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down if picker.selected + 1 < picker.entries.len() => picker.selected += 1,
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(parent) = picker.dir.parent().map(Path::to_path_buf) {
+                    if let Err(e) = picker.load(parent) {
+                        self.add_log(LogLevel::Error, &format!("❌ Can't list images: {e}"));
+                    }
+                }
+            }
+            KeyCode::Enter | KeyCode::Right => {
+                let Some((name, is_dir)) = picker.entries.get(picker.selected).cloned() else {
+                    return;
+                };
+                let path = picker.dir.join(name);
+                if is_dir {
+                    if let Err(e) = picker.load(path) {
+                        self.add_log(LogLevel::Error, &format!("❌ Can't list images: {e}"));
+                    }
+                } else if key.code == KeyCode::Enter {
+                    picker.visible = false;
+                    let result = self.attach_image(path);
+                    self.add_command_notice(result);
+                }
+            }
+            KeyCode::Esc => picker.visible = false,
+            _ => {}
+        }
+    }
+
+    /// `/set <option> [value]`; without a value the option is reset
+    fn set_session_option(&mut self, args: &str) -> anyhow::Result<String> {
+        let (key, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        if key.is_empty() {
+            let keys: Vec<_> = ChatSession::option_keys().collect();
+            anyhow::bail!("Usage: /set <option> [value]. Options: {}", keys.join(", "));
+        }
+        if !ChatSession::option_keys().any(|k| k == key) {
+            anyhow::bail!("Unknown option `{key}`");
+        }
+
+        let session = self.chat_state.current_session();
+        session.set_option(key, value)?;
+        let message = match session.option_value(key) {
+            Some(value) => format!("⚙️ {key} = {value}"),
+            None => format!("⚙️ {key} reset to the model default"),
+        };
+        self.save_current_session();
+        Ok(message)
+    }
+
+    fn export_current_session(&mut self, args: &str) -> anyhow::Result<String> {
+        let (format_name, path) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let format_name = if format_name.is_empty() {
+            "md"
+        } else {
+            format_name
+        };
+        let format = ExportFormat::parse(format_name).ok_or_else(|| {
+            anyhow::anyhow!("Unknown export format `{format_name}` (md, json, jsonl)")
+        })?;
+
+        let session = self.chat_state.current_session();
+        let path = match path.trim() {
+            "" => export::default_export_path(session, format),
+            path => expand_home(path),
+        };
+        let content = export::export_session(session, format)?;
+        storage::write_atomic(&path, content.as_bytes())?;
+        Ok(format!("📤 Exported session to {}", path.display()))
+    }
+
+    fn import_sessions(&mut self, args: &str) -> anyhow::Result<String> {
+        if args.is_empty() {
+            anyhow::bail!("Usage: /import <path to .md, .json or .jsonl>");
+        }
+        let path = expand_home(args);
+        let default_model = self.chat_state.current_session().current_model.clone();
+        let sessions = export::import_sessions(&path, &default_model)?;
+
+        let count = sessions.len();
+        for session in sessions {
+            self.chat_state.open_session(session);
+            self.save_current_session();
+        }
+        Ok(format!(
+            "📥 Imported {count} session(s) from {}",
+            path.display()
+        ))
+    }
+
+    /// Drain every in-flight stream into the session that started it
+    pub async fn process_chat_responses(&mut self) {
+        let session_ids: Vec<String> = self.chat_streams.keys().cloned().collect();
+        for session_id in session_ids {
+            self.process_chat_stream(&session_id);
+        }
+    }
+
+    fn process_chat_stream(&mut self, session_id: &str) {
+        let Some(index) = self.chat_state.session_index(session_id) else {
+            // The session was deleted; aborting drops the HTTP stream
+            if let Some(stream) = self.chat_streams.remove(session_id) {
+                stream.handle.abort();
+            }
+            return;
+        };
+        let Some(stream) = self.chat_streams.get_mut(session_id) else {
+            return;
+        };
+        let session = &mut self.chat_state.sessions[index];
+
+        let (end, summary_log) = stream.receive(session);
+        let title = session.display_title();
+        if let Some((level, message)) = summary_log {
+            self.add_log(level, &message);
+        }
+        let Some(end) = end else {
+            return;
+        };
+        self.chat_streams.remove(session_id);
+
+        match end {
+            StreamEnd::Completed => {
+                self.add_log(LogLevel::Info, &format!("✅ Response completed: {title}"));
+                self.queue_tool_calls(index);
+            }
+            StreamEnd::Failed(error) => {
+                self.chat_state.sessions[index].add_message(MessageRole::Notice, error);
+            }
+            StreamEnd::Disconnected => {
+                self.add_log(
+                    LogLevel::Error,
+                    &format!("❌ Chat stream disconnected: {title}"),
+                );
+            }
+        }
+        self.save_session(index);
+    }
+
+    /// Stop the active session's generation. Aborting the task drops the HTTP
+    /// stream, which makes Ollama stop generating; the partial reply is kept.
+    pub fn stop_generation(&mut self) {
+        let session_id = self.chat_state.current_session().id.clone();
+        if let Some(position) = self
+            .retrievals
+            .iter()
+            .position(|r| r.session_id == session_id)
+        {
+            self.retrievals.remove(position).stop();
+            self.add_log(LogLevel::Warning, "⏹️ Stopped looking up excerpts");
+            return;
+        }
+        let Some(stream) = self.chat_streams.remove(&session_id) else {
+            return;
+        };
+        stream.stop(self.chat_state.current_session());
+        self.save_current_session();
+        self.add_log(LogLevel::Warning, "⏹️ Generation stopped");
+    }
+
+    pub fn generating_count(&self) -> usize {
+        self.chat_streams.len()
+    }
+
+    /// Ask about the tool calls in session `index`'s latest reply, if it made any
+    fn queue_tool_calls(&mut self, index: usize) {
+        let session = &self.chat_state.sessions[index];
+        let Some(reply) = session.messages.back() else {
+            return;
+        };
+        if reply.role != MessageRole::Assistant || reply.tool_calls.is_empty() {
+            return;
+        }
+        let calls = reply.tool_calls.clone();
+        let title = session.display_title();
+        let session_id = session.id.clone();
+        self.add_log(
+            LogLevel::Info,
+            &format!("🛠️ {title} wants to run {} tool call(s)", calls.len()),
+        );
+        self.tool_batches.push(ToolBatch {
+            session_id,
+            results: vec![None; calls.len()],
+            calls,
+            next: 0,
+            running: Vec::new(),
+        });
+    }
+
+    /// The first batch with a call waiting for the user's answer
+    pub fn pending_tool_batch(&self) -> Option<&ToolBatch> {
+        self.tool_batches.iter().find(|b| b.pending().is_some())
+    }
+
+    /// y runs the tool call, n declines it, Esc declines the rest of the reply's calls
+    pub fn handle_tool_approval_key(&mut self, key: KeyEvent) {
+        let Some(batch) = self.tool_batches.iter_mut().find(|b| b.pending().is_some()) else {
+            return;
+        };
+        let name = batch.calls[batch.next].function.name.clone();
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                let (tx, rx) = oneshot::channel();
+                let call = batch.calls[batch.next].clone();
+                tokio::spawn(async move {
+                    let _ = tx.send(tools::run(call).await);
+                });
+                batch.running.push((batch.next, rx));
+                batch.next += 1;
+                self.add_log(LogLevel::Info, &format!("🛠️ Running {name}"));
+            }
+            KeyCode::Char('n') => {
+                batch.results[batch.next] = Some(TOOL_DECLINED.to_string());
+                batch.next += 1;
+                self.add_log(LogLevel::Warning, &format!("🚫 Declined {name}"));
+            }
+            KeyCode::Esc => {
+                for result in &mut batch.results[batch.next..] {
+                    *result = Some(TOOL_DECLINED.to_string());
+                }
+                batch.next = batch.calls.len();
+                self.add_log(LogLevel::Warning, "🚫 Declined the remaining tool calls");
+            }
+            _ => {}
+        }
+    }
+
+    /// Collect finished tool calls. When a reply's calls are all answered the
+    /// results are added to its session and the model continues from them.
+    pub async fn process_tool_batches(&mut self) {
+        let sessions = &self.chat_state;
+        self.tool_batches
+            .retain(|b| sessions.session_index(&b.session_id).is_some());
+        for batch in &mut self.tool_batches {
+            batch
+                .running
+                .retain_mut(|(index, receiver)| match receiver.try_recv() {
+                    Ok(output) => {
+                        batch.results[*index] = Some(output);
+                        false
+                    }
+                    Err(oneshot::error::TryRecvError::Empty) => true,
+                    Err(oneshot::error::TryRecvError::Closed) => {
+                        batch.results[*index] = Some("Error: the tool stopped unexpectedly".into());
+                        false
+                    }
+                });
+        }
+
+        while let Some(position) = self.tool_batches.iter().position(ToolBatch::is_finished) {
+            let batch = self.tool_batches.remove(position);
+            // Results for a deleted session have nowhere to go
+            let Some(index) = self.chat_state.session_index(&batch.session_id) else {
+                continue;
+            };
+            let session = &mut self.chat_state.sessions[index];
+            for (call, result) in batch.calls.into_iter().zip(batch.results) {
+                session.add_tool_result(call.function.name, result.unwrap_or_default());
+            }
+            self.save_session(index);
+            self.start_generation_at(index).await;
+        }
+    }
+
+    /// Keys typed while composing a message. Enter sends; Shift+Enter and
+    /// Alt+Enter insert a newline; Up/Down walk the history from the first or
+    /// last line and move the cursor otherwise.
+    pub async fn handle_chat_input_key(&mut self, key: KeyEvent) {
+        let session = self.chat_state.current_session();
+        let (row, _) = session.input.cursor();
+        let last_row = session.input.lines().len() - 1;
+
+        match key.code {
+            KeyCode::Enter
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+            {
+                session.reset_history_navigation();
+                session.input.insert_newline();
+            }
+            KeyCode::Enter => {
+                session.reset_history_navigation();
+                self.send_chat_message().await;
+            }
+            KeyCode::Esc => {
+                session.reset_history_navigation();
+                // Abandon an edit rather than leave it half-applied
+                if session.editing_index.take().is_some() {
+                    session.set_input("");
+                    session.attachments.clear();
+                    session.attached_files.clear();
+                }
+                self.toggle_chat_input_mode();
+            }
+            KeyCode::PageUp => self.chat_state.view.page(-1),
+            KeyCode::PageDown => self.chat_state.view.page(1),
+            KeyCode::Up if row == 0 => session.navigate_history_up(),
+            KeyCode::Down if row == last_row && session.history_index.is_some() => {
+                session.navigate_history_down();
+            }
+            _ => {
+                if session.input.input(key) {
+                    session.reset_history_navigation();
+                }
+            }
+        }
+    }
+
+    /// Keys typed into the `/` search prompt. Matches update as you type.
+    pub fn handle_transcript_search_key(&mut self, key: KeyEvent) {
+        let view = &mut self.chat_state.view;
+        let Some(search) = &mut view.search else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                search.query.push(c);
+                search.current = None;
+                search.jump = true;
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.current = None;
+                search.jump = true;
+            }
+            KeyCode::Enter if search.query.is_empty() => view.search = None,
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Esc => view.search = None,
+            KeyCode::Up => view.next_match(-1),
+            KeyCode::Down => view.next_match(1),
+            _ => {}
+        }
+    }
+
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.current_screen == CurrentScreen::Compare {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => self.compare.scroll_back += 3,
+                MouseEventKind::ScrollDown => {
+                    self.compare.scroll_back = self.compare.scroll_back.saturating_sub(3);
+                }
+                _ => {}
+            }
+            return;
+        }
+        if self.current_screen != CurrentScreen::Chat {
+            return;
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.chat_state.view.scroll_by(-3),
+            MouseEventKind::ScrollDown => self.chat_state.view.scroll_by(3),
+            _ => {}
+        }
+    }
+
+    /// Open the compare screen, choosing models first if none are set up
+    pub fn open_compare(&mut self) {
+        self.current_screen = CurrentScreen::Compare;
+        if self.compare.columns.is_empty() {
+            self.open_compare_picker();
+        }
+    }
+
+    pub fn open_compare_picker(&mut self) {
+        let models = self.models.iter().map(|m| m.name.clone()).collect();
+        self.compare.picker = Some(ModelPicker::new(models, self.compare.models()));
+    }
+
+    pub fn handle_compare_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.compare.picker else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down if picker.selected + 1 < picker.models.len() => picker.selected += 1,
+            KeyCode::Char(' ') if !picker.toggle_selected() => {
+                self.add_log(
+                    LogLevel::Warning,
+                    &format!(
+                        "⚠️ Compare up to {} models at once",
+                        crate::compare::MAX_MODELS
+                    ),
+                );
+            }
+            KeyCode::Enter if picker.chosen.len() < 2 => {
+                self.add_log(LogLevel::Warning, "⚠️ Pick at least two models to compare");
+            }
+            KeyCode::Enter => {
+                let chosen = std::mem::take(&mut picker.chosen);
+                self.compare.picker = None;
+                self.set_compare_models(chosen);
+            }
+            KeyCode::Esc => {
+                self.compare.picker = None;
+                if self.compare.columns.is_empty() {
+                    self.current_screen = CurrentScreen::Chat;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep the columns of models that are still chosen and add new ones. New
+    /// columns take the active chat session's system prompt and options so
+    /// every model gets the same request.
+    fn set_compare_models(&mut self, models: Vec<String>) {
+        let mut old = std::mem::take(&mut self.compare.columns);
+        let template = self
+            .chat_state
+            .sessions
+            .get(self.chat_state.active_session_index);
+
+        for model in &models {
+            let column = match old.iter().position(|c| &c.session.current_model == model) {
+                Some(index) => old.remove(index),
+                None => {
+                    let mut session = ChatSession::new(model.clone());
+                    if let Some(template) = template {
+                        session.system_prompt = template.system_prompt.clone();
+                        session.options = template.options.clone();
+                        session.keep_alive = template.keep_alive.clone();
+                    }
+                    CompareColumn {
+                        session,
+                        stream: None,
+                        queued: false,
+                    }
+                }
+            };
+            self.compare.columns.push(column);
+        }
+        for column in old {
+            if let Some(stream) = column.stream {
+                stream.handle.abort();
+            }
+        }
+        self.add_log(
+            LogLevel::Info,
+            &format!("🆚 Comparing {}", models.join(", ")),
+        );
+    }
+
+    pub fn handle_compare_key(&mut self, key: KeyEvent) {
+        if self.compare.editing {
+            match key.code {
+                KeyCode::Enter
+                    if key
+                        .modifiers
+                        .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+                {
+                    self.compare.input.insert_newline();
+                }
+                KeyCode::Enter => self.send_compare_prompt(),
+                KeyCode::Esc => self.compare.editing = false,
+                _ => {
+                    self.compare.input.input(key);
+                }
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.stop_compare();
+            }
+            KeyCode::Char('i' | 'e') => self.compare.editing = true,
+            KeyCode::Char('m') => self.open_compare_picker(),
+            KeyCode::Char('p') => {
+                self.compare.sequential = !self.compare.sequential;
+                let mode = if self.compare.sequential {
+                    "one model at a time"
+                } else {
+                    "all models in parallel"
+                };
+                self.add_log(LogLevel::Info, &format!("🆚 Compare runs {mode}"));
+            }
+            KeyCode::Char('t') => {
+                self.compare.multi_turn = !self.compare.multi_turn;
+                let mode = if self.compare.multi_turn {
+                    "continue each conversation"
+                } else {
+                    "start fresh"
+                };
+                self.add_log(LogLevel::Info, &format!("🆚 New prompts {mode}"));
+            }
+            KeyCode::Char('c') => self.clear_compare(),
+            KeyCode::PageUp => self.compare.scroll_back += 10,
+            KeyCode::PageDown => {
+                self.compare.scroll_back = self.compare.scroll_back.saturating_sub(10);
+            }
+            KeyCode::End => self.compare.scroll_back = 0,
+            KeyCode::Esc => self.current_screen = CurrentScreen::Chat,
+            _ => {}
+        }
+    }
+
+    /// Send the prompt to every column, all at once or one after another
+    fn send_compare_prompt(&mut self) {
+        let prompt = self.compare.input_text();
+        if prompt.trim().is_empty() {
+            return;
+        }
+        if self.compare.is_running() {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ Still waiting for the models to finish answering",
+            );
+            return;
+        }
+
+        self.compare.clear_input();
+        self.compare.scroll_back = 0;
+        let multi_turn = self.compare.multi_turn;
+        for column in &mut self.compare.columns {
+            if !multi_turn {
+                column.session.clear_session();
+            }
+            column
+                .session
+                .add_message(MessageRole::User, prompt.clone());
+            column.queued = true;
+        }
+
+        if self.compare.sequential {
+            if let Some(next) = self.compare.next_queued() {
+                self.start_compare_column(next);
+            }
+        } else {
+            for index in 0..self.compare.columns.len() {
+                self.start_compare_column(index);
+            }
+        }
+        self.add_log(
+            LogLevel::Info,
+            &format!("🆚 Asking {} models", self.compare.columns.len()),
+        );
+    }
+
+    fn start_compare_column(&mut self, index: usize) {
+        let model = self.compare.columns[index].session.current_model.clone();
+        self.request_model_info(&model);
+        let window = self.context_window(&self.compare.columns[index].session);
+
+        let column = &mut self.compare.columns[index];
+        column.queued = false;
+        column.stream = Some(ChatStream::spawn(
+            &self.ollama_client,
+            &mut column.session,
+            window,
+        ));
+    }
+
+    /// Drain the compare streams and, in sequential mode, start the next model
+    pub fn process_compare_responses(&mut self) {
+        let mut logs = Vec::new();
+        for column in &mut self.compare.columns {
+            let Some(stream) = &mut column.stream else {
+                continue;
+            };
+            let (end, summary_log) = stream.receive(&mut column.session);
+            logs.extend(summary_log);
+            let Some(end) = end else {
+                continue;
+            };
+            column.stream = None;
+            match end {
+                StreamEnd::Completed => {}
+                StreamEnd::Failed(error) => {
+                    column.session.add_message(MessageRole::Notice, error);
+                }
+                StreamEnd::Disconnected => logs.push((
+                    LogLevel::Error,
+                    format!(
+                        "❌ Compare stream disconnected: {}",
+                        column.session.current_model
+                    ),
+                )),
+            }
+        }
+        for (level, message) in logs {
+            self.add_log(level, &message);
+        }
+
+        if let Some(next) = self.compare.next_queued() {
+            self.start_compare_column(next);
+        }
+    }
+
+    pub fn stop_compare(&mut self) {
+        if !self.compare.is_running() {
+            return;
+        }
+        for column in &mut self.compare.columns {
+            column.queued = false;
+            if let Some(stream) = column.stream.take() {
+                stream.stop(&mut column.session);
+            }
+        }
+        self.add_log(LogLevel::Warning, "⏹️ Comparison stopped");
+    }
+
+    fn clear_compare(&mut self) {
+        self.stop_compare();
+        for column in &mut self.compare.columns {
+            column.session.clear_session();
+        }
+        self.compare.scroll_back = 0;
+    }
+
+    /// Tool compatibility matrix; runs the cases the first time it's opened
+    pub fn open_tool_check(&mut self) {
+        self.current_screen = CurrentScreen::ToolCheck;
+        if self.tool_check.models.is_empty() && !self.tool_check.is_running() {
+            self.start_tool_check();
+        }
+    }
+
+    fn start_tool_check(&mut self) {
+        if self.models.is_empty() {
+            self.add_log(LogLevel::Warning, "⚠️ No models installed to check");
+            return;
+        }
+        let models: Vec<String> = self.models.iter().map(|m| m.name.clone()).collect();
+        self.add_log(
+            LogLevel::Info,
+            &format!(
+                "🧰 Checking tool calling on {} model(s), {} prompts each",
+                models.len(),
+                tool_check::CASES.len()
+            ),
+        );
+        self.tool_check.start(&self.ollama_client, models);
+    }
+
+    pub fn handle_tool_check_key(&mut self, key: KeyEvent) {
+        let check = &mut self.tool_check;
+        match key.code {
+            KeyCode::Up => check.selected = check.selected.saturating_sub(1),
+            KeyCode::Down if check.selected + 1 < check.models.len() => check.selected += 1,
+            KeyCode::Char('r') if !check.is_running() => self.start_tool_check(),
+            KeyCode::Char('c')
+                if key.modifiers.contains(KeyModifiers::CONTROL) && check.is_running() =>
+            {
+                check.stop();
+                self.add_log(LogLevel::Warning, "⏹️ Tool check stopped");
+            }
+            KeyCode::Esc => self.current_screen = CurrentScreen::Models,
+            _ => {}
+        }
+    }
+
+    /// Collect tool check results; a finished run is written out as a JSON report
+    pub fn process_tool_check(&mut self) {
+        if !self.tool_check.poll() {
+            return;
+        }
+        let Some(started) = self.tool_check.started else {
+            return;
+        };
+        let path = storage::data_dir().join("tool-reports").join(format!(
+            "tool-check-{}.json",
+            started.format("%Y%m%d-%H%M%S")
+        ));
+        match storage::write_json(&path, &self.tool_check.report()) {
+            Ok(()) => {
+                self.add_log(
+                    LogLevel::Info,
+                    &format!("🧰 Tool check finished, report saved to {}", path.display()),
+                );
+                self.tool_check.report_path = Some(path);
+            }
+            Err(e) => self.add_log(
+                LogLevel::Error,
+                &format!("❌ Failed to save the tool report: {e}"),
+            ),
         }
     }
 
-    pub fn handle_chat_backspace(&mut self) {
-        if self.chat_state.input_mode == InputMode::Editing {
-            self.chat_state.current_session().input_buffer.pop();
+    /// Bracketed paste: insert the text as-is instead of replaying it as keys
+    pub fn handle_paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.current_screen {
+            CurrentScreen::Chat if self.chat_state.input_mode == InputMode::Editing => {
+                let session = self.chat_state.current_session();
+                session.reset_history_navigation();
+                session.input.insert_str(&text);
+            }
+            CurrentScreen::Compare if self.compare.editing => {
+                self.compare.input.insert_str(&text);
+            }
+            CurrentScreen::ModelfileEditor => {
+                if let Some(editor) = &mut self.modelfile_editor {
+                    if editor.focus == EditorFocus::Content && editor.textarea.insert_str(&text) {
+                        editor.validate();
+                    }
+                }
+            }
+            _ => {}
         }
     }
 
@@ -959,18 +3070,20 @@ impl App {
         let model_names: Vec<String> = self.models.iter().map(|m| m.name.clone()).collect();
 
         if !model_names.is_empty() {
-            self.chat_state = ChatState::new(model_names.clone());
+            self.chat_state.available_models = model_names.clone();
+            self.chat_state.selected_model_index = 0;
             self.add_log(
                 LogLevel::Info,
                 &format!("Chat initialized with {} models", model_names.len()),
             );
 
-            // Set the first available model as current
-            if let Some(first_model) = model_names.first() {
-                self.chat_state.current_session().current_model = first_model.clone();
+            // Restored sessions keep their model if it's still installed
+            let session = self.chat_state.current_session();
+            if !model_names.contains(&session.current_model) {
+                session.current_model = model_names[0].clone();
                 self.add_log(
                     LogLevel::Info,
-                    &format!("🤖 Chat model selected: {first_model}"),
+                    &format!("🤖 Chat model selected: {}", model_names[0]),
                 );
             }
         } else {
@@ -981,6 +3094,555 @@ impl App {
         }
     }
 
+    pub fn new_chat_session(&mut self) {
+        self.chat_state.new_session();
+        self.save_open_sessions();
+    }
+
+    pub fn switch_chat_session(&mut self, delta: isize) {
+        self.chat_state.switch_session(delta);
+        self.save_open_sessions();
+    }
+
+    pub fn move_chat_session(&mut self, delta: isize) {
+        self.chat_state.move_session(delta);
+        self.save_open_sessions();
+    }
+
+    pub fn duplicate_chat_session(&mut self) {
+        self.chat_state.duplicate_session();
+        self.save_current_session();
+        self.add_log(LogLevel::Info, "📑 Duplicated chat session");
+    }
+
+    /// Close the active session. It stays on disk and can be reopened from the browser.
+    pub fn close_chat_session(&mut self) {
+        if self.chat_state.current_session().is_streaming {
+            self.add_log(
+                LogLevel::Warning,
+                "⚠️ Wait for the response to finish before closing this session",
+            );
+            return;
+        }
+        let index = self.chat_state.active_session_index;
+        if let Some(closed) = self.chat_state.remove_session(index) {
+            self.save_open_sessions();
+            self.add_log(
+                LogLevel::Info,
+                &format!("📕 Closed session: {}", closed.display_title()),
+            );
+        }
+    }
+
+    pub fn clear_chat_session(&mut self) {
+        self.chat_state.current_session().clear_session();
+        self.save_current_session();
+    }
+
+    pub fn select_chat_model(&mut self) {
+        self.chat_state.select_model();
+        self.save_current_session();
+    }
+
+    pub fn save_current_session(&mut self) {
+        self.save_session(self.chat_state.active_session_index);
+    }
+
+    /// Persist a session; sessions without messages are removed from disk
+    pub fn save_session(&mut self, index: usize) {
+        let Some(session) = self.chat_state.sessions.get(index) else {
+            return;
+        };
+        let result = if session.messages.is_empty() {
+            self.session_store.delete(&session.id)
+        } else {
+            self.session_store.save(session)
+        };
+        if let Err(e) = result {
+            self.add_log(LogLevel::Error, &format!("❌ Failed to save session: {e}"));
+        }
+        self.save_open_sessions();
+    }
+
+    fn save_open_sessions(&mut self) {
+        let open = OpenSessions {
+            ids: self
+                .chat_state
+                .sessions
+                .iter()
+                .map(|s| s.id.clone())
+                .collect(),
+            active_id: self
+                .chat_state
+                .sessions
+                .get(self.chat_state.active_session_index)
+                .map(|s| s.id.clone()),
+        };
+        if let Err(e) = self.session_store.save_open_sessions(&open) {
+            self.add_log(LogLevel::Error, &format!("❌ Failed to save sessions: {e}"));
+        }
+    }
+
+    pub fn toggle_session_browser(&mut self) {
+        self.session_browser.visible = !self.session_browser.visible;
+        if self.session_browser.visible {
+            self.session_browser.sessions = self.session_store.list();
+            self.session_browser.selected = 0;
+            self.session_browser.rename_buffer = None;
+            self.session_browser.confirm_delete = false;
+        }
+    }
+
+    pub fn handle_session_browser_key(&mut self, key: KeyEvent) {
+        if let Some(buffer) = &mut self.session_browser.rename_buffer {
+            match key.code {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Enter => {
+                    let title = buffer.trim().to_string();
+                    self.session_browser.rename_buffer = None;
+                    self.rename_selected_session(title);
+                }
+                KeyCode::Esc => self.session_browser.rename_buffer = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if self.session_browser.confirm_delete {
+            self.session_browser.confirm_delete = false;
+            if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                self.delete_selected_session();
+            }
+            return;
+        }
+
+        let browser = &mut self.session_browser;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                browser.selected = browser.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if browser.selected + 1 < browser.sessions.len() => {
+                browser.selected += 1;
+            }
+            KeyCode::Enter => self.reopen_selected_session(),
+            KeyCode::Char('r') => {
+                if let Some(summary) = browser.selected_session() {
+                    browser.rename_buffer = Some(summary.title.clone());
+                }
+            }
+            KeyCode::Char('d' | 'D') if browser.selected_session().is_some() => {
+                browser.confirm_delete = true;
+            }
+            KeyCode::Esc | KeyCode::Char('b') => self.toggle_session_browser(),
+            _ => {}
+        }
+    }
+
+    pub fn toggle_preset_picker(&mut self) {
+        let picker = &mut self.preset_picker;
+        picker.visible = !picker.visible;
+        picker.name_buffer = None;
+        picker.confirm_delete = false;
+        picker.selected = picker
+            .selected
+            .min(self.presets.presets.len().saturating_sub(1));
+    }
+
+    pub fn handle_preset_picker_key(&mut self, key: KeyEvent) {
+        if let Some(buffer) = &mut self.preset_picker.name_buffer {
+            match key.code {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Enter => {
+                    let name = buffer.trim().to_string();
+                    self.preset_picker.name_buffer = None;
+                    self.save_prompt_as_preset(&name);
+                }
+                KeyCode::Esc => self.preset_picker.name_buffer = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if self.preset_picker.confirm_delete {
+            self.preset_picker.confirm_delete = false;
+            if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                match self.presets.remove(self.preset_picker.selected) {
+                    Ok(Some(preset)) => {
+                        self.preset_picker.selected = self
+                            .preset_picker
+                            .selected
+                            .min(self.presets.presets.len().saturating_sub(1));
+                        self.add_log(
+                            LogLevel::Info,
+                            &format!("🗑️ Deleted preset: {}", preset.name),
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        self.add_log(LogLevel::Error, &format!("❌ Failed to save presets: {e}"))
+                    }
+                }
+            }
+            return;
+        }
+
+        let picker = &mut self.preset_picker;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.selected = picker.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j')
+                if picker.selected + 1 < self.presets.presets.len() =>
+            {
+                picker.selected += 1;
+            }
+            KeyCode::Enter => {
+                if let Some(preset) = self.presets.presets.get(picker.selected).cloned() {
+                    picker.visible = false;
+                    self.chat_state.current_session().set_system_prompt(
+                        Some(preset.prompt),
+                        &format!("preset \"{}\"", preset.name),
+                    );
+                    self.save_current_session();
+                }
+            }
+            KeyCode::Char('c') => {
+                picker.visible = false;
+                self.chat_state
+                    .current_session()
+                    .set_system_prompt(None, "");
+                self.save_current_session();
+            }
+            KeyCode::Char('s') => {
+                if self.chat_state.current_session().system_prompt.is_some() {
+                    picker.name_buffer = Some(String::new());
+                } else {
+                    self.add_log(
+                        LogLevel::Warning,
+                        "⚠️ This session has no system prompt to save",
+                    );
+                }
+            }
+            KeyCode::Char('d') if !self.presets.presets.is_empty() => {
+                picker.confirm_delete = true;
+            }
+            KeyCode::Esc | KeyCode::Char('P') => self.toggle_preset_picker(),
+            _ => {}
+        }
+    }
+
+    pub fn toggle_code_block_picker(&mut self) {
+        let picker = &mut self.code_block_picker;
+        if picker.visible {
+            picker.visible = false;
+            return;
+        }
+
+        let blocks: Vec<CodeBlock> = self
+            .chat_state
+            .current_session()
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Assistant)
+            .flat_map(|m| markdown::code_blocks(&m.content))
+            .collect();
+        if blocks.is_empty() {
+            self.add_log(LogLevel::Warning, "⚠️ No code blocks in this session");
+            return;
+        }
+
+        let picker = &mut self.code_block_picker;
+        picker.visible = true;
+        picker.path_buffer = None;
+        // Most of the time the block you want is the one just answered
+        picker.selected = blocks.len() - 1;
+        picker.blocks = blocks;
+    }
+
+    pub fn handle_code_block_picker_key(&mut self, key: KeyEvent) {
+        if let Some(buffer) = &mut self.code_block_picker.path_buffer {
+            match key.code {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Enter => {
+                    let path = buffer.trim().to_string();
+                    self.code_block_picker.path_buffer = None;
+                    if !path.is_empty() {
+                        self.write_selected_code_block(&path);
+                    }
+                }
+                KeyCode::Esc => self.code_block_picker.path_buffer = None,
+                _ => {}
+            }
+            return;
+        }
+
+        let picker = &mut self.code_block_picker;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.selected = picker.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if picker.selected + 1 < picker.blocks.len() => {
+                picker.selected += 1;
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < picker.blocks.len() {
+                    picker.selected = index;
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('y') => self.copy_selected_code_block(),
+            KeyCode::Char('w') => {
+                if let Some(block) = picker.blocks.get(picker.selected) {
+                    picker.path_buffer = Some(format!(
+                        "snippet-{}.{}",
+                        picker.selected + 1,
+                        block.extension()
+                    ));
+                }
+            }
+            KeyCode::Esc => picker.visible = false,
+            _ => {}
+        }
+    }
+
+    fn copy_selected_code_block(&mut self) {
+        let picker = &mut self.code_block_picker;
+        let Some(block) = picker.blocks.get(picker.selected) else {
+            return;
+        };
+        let number = picker.selected + 1;
+        match clipboard::copy(&block.code) {
+            Ok(method) => {
+                picker.visible = false;
+                let lines = block.code.lines().count();
+                self.add_log(
+                    LogLevel::Info,
+                    &format!("📋 Copied code block #{number} ({lines} lines) via {method}"),
+                );
+            }
+            Err(e) => self.add_log(LogLevel::Error, &format!("❌ Failed to copy: {e}")),
+        }
+    }
+
+    fn write_selected_code_block(&mut self, path: &str) {
+        let picker = &mut self.code_block_picker;
+        let Some(block) = picker.blocks.get(picker.selected) else {
+            return;
+        };
+        let number = picker.selected + 1;
+        let path = expand_home(path);
+
+        // Never clobber an existing file from a one-key shortcut
+        let result = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| {
+                use std::io::Write;
+                file.write_all(block.code.as_bytes())
+            });
+        match result {
+            Ok(()) => {
+                picker.visible = false;
+                self.add_log(
+                    LogLevel::Info,
+                    &format!("💾 Wrote code block #{number} to {}", path.display()),
+                );
+            }
+            Err(e) => self.add_log(
+                LogLevel::Error,
+                &format!("❌ Failed to write {}: {e}", path.display()),
+            ),
+        }
+    }
+
+    pub fn toggle_options_panel(&mut self) {
+        let panel = &mut self.options_panel;
+        panel.visible = !panel.visible;
+        panel.edit_buffer = None;
+        panel.error = None;
+    }
+
+    pub fn handle_options_panel_key(&mut self, key: KeyEvent) {
+        let keys: Vec<&str> = ChatSession::option_keys().collect();
+        let option = keys[self.options_panel.selected.min(keys.len() - 1)];
+
+        if let Some(buffer) = &mut self.options_panel.edit_buffer {
+            match key.code {
+                KeyCode::Char(c) => buffer.push(c),
+                KeyCode::Backspace => {
+                    buffer.pop();
+                }
+                KeyCode::Enter => {
+                    let value = buffer.clone();
+                    self.apply_option(option, &value);
+                }
+                KeyCode::Esc => {
+                    self.options_panel.edit_buffer = None;
+                    self.options_panel.error = None;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let panel = &mut self.options_panel;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                panel.selected = panel.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if panel.selected + 1 < keys.len() => {
+                panel.selected += 1;
+            }
+            KeyCode::Enter => {
+                panel.error = None;
+                panel.edit_buffer = Some(
+                    self.chat_state
+                        .current_session()
+                        .option_value(option)
+                        .unwrap_or_default(),
+                );
+            }
+            KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x') => {
+                self.apply_option(option, "");
+            }
+            KeyCode::Char('R') => {
+                let session = self.chat_state.current_session();
+                session.options = GenerationOptions::default();
+                session.keep_alive = None;
+                self.save_current_session();
+                self.add_log(LogLevel::Info, "⚙️ Reset all generation options");
+            }
+            KeyCode::Esc | KeyCode::Char('o') => self.toggle_options_panel(),
+            _ => {}
+        }
+    }
+
+    fn apply_option(&mut self, key: &str, value: &str) {
+        match self.chat_state.current_session().set_option(key, value) {
+            Ok(()) => {
+                self.options_panel.edit_buffer = None;
+                self.options_panel.error = None;
+                self.save_current_session();
+            }
+            Err(e) => self.options_panel.error = Some(e.to_string()),
+        }
+    }
+
+    fn save_prompt_as_preset(&mut self, name: &str) {
+        let Some(prompt) = self.chat_state.current_session().system_prompt.clone() else {
+            return;
+        };
+        if name.is_empty() {
+            return;
+        }
+        match self.presets.upsert(name, &prompt) {
+            Ok(()) => {
+                self.preset_picker.selected = self
+                    .presets
+                    .presets
+                    .iter()
+                    .position(|p| p.name == name)
+                    .unwrap_or(0);
+                self.add_log(LogLevel::Info, &format!("💾 Saved preset: {name}"));
+            }
+            Err(e) => self.add_log(LogLevel::Error, &format!("❌ Failed to save presets: {e}")),
+        }
+    }
+
+    fn reopen_selected_session(&mut self) {
+        let Some(summary) = self.session_browser.selected_session().cloned() else {
+            return;
+        };
+
+        if let Some(index) = self.chat_state.session_index(&summary.id) {
+            self.chat_state.active_session_index = index;
+        } else {
+            match self.session_store.load(&summary.id) {
+                Ok(session) => self.chat_state.open_session(session),
+                Err(e) => {
+                    self.add_log(LogLevel::Error, &format!("❌ Failed to open session: {e}"));
+                    return;
+                }
+            }
+        }
+
+        self.session_browser.visible = false;
+        self.save_open_sessions();
+        self.add_log(
+            LogLevel::Info,
+            &format!("📂 Reopened session: {}", summary.title),
+        );
+    }
+
+    fn rename_selected_session(&mut self, title: String) {
+        let Some(summary) = self.session_browser.selected_session().cloned() else {
+            return;
+        };
+        let title = (!title.is_empty()).then_some(title);
+
+        let result = match self.chat_state.session_index(&summary.id) {
+            Some(index) => {
+                self.chat_state.sessions[index].title = title;
+                self.session_store.save(&self.chat_state.sessions[index])
+            }
+            None => self
+                .session_store
+                .load(&summary.id)
+                .and_then(|mut session| {
+                    session.title = title;
+                    self.session_store.save(&session)
+                }),
+        };
+
+        match result {
+            Ok(()) => {
+                let selected = self.session_browser.selected;
+                self.session_browser.sessions = self.session_store.list();
+                self.session_browser.selected =
+                    selected.min(self.session_browser.sessions.len().saturating_sub(1));
+            }
+            Err(e) => self.add_log(LogLevel::Error, &format!("❌ Rename failed: {e}")),
+        }
+    }
+
+    fn delete_selected_session(&mut self) {
+        let Some(summary) = self.session_browser.selected_session().cloned() else {
+            return;
+        };
+
+        if let Err(e) = self.session_store.delete(&summary.id) {
+            self.add_log(LogLevel::Error, &format!("❌ Delete failed: {e}"));
+            return;
+        }
+        if let Some(stream) = self.chat_streams.remove(&summary.id) {
+            stream.handle.abort();
+        }
+        if let Some(index) = self.chat_state.session_index(&summary.id) {
+            self.chat_state.remove_session(index);
+        }
+        self.save_open_sessions();
+
+        let browser = &mut self.session_browser;
+        browser.sessions.retain(|s| s.id != summary.id);
+        browser.selected = browser
+            .selected
+            .min(browser.sessions.len().saturating_sub(1));
+        self.add_log(
+            LogLevel::Info,
+            &format!("🗑️ Deleted session: {}", summary.title),
+        );
+    }
+
     async fn update_system_memory(&mut self) {
         // Get system memory info (works on macOS and Linux)
         #[cfg(target_os = "macos")]
@@ -1054,3 +3716,53 @@ impl App {
         }
     }
 }
+
+/// The Modelfile to start editing from for an installed model
+fn modelfile_of(model_name: &str, info: &ModelInfo) -> String {
+    if info.modelfile.trim().is_empty() {
+        default_modelfile(model_name)
+    } else {
+        info.modelfile.clone()
+    }
+}
+
+fn default_modelfile(base_model: &str) -> String {
+    format!(
+        r#"# Custom model built with llamaloop
+FROM {base_model}
+
+SYSTEM """You are a helpful assistant."""
+
+PARAMETER temperature 0.7
+PARAMETER num_ctx 8192
+"#
+    )
+}
+
+/// Expand a leading `~/` so paths typed in chat work like they do in a shell
+fn expand_home(path: &str) -> std::path::PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => std::path::PathBuf::from(home).join(rest),
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+/// Reopen the sessions that were open last time, or start a fresh one
+fn restore_chat_state(store: &SessionStore) -> ChatState {
+    let mut chat_state = ChatState::new(Vec::new());
+    let open = store.load_open_sessions();
+    let sessions: Vec<ChatSession> = open
+        .ids
+        .iter()
+        .filter_map(|id| store.load(id).ok())
+        .collect();
+
+    if !sessions.is_empty() {
+        chat_state.active_session_index = open
+            .active_id
+            .and_then(|id| sessions.iter().position(|s| s.id == id))
+            .unwrap_or(sessions.len() - 1);
+        chat_state.sessions = sessions;
+    }
+    chat_state
+}
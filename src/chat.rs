@@ -1,51 +1,354 @@
+use crate::api::{GenerationMetrics, GenerationOptions, ToolCall};
+use crate::files::FileContext;
+use crate::markdown::{MarkdownStream, RenderCache};
+use crate::rag::RagSource;
+use crate::theme::TokyoNight;
+use anyhow::Context;
+use base64::Engine;
 use chrono::{DateTime, Local};
+use ratatui::style::Style;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tui_textarea::TextArea;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: MessageRole,
     pub content: String,
     pub timestamp: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool, // Generation was stopped before the model finished
+    /// The other continuations from this point, each starting with its own
+    /// version of this message. Only the active message of a fork holds them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<Vec<ChatMessage>>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub branch: usize, // Position of this continuation among its siblings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<GenerationMetrics>, // How the reply was generated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>, // Functions the assistant asked us to run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>, // Tool that produced a `Tool` message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PathBuf>, // Attached image files, read when first sent after a launch
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileContext>, // Attached text files, sent ahead of the message
+}
+
+impl ChatMessage {
+    /// The message as sent to `/api/chat`; notices stay local. Images come
+    /// from `encoded`, filled in by `ChatSession::load_images`.
+    fn to_api(&self, encoded: &HashMap<PathBuf, String>) -> Option<serde_json::Value> {
+        let role = match self.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::Tool => "tool",
+            MessageRole::Notice => return None,
+        };
+        let mut message = serde_json::json!({ "role": role, "content": self.api_content() });
+        if !self.tool_calls.is_empty() {
+            message["tool_calls"] = serde_json::json!(self.tool_calls);
+        }
+        if let Some(name) = &self.tool_name {
+            message["tool_name"] = serde_json::json!(name);
+        }
+        if !self.images.is_empty() {
+            let images: Vec<&String> = self
+                .images
+                .iter()
+                .filter_map(|path| encoded.get(path))
+                .collect();
+            message["images"] = serde_json::json!(images);
+        }
+        Some(message)
+    }
+
+    /// The text the model sees: attached files, then the message
+    pub fn api_content(&self) -> String {
+        if self.files.is_empty() {
+            return self.content.clone();
+        }
+        let mut content: Vec<String> = self.files.iter().map(FileContext::render).collect();
+        if self.files.iter().any(|f| f.lines.is_some()) {
+            content.push(RETRIEVAL_NOTE.to_string());
+        }
+        content.push(self.content.clone());
+        content.join("\n\n")
+    }
+
+    fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.api_content())
+            + self
+                .tool_calls
+                .iter()
+                .map(|call| estimate_tokens(&call.function.arguments.to_string()))
+                .sum::<usize>()
+    }
+}
+
+/// Sent after excerpts found by retrieval, so the reply says where it looked
+const RETRIEVAL_NOTE: &str = "The excerpts above were retrieved from the user's files for this \
+     question. Use the ones that help, and cite each one you use by its path and lines, \
+     like [src/main.rs:10-42].";
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// A conversation cut at `index`, waiting for the message that starts the new branch
+#[derive(Debug, Clone)]
+struct Fork {
+    index: usize,
+    siblings: Vec<Vec<ChatMessage>>, // Every continuation, including the one being replaced
+    replaced: usize,                 // Which sibling was active before the fork
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum MessageRole {
-    System,
+    /// Local status shown in the transcript, never sent to the model.
+    /// Older session files stored these as "System".
+    #[serde(alias = "System")]
+    Notice,
     User,
     Assistant,
+    /// Output of a tool call, sent back so the assistant can continue
+    Tool,
 }
 
-#[derive(Debug, Clone)]
+/// What to do when a conversation outgrows the model's context window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Leave out the oldest turns
+    #[default]
+    Truncate,
+    /// Keep the first exchange, leave out the turns after it
+    Pin,
+    /// Ask the model to condense the older turns into a summary
+    Summarize,
+}
+
+impl ContextStrategy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "truncate" => Some(Self::Truncate),
+            "pin" => Some(Self::Pin),
+            "summarize" => Some(Self::Summarize),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Truncate => "truncate",
+            Self::Pin => "pin",
+            Self::Summarize => "summarize",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Model-written summary standing in for the first `covers` messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSummary {
+    pub text: String,
+    pub covers: usize,
+}
+
+/// The messages for one request, fitted to the context window
+#[derive(Debug)]
+pub struct ContextPlan {
+    pub messages: Vec<serde_json::Value>,
+    pub estimated_tokens: usize,
+    pub dropped: usize, // Messages left out to make room
+    pub summarize: Option<SummaryRequest>,
+}
+
+/// Older turns to condense before sending. Until the summary arrives the
+/// plan's `messages` are the truncated fallback.
+#[derive(Debug)]
+pub struct SummaryRequest {
+    pub covers: usize, // Messages the new summary replaces
+    transcript: String,
+    head: Vec<serde_json::Value>,
+    recent: Vec<serde_json::Value>,
+}
+
+impl SummaryRequest {
+    /// Messages asking the model for the summary
+    pub fn prompt(&self) -> Vec<serde_json::Value> {
+        vec![
+            serde_json::json!({
+                "role": "system",
+                "content": "Summarize the conversation below so it can replace it in a chat \
+                    history. Keep facts, names, decisions, open questions and code \
+                    identifiers. Be concise and write only the summary.",
+            }),
+            serde_json::json!({ "role": "user", "content": self.transcript }),
+        ]
+    }
+
+    /// The request to send once the summary is known
+    pub fn messages_with(&self, summary: &str) -> Vec<serde_json::Value> {
+        let mut messages = self.head.clone();
+        messages.push(summary_message(summary));
+        messages.extend(self.recent.iter().cloned());
+        messages
+    }
+}
+
+fn summary_message(summary: &str) -> serde_json::Value {
+    serde_json::json!({
+        "role": "system",
+        "content": format!("Summary of the earlier conversation:\n{summary}"),
+    })
+}
+
+/// Rough token count: about four characters per token, plus the chat
+/// template's per-message overhead
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4) + 4
+}
+
+/// How many of `turns` fit in `budget`, counting back from the newest.
+/// The newest turn is always kept.
+fn newest_fitting(turns: &[(usize, serde_json::Value, usize)], budget: usize) -> usize {
+    let mut used = 0;
+    let kept = turns
+        .iter()
+        .rev()
+        .take_while(|(_, _, tokens)| {
+            used += tokens;
+            used <= budget
+        })
+        .count();
+    kept.max(1).min(turns.len())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatSession {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
     pub messages: VecDeque<ChatMessage>,
     pub current_model: String,
+    #[serde(default)]
+    pub system_prompt: Option<String>, // Sent as the first message of every request
+    #[serde(default, skip_serializing_if = "GenerationOptions::is_empty")]
+    pub options: GenerationOptions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>, // How long Ollama keeps the model loaded, e.g. "10m" or "-1"
+    #[serde(default, skip_serializing_if = "ContextStrategy::is_default")]
+    pub context_strategy: ContextStrategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_summary: Option<ContextSummary>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub context_used: usize, // prompt_eval_count + eval_count of the last reply
+    #[serde(skip)]
+    pub context_estimate: usize, // Estimated size of the last request
+    #[serde(skip)]
+    pub context_dropped: usize, // Messages left out of the last request
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tools: bool, // Offer the local tools to the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rag: Option<RagSource>, // Indexed folder to look each question up in
+    #[serde(skip)]
     pub is_streaming: bool,
+    #[serde(skip)]
     pub current_response: String,
-    pub input_buffer: String,
+    #[serde(skip)]
+    pub current_tool_calls: Vec<ToolCall>, // Tool calls streamed with the current response
+    #[serde(skip)]
+    pub response_markdown: MarkdownStream, // Rendered prefix of current_response
+    #[serde(skip, default = "new_input")]
+    pub input: TextArea<'static>, // Message being composed
     pub total_tokens: usize,
     pub session_started: DateTime<Local>,
+    #[serde(default)]
     pub message_history: VecDeque<String>, // Store previous messages for navigation
-    pub history_index: Option<usize>,      // Current position in history
-    pub temp_input: String,                // Temporary storage when navigating history
+    #[serde(skip)]
+    pub history_index: Option<usize>, // Current position in history
+    #[serde(skip)]
+    pub temp_input: String, // Temporary storage when navigating history
+    #[serde(skip)]
+    pub editing_index: Option<usize>, // User message being edited for resend
+    #[serde(skip)]
+    pub attachments: Vec<PathBuf>, // Images to send with the next message
+    #[serde(skip)]
+    pub attached_files: Vec<FileContext>, // Files to send with the next message
+    #[serde(skip)]
+    encoded_images: HashMap<PathBuf, String>, // Base64 of each attached image, read once
+    #[serde(skip)]
+    pending_fork: Option<Fork>, // Set while regenerating a reply
 }
 
 impl ChatSession {
     pub fn new(model: String) -> Self {
         Self {
+            id: new_session_id(),
+            title: None,
             messages: VecDeque::new(),
             current_model: model,
+            system_prompt: None,
+            options: GenerationOptions::default(),
+            keep_alive: None,
             is_streaming: false,
             current_response: String::new(),
-            input_buffer: String::new(),
+            current_tool_calls: Vec::new(),
+            response_markdown: MarkdownStream::default(),
+            input: new_input(),
             total_tokens: 0,
             session_started: Local::now(),
             message_history: VecDeque::new(),
             history_index: None,
             temp_input: String::new(),
+            editing_index: None,
+            attachments: Vec::new(),
+            attached_files: Vec::new(),
+            encoded_images: HashMap::new(),
+            pending_fork: None,
+            context_strategy: ContextStrategy::default(),
+            context_summary: None,
+            context_used: 0,
+            context_estimate: 0,
+            context_dropped: 0,
+            tools: false,
+            rag: None,
         }
     }
 
+    /// The user-given title, or a preview of the first question
+    pub fn display_title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+        self.messages
+            .iter()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| {
+                let first_line = m.content.lines().next().unwrap_or_default().trim();
+                let preview: String = first_line.chars().take(40).collect();
+                if first_line.chars().count() > 40 {
+                    format!("{preview}…")
+                } else {
+                    preview
+                }
+            })
+            .unwrap_or_else(|| "New chat".to_string())
+    }
+
+    pub fn last_activity(&self) -> DateTime<Local> {
+        self.messages
+            .back()
+            .map(|m| m.timestamp)
+            .unwrap_or(self.session_started)
+    }
+
     pub fn add_message(&mut self, role: MessageRole, content: String) {
         // Add to history if it's a user message
         if role == MessageRole::User {
@@ -60,13 +363,197 @@ impl ChatSession {
             role,
             content,
             timestamp: Local::now(),
+            interrupted: false,
+            branches: Vec::new(),
+            branch: 0,
+            metrics: None,
+            tool_calls: Vec::new(),
+            tool_name: None,
+            images: Vec::new(),
+            files: Vec::new(),
         };
         self.messages.push_back(message);
+    }
+
+    /// Move the pending images and files onto the message just added
+    pub fn attach_pending(&mut self) {
+        let images = std::mem::take(&mut self.attachments);
+        let files = std::mem::take(&mut self.attached_files);
+        if let Some(message) = self.messages.back_mut() {
+            message.images = images;
+            message.files = files;
+        }
+    }
+
+    /// Read and encode an image for sending, unless that was done already
+    pub fn encode_image(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !self.encoded_images.contains_key(path) {
+            let bytes =
+                std::fs::read(path).with_context(|| format!("can't read {}", path.display()))?;
+            let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+            self.encoded_images.insert(path.to_path_buf(), data);
+        }
+        Ok(())
+    }
+
+    /// Encode every image in the conversation that isn't yet, e.g. after a
+    /// restart. Returns the ones that can no longer be read.
+    pub fn load_images(&mut self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .messages
+            .iter()
+            .flat_map(|m| &m.images)
+            .cloned()
+            .collect();
+        paths.dedup();
+        paths
+            .into_iter()
+            .filter(|path| self.encode_image(path).is_err())
+            .collect()
+    }
+
+    /// Record what a tool call returned, to be sent back to the model
+    pub fn add_tool_result(&mut self, name: String, content: String) {
+        self.add_message(MessageRole::Tool, content);
+        if let Some(message) = self.messages.back_mut() {
+            message.tool_name = Some(name);
+        }
+    }
+
+    /// Move the streamed text into the transcript and leave the streaming state
+    pub fn finish_response(&mut self, interrupted: bool, metrics: Option<GenerationMetrics>) {
+        self.is_streaming = false;
+        let content = std::mem::take(&mut self.current_response);
+        self.response_markdown = MarkdownStream::default();
+        let fork = self.pending_fork.take();
+        let tool_calls = std::mem::take(&mut self.current_tool_calls);
+        if content.is_empty() && tool_calls.is_empty() {
+            // Nothing came back, so put the reply we were replacing back
+            if let Some(fork) = fork {
+                self.restore_fork(fork);
+            }
+            return;
+        }
+
+        self.add_message(MessageRole::Assistant, content);
+        if let Some(message) = self.messages.back_mut() {
+            message.interrupted = interrupted;
+            message.metrics = metrics;
+            message.tool_calls = tool_calls;
+        }
+        if let Some(fork) = fork {
+            self.attach_fork(fork);
+        }
+    }
+
+    /// Cut the conversation at `index`, keeping what followed as a sibling branch
+    fn fork_at(&mut self, index: usize) -> Fork {
+        // A summary of messages that are about to change no longer applies
+        if self
+            .context_summary
+            .as_ref()
+            .is_some_and(|summary| index < summary.covers)
+        {
+            self.context_summary = None;
+        }
+        let mut replaced: Vec<ChatMessage> = self.messages.split_off(index).into();
+        let (mut siblings, position) = match replaced.first_mut() {
+            Some(first) => (std::mem::take(&mut first.branches), first.branch),
+            None => (Vec::new(), 0),
+        };
+        let position = position.min(siblings.len());
+        if !replaced.is_empty() {
+            siblings.insert(position, replaced);
+        }
+        Fork {
+            index,
+            siblings,
+            replaced: position,
+        }
+    }
+
+    /// Make the message at the fork point the newest sibling
+    fn attach_fork(&mut self, fork: Fork) {
+        if let Some(first) = self.messages.get_mut(fork.index) {
+            first.branch = fork.siblings.len();
+            first.branches = fork.siblings;
+        }
+    }
+
+    fn restore_fork(&mut self, mut fork: Fork) {
+        self.messages.truncate(fork.index);
+        if fork.replaced >= fork.siblings.len() {
+            return;
+        }
+        let mut active = fork.siblings.remove(fork.replaced);
+        if let Some(first) = active.first_mut() {
+            first.branches = fork.siblings;
+            first.branch = fork.replaced;
+        }
+        self.messages.extend(active);
+    }
+
+    /// Prepare to generate the last reply again. The old reply becomes a
+    /// sibling branch once the new one arrives. Returns false when there is
+    /// nothing to answer.
+    pub fn begin_regenerate(&mut self) -> bool {
+        let Some(last) = self
+            .messages
+            .iter()
+            .rposition(|m| m.role != MessageRole::Notice)
+        else {
+            return false;
+        };
+        match self.messages[last].role {
+            MessageRole::Assistant => {
+                self.pending_fork = Some(self.fork_at(last));
+                true
+            }
+            // The last question never got an answer; just ask again
+            _ => true,
+        }
+    }
+
+    /// Replace the user message at `index` and drop what followed into a sibling branch
+    pub fn resend_edited(&mut self, index: usize, content: String) {
+        let fork = self.fork_at(index);
+        self.add_message(MessageRole::User, content);
+        self.attach_fork(fork);
+    }
+
+    /// Number of continuations at the fork at `index`
+    pub fn branch_count(&self, index: usize) -> usize {
+        self.messages.get(index).map_or(1, |m| m.branches.len() + 1)
+    }
+
+    /// Switch the fork at `index` to the next (`1`) or previous (`-1`)
+    /// sibling. Returns the new position and the number of siblings.
+    pub fn switch_branch(&mut self, index: usize, delta: isize) -> Option<(usize, usize)> {
+        let count = self.branch_count(index);
+        if count < 2 || self.is_streaming {
+            return None;
+        }
+        let current = self.messages[index].branch as isize;
+        let target = (current + delta).rem_euclid(count as isize) as usize;
 
-        // Keep only last 100 messages to prevent memory issues
-        while self.messages.len() > 100 {
-            self.messages.pop_front();
+        let mut fork = self.fork_at(index);
+        let mut active = fork.siblings.remove(target);
+        if let Some(first) = active.first_mut() {
+            first.branches = fork.siblings;
+            first.branch = target;
         }
+        self.messages.extend(active);
+        Some((target + 1, count))
+    }
+
+    pub fn input_text(&self) -> String {
+        self.input.lines().join("\n")
+    }
+
+    /// Replace the input with `text`, leaving the cursor at the end
+    pub fn set_input(&mut self, text: &str) {
+        self.input = new_input();
+        self.input.insert_str(text);
     }
 
     pub fn navigate_history_up(&mut self) {
@@ -77,16 +564,16 @@ impl ChatSession {
         match self.history_index {
             None => {
                 // Save current input before starting navigation
-                self.temp_input = self.input_buffer.clone();
+                self.temp_input = self.input_text();
                 self.history_index = Some(0);
-                if let Some(msg) = self.message_history.front() {
-                    self.input_buffer = msg.clone();
+                if let Some(msg) = self.message_history.front().cloned() {
+                    self.set_input(&msg);
                 }
             }
             Some(index) if index < self.message_history.len() - 1 => {
                 self.history_index = Some(index + 1);
-                if let Some(msg) = self.message_history.get(index + 1) {
-                    self.input_buffer = msg.clone();
+                if let Some(msg) = self.message_history.get(index + 1).cloned() {
+                    self.set_input(&msg);
                 }
             }
             _ => {}
@@ -97,14 +584,14 @@ impl ChatSession {
         match self.history_index {
             Some(0) => {
                 // Restore original input
-                self.input_buffer = self.temp_input.clone();
+                let input = std::mem::take(&mut self.temp_input);
+                self.set_input(&input);
                 self.history_index = None;
-                self.temp_input.clear();
             }
             Some(index) => {
                 self.history_index = Some(index - 1);
-                if let Some(msg) = self.message_history.get(index - 1) {
-                    self.input_buffer = msg.clone();
+                if let Some(msg) = self.message_history.get(index - 1).cloned() {
+                    self.set_input(&msg);
                 }
             }
             None => {}
@@ -119,34 +606,208 @@ impl ChatSession {
     pub fn clear_session(&mut self) {
         self.messages.clear();
         self.current_response.clear();
-        self.input_buffer.clear();
+        self.response_markdown = MarkdownStream::default();
+        self.input = new_input();
         self.total_tokens = 0;
+        self.editing_index = None;
+        self.attachments.clear();
+        self.attached_files.clear();
+        self.pending_fork = None;
+        self.context_summary = None;
+        self.context_used = 0;
+        self.context_estimate = 0;
+        self.context_dropped = 0;
         self.session_started = Local::now();
     }
 
     pub fn change_model(&mut self, new_model: String) {
         self.current_model = new_model;
         self.add_message(
-            MessageRole::System,
+            MessageRole::Notice,
             format!("Switched to model: {}", self.current_model),
         );
     }
 
-    pub fn get_context_for_api(&self) -> Vec<serde_json::Value> {
-        self.messages
+    /// Set or clear the system prompt, noting the change in the transcript
+    pub fn set_system_prompt(&mut self, prompt: Option<String>, source: &str) {
+        let prompt = prompt.filter(|p| !p.trim().is_empty());
+        let notice = match &prompt {
+            Some(_) => format!("System prompt set from {source}"),
+            None => "System prompt cleared".to_string(),
+        };
+        self.system_prompt = prompt;
+        self.add_message(MessageRole::Notice, notice);
+    }
+
+    /// Generation option names, plus `keep_alive`, the `context` strategy and `tools`
+    pub fn option_keys() -> impl Iterator<Item = &'static str> {
+        GenerationOptions::KEYS
             .iter()
-            .filter(|m| m.role != MessageRole::System)
-            .map(|msg| {
-                serde_json::json!({
-                    "role": match msg.role {
-                        MessageRole::User => "user",
-                        MessageRole::Assistant => "assistant",
-                        MessageRole::System => "system",
-                    },
-                    "content": msg.content
-                })
+            .copied()
+            .chain(["keep_alive", "context", "tools"])
+    }
+
+    pub fn option_value(&self, key: &str) -> Option<String> {
+        match key {
+            "keep_alive" => self.keep_alive.clone(),
+            "context" => (!self.context_strategy.is_default())
+                .then(|| self.context_strategy.name().to_string()),
+            "tools" => self.tools.then(|| "on".to_string()),
+            _ => self.options.get(key),
+        }
+    }
+
+    /// Set a generation option from text; an empty value resets it
+    pub fn set_option(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "keep_alive" => {
+                let value = value.trim();
+                let valid = value.parse::<i64>().is_ok()
+                    || value
+                        .strip_suffix(['s', 'm', 'h'])
+                        .is_some_and(|n| n.parse::<f64>().is_ok());
+                if !value.is_empty() && !valid {
+                    anyhow::bail!("keep_alive expects seconds or a duration like 30s, 10m, 1h");
+                }
+                self.keep_alive = (!value.is_empty()).then(|| value.to_string());
+                Ok(())
+            }
+            "context" => {
+                let value = value.trim();
+                self.context_strategy = match value {
+                    "" => ContextStrategy::default(),
+                    _ => ContextStrategy::parse(value).ok_or_else(|| {
+                        anyhow::anyhow!("context expects truncate, pin or summarize")
+                    })?,
+                };
+                Ok(())
+            }
+            "tools" => {
+                self.tools = match value.trim() {
+                    "" | "off" => false,
+                    "on" => true,
+                    _ => anyhow::bail!("tools expects on or off"),
+                };
+                Ok(())
+            }
+            _ => self.options.set(key, value),
+        }
+    }
+
+    /// `key=value` list of every option that differs from the model defaults
+    pub fn options_summary(&self) -> String {
+        Self::option_keys()
+            .filter_map(|key| self.option_value(key).map(|value| format!("{key}={value}")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Messages for the next request. With a known context `window`, older
+    /// turns are left out or summarized so the prompt leaves a quarter of the
+    /// window for the reply.
+    pub fn plan_context(&self, window: Option<usize>) -> ContextPlan {
+        let summary = self
+            .context_summary
+            .as_ref()
+            .filter(|_| self.context_strategy == ContextStrategy::Summarize);
+        let start = summary.map_or(0, |s| s.covers.min(self.messages.len()));
+
+        let mut head: Vec<serde_json::Value> = self
+            .system_prompt
+            .iter()
+            .map(|prompt| serde_json::json!({ "role": "system", "content": prompt }))
+            .collect();
+        let mut head_tokens: usize = self.system_prompt.iter().map(|p| estimate_tokens(p)).sum();
+        let system_head = head.clone();
+        if let Some(summary) = summary {
+            head.push(summary_message(&summary.text));
+            head_tokens += estimate_tokens(&summary.text);
+        }
+
+        let turns: Vec<(usize, serde_json::Value, usize)> = self
+            .messages
+            .iter()
+            .enumerate()
+            .skip(start)
+            .filter_map(|(index, msg)| {
+                let message = msg.to_api(&self.encoded_images)?;
+                Some((index, message, msg.estimated_tokens()))
             })
-            .collect()
+            .collect();
+        let total = head_tokens + turns.iter().map(|(_, _, t)| t).sum::<usize>();
+
+        let plan = |kept: Vec<&(usize, serde_json::Value, usize)>, head: &[serde_json::Value]| {
+            ContextPlan {
+                estimated_tokens: head_tokens + kept.iter().map(|(_, _, t)| t).sum::<usize>(),
+                dropped: turns.len() - kept.len(),
+                messages: head
+                    .iter()
+                    .cloned()
+                    .chain(kept.into_iter().map(|(_, m, _)| m.clone()))
+                    .collect(),
+                summarize: None,
+            }
+        };
+
+        let budget = match window {
+            Some(window) if total > window - window / 4 => {
+                (window - window / 4).saturating_sub(head_tokens)
+            }
+            _ => return plan(turns.iter().collect(), &head),
+        };
+
+        let truncated = |budget: usize| {
+            let kept = newest_fitting(&turns, budget);
+            plan(turns[turns.len() - kept..].iter().collect(), &head)
+        };
+
+        match self.context_strategy {
+            ContextStrategy::Truncate => truncated(budget),
+            ContextStrategy::Pin => {
+                let pinned = turns.len().min(2);
+                let pinned_tokens: usize = turns[..pinned].iter().map(|(_, _, t)| t).sum();
+                let rest = &turns[pinned..];
+                if rest.is_empty() {
+                    return truncated(budget);
+                }
+                let kept = newest_fitting(rest, budget.saturating_sub(pinned_tokens));
+                let messages = turns[..pinned]
+                    .iter()
+                    .chain(&rest[rest.len() - kept..])
+                    .collect();
+                plan(messages, &head)
+            }
+            ContextStrategy::Summarize => {
+                // Keep recent turns verbatim in half the budget, condense the rest
+                let kept = newest_fitting(&turns, budget / 2);
+                let cut = turns.len() - kept;
+                if cut == 0 {
+                    return truncated(budget);
+                }
+
+                let mut transcript = summary
+                    .map(|s| format!("Earlier summary:\n{}\n\n", s.text))
+                    .unwrap_or_default();
+                for (index, _, _) in &turns[..cut] {
+                    let msg = &self.messages[*index];
+                    let speaker = match msg.role {
+                        MessageRole::User => "User",
+                        MessageRole::Tool => "Tool",
+                        _ => "Assistant",
+                    };
+                    transcript.push_str(&format!("{speaker}: {}\n\n", msg.api_content()));
+                }
+
+                let mut fallback = truncated(budget);
+                fallback.summarize = Some(SummaryRequest {
+                    covers: turns[cut].0,
+                    transcript,
+                    head: system_head,
+                    recent: turns[cut..].iter().map(|(_, m, _)| m.clone()).collect(),
+                });
+                fallback
+            }
+        }
     }
 }
 
@@ -156,10 +817,91 @@ pub struct ChatState {
     pub active_session_index: usize,
     pub input_mode: InputMode,
     pub show_model_selector: bool,
+    pub show_sidebar: bool,
+    pub view: TranscriptView,
+    pub render_cache: RenderCache, // Rendered replies of the active session
     pub available_models: Vec<String>,
     pub selected_model_index: usize,
 }
 
+/// Scroll position and search over the active session's transcript
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptView {
+    pub session_id: String, // Session the view belongs to; reset when it changes
+    pub scroll: Option<usize>, // Top line when scrolled back; None follows new output
+    pub total_lines: usize, // Recorded while drawing
+    pub page_height: usize,
+    pub message_starts: Vec<usize>, // First line of each message
+    pub search: Option<TranscriptSearch>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptSearch {
+    pub query: String,
+    pub editing: bool,          // The `/` prompt is open
+    pub current: Option<usize>, // Selected match; None picks the one nearest the view
+    pub match_count: usize,     // Recorded while drawing
+    pub jump: bool,             // Scroll the selected match into view on the next draw
+}
+
+impl TranscriptView {
+    pub fn max_top(&self) -> usize {
+        self.total_lines.saturating_sub(self.page_height)
+    }
+
+    /// First line to draw
+    pub fn top(&self) -> usize {
+        self.scroll
+            .map_or(self.max_top(), |top| top.min(self.max_top()))
+    }
+
+    /// One past the last visible line
+    pub fn bottom(&self) -> usize {
+        self.top() + self.page_height
+    }
+
+    /// Scroll by `delta` lines; reaching the bottom resumes following
+    pub fn scroll_by(&mut self, delta: isize) {
+        let top = self.top().saturating_add_signed(delta);
+        self.scroll = (top < self.max_top()).then_some(top);
+    }
+
+    pub fn page(&mut self, pages: isize) {
+        let page = self.page_height.saturating_sub(1).max(1) as isize;
+        self.scroll_by(pages * page);
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = (self.max_top() > 0).then_some(0);
+    }
+
+    /// Put `line` in the middle of the view
+    pub fn reveal(&mut self, line: usize) {
+        let top = line.saturating_sub(self.page_height / 2);
+        self.scroll = (top < self.max_top()).then_some(top);
+    }
+
+    pub fn start_search(&mut self) {
+        self.search = Some(TranscriptSearch {
+            editing: true,
+            ..TranscriptSearch::default()
+        });
+    }
+
+    /// Move to the next (`1`) or previous (`-1`) match, wrapping around
+    pub fn next_match(&mut self, delta: isize) {
+        if let Some(search) = &mut self.search {
+            if search.match_count == 0 {
+                return;
+            }
+            let current = search.current.unwrap_or(0) as isize;
+            search.current =
+                Some((current + delta).rem_euclid(search.match_count as isize) as usize);
+            search.jump = true;
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InputMode {
     Normal,
@@ -181,6 +923,9 @@ impl ChatState {
             active_session_index: 0,
             input_mode: InputMode::Normal,
             show_model_selector: false,
+            show_sidebar: false,
+            view: TranscriptView::default(),
+            render_cache: RenderCache::default(),
             available_models,
             selected_model_index: 0,
         }
@@ -196,6 +941,68 @@ impl ChatState {
         self.active_session_index = self.sessions.len() - 1;
     }
 
+    /// Switch to a session, opening it if it isn't already
+    pub fn open_session(&mut self, session: ChatSession) {
+        match self.sessions.iter().position(|s| s.id == session.id) {
+            Some(index) => self.active_session_index = index,
+            None => {
+                self.sessions.push(session);
+                self.active_session_index = self.sessions.len() - 1;
+            }
+        }
+    }
+
+    /// Remove a session, always leaving at least one open
+    pub fn remove_session(&mut self, index: usize) -> Option<ChatSession> {
+        if index >= self.sessions.len() {
+            return None;
+        }
+        let removed = self.sessions.remove(index);
+        if self.sessions.is_empty() {
+            self.sessions
+                .push(ChatSession::new(removed.current_model.clone()));
+        }
+        if self.active_session_index > index || self.active_session_index >= self.sessions.len() {
+            self.active_session_index = self.active_session_index.saturating_sub(1);
+        }
+        Some(removed)
+    }
+
+    /// Move the selection by `delta`, wrapping around
+    pub fn switch_session(&mut self, delta: isize) {
+        let len = self.sessions.len() as isize;
+        self.active_session_index =
+            (self.active_session_index as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Move the active session up or down the list
+    pub fn move_session(&mut self, delta: isize) {
+        let target = self.active_session_index as isize + delta;
+        if target < 0 || target >= self.sessions.len() as isize {
+            return;
+        }
+        self.sessions
+            .swap(self.active_session_index, target as usize);
+        self.active_session_index = target as usize;
+    }
+
+    /// Copy the active session into a new one right after it
+    pub fn duplicate_session(&mut self) {
+        let source = &self.sessions[self.active_session_index];
+        let mut copy = source.clone();
+        copy.id = new_session_id();
+        copy.title = Some(format!("{} (copy)", source.display_title()));
+        copy.is_streaming = false;
+        copy.current_response.clear();
+        copy.response_markdown = MarkdownStream::default();
+        self.sessions.insert(self.active_session_index + 1, copy);
+        self.active_session_index += 1;
+    }
+
+    pub fn session_index(&self, id: &str) -> Option<usize> {
+        self.sessions.iter().position(|s| s.id == id)
+    }
+
     pub fn toggle_model_selector(&mut self) {
         self.show_model_selector = !self.show_model_selector;
         if self.show_model_selector {
@@ -214,3 +1021,22 @@ impl ChatState {
         self.input_mode = InputMode::Normal;
     }
 }
+
+fn new_input() -> TextArea<'static> {
+    let mut input = TextArea::default();
+    input.set_cursor_line_style(Style::default());
+    input.set_style(Style::default().fg(TokyoNight::FG));
+    input.set_placeholder_text("Type a message or /command…");
+    input.set_placeholder_style(Style::default().fg(TokyoNight::DARK3));
+    input
+}
+
+/// Unique, time-ordered id used as the session's file name
+pub fn new_session_id() -> String {
+    let now = Local::now();
+    format!(
+        "{}-{:09}",
+        now.format("%Y%m%d-%H%M%S"),
+        now.timestamp_subsec_nanos()
+    )
+}
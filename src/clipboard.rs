@@ -0,0 +1,83 @@
+use anyhow::{bail, Result};
+use base64::Engine;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Copy text to the system clipboard and return how it was copied.
+///
+/// Over SSH the local clipboard is the one that matters, so we ask the
+/// terminal to set it with an OSC 52 escape sequence. Otherwise we use the
+/// platform's clipboard command, falling back to OSC 52 if none is installed.
+/// `LLAMALOOP_CLIPBOARD=osc52` forces the escape sequence everywhere.
+pub fn copy(text: &str) -> Result<&'static str> {
+    let forced = std::env::var("LLAMALOOP_CLIPBOARD").is_ok_and(|v| v == "osc52");
+    let over_ssh =
+        std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+
+    if !forced && !over_ssh {
+        for (program, args) in clipboard_commands() {
+            if pipe_to(program, args, text).is_ok() {
+                return Ok(program);
+            }
+        }
+    }
+
+    copy_osc52(text)?;
+    Ok("OSC 52")
+}
+
+/// Commands that read the clipboard contents from stdin, best first
+fn clipboard_commands() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        return vec![("pbcopy", &[])];
+    }
+    if cfg!(target_os = "windows") {
+        return vec![("clip", &[])];
+    }
+
+    let mut commands: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        commands.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        commands.push(("xclip", &["-selection", "clipboard"]));
+        commands.push(("xsel", &["--clipboard", "--input"]));
+    }
+    // WSL can reach the Windows clipboard
+    commands.push(("clip.exe", &[]));
+    commands
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{program} exited with {status}");
+    }
+    Ok(())
+}
+
+/// Ask the terminal to set the clipboard. Works through SSH in most modern
+/// terminals; tmux needs the sequence wrapped in a passthrough.
+fn copy_osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{encoded}\x07");
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    };
+
+    let mut stdout = std::io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
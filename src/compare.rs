@@ -0,0 +1,110 @@
+use crate::app::ChatStream;
+use crate::chat::ChatSession;
+use crate::theme::TokyoNight;
+use ratatui::style::Style;
+use tui_textarea::TextArea;
+
+/// More columns than this get too narrow to read
+pub const MAX_MODELS: usize = 4;
+
+/// One prompt sent to several models, each answering in its own column
+pub struct CompareState {
+    pub columns: Vec<CompareColumn>,
+    pub picker: Option<ModelPicker>, // Shown while choosing models
+    pub input: TextArea<'static>,
+    pub editing: bool,
+    pub sequential: bool, // One model at a time, so they don't all have to fit in memory
+    pub multi_turn: bool, // Follow-up prompts continue each column's conversation
+    pub scroll_back: usize, // Lines scrolled up from the bottom of every column
+}
+
+/// A model's side of the comparison. The session holds its conversation and
+/// streaming state, exactly like a chat tab, but is never saved.
+pub struct CompareColumn {
+    pub session: ChatSession,
+    pub stream: Option<ChatStream>,
+    pub queued: bool, // Waiting for its turn in sequential mode
+}
+
+/// Checklist of installed models to compare
+#[derive(Clone, Default)]
+pub struct ModelPicker {
+    pub models: Vec<String>,
+    pub chosen: Vec<String>, // In the order they were picked
+    pub selected: usize,
+}
+
+impl CompareState {
+    pub fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+            picker: None,
+            input: new_input(),
+            editing: false,
+            sequential: false,
+            multi_turn: false,
+            scroll_back: 0,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.columns.iter().any(|c| c.stream.is_some() || c.queued)
+    }
+
+    pub fn models(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|c| c.session.current_model.clone())
+            .collect()
+    }
+
+    pub fn input_text(&self) -> String {
+        self.input.lines().join("\n")
+    }
+
+    pub fn clear_input(&mut self) {
+        self.input = new_input();
+    }
+
+    /// Next column waiting for its turn, once nothing else is streaming
+    pub fn next_queued(&self) -> Option<usize> {
+        if self.columns.iter().any(|c| c.stream.is_some()) {
+            return None;
+        }
+        self.columns.iter().position(|c| c.queued)
+    }
+}
+
+impl ModelPicker {
+    pub fn new(models: Vec<String>, chosen: Vec<String>) -> Self {
+        Self {
+            models,
+            chosen,
+            selected: 0,
+        }
+    }
+
+    /// Tick or untick the selected model; returns false when the list is full
+    pub fn toggle_selected(&mut self) -> bool {
+        let Some(model) = self.models.get(self.selected) else {
+            return true;
+        };
+        if let Some(pos) = self.chosen.iter().position(|m| m == model) {
+            self.chosen.remove(pos);
+        } else if self.chosen.len() < MAX_MODELS {
+            self.chosen.push(model.clone());
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+fn new_input() -> TextArea<'static> {
+    let mut input = TextArea::default();
+    input.set_cursor_line_style(Style::default());
+    input.set_style(Style::default().fg(TokyoNight::FG));
+    input.set_placeholder_text("Ask every model the same question…");
+    input.set_placeholder_style(Style::default().fg(TokyoNight::DARK3));
+    input
+}
@@ -0,0 +1,389 @@
+use crate::api::{OllamaClient, PullProgress};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Progress of a single layer (blob) within a model pull
+#[derive(Clone)]
+pub struct LayerProgress {
+    pub digest: String,
+    pub total: u64,
+    pub completed: u64,
+}
+
+/// Aggregated progress of one pull, built from Ollama's NDJSON status events
+#[derive(Clone)]
+pub struct PullProgressState {
+    pub status: String,
+    pub layers: Vec<LayerProgress>,
+    pub started_at: Instant,
+    pub finished_at: Option<Instant>,
+    pub bytes_per_sec: f64,
+    rate_sample: (Instant, u64),
+}
+
+impl PullProgressState {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            status: "queued".to_string(),
+            layers: Vec::new(),
+            started_at: now,
+            finished_at: None,
+            bytes_per_sec: 0.0,
+            rate_sample: (now, 0),
+        }
+    }
+
+    fn apply(&mut self, progress: PullProgress) {
+        if !progress.status.is_empty() {
+            self.status = progress.status;
+        }
+
+        if let (Some(digest), Some(total)) = (progress.digest, progress.total) {
+            let completed = progress.completed.unwrap_or(0);
+            match self.layers.iter_mut().find(|l| l.digest == digest) {
+                Some(layer) => {
+                    layer.total = total;
+                    layer.completed = completed;
+                }
+                None => self.layers.push(LayerProgress {
+                    digest,
+                    total,
+                    completed,
+                }),
+            }
+        }
+
+        // Smooth the transfer rate over half-second windows
+        let (sample_time, sample_bytes) = self.rate_sample;
+        let elapsed = sample_time.elapsed();
+        if elapsed >= Duration::from_millis(500) {
+            let completed = self.completed_bytes();
+            let delta = completed.saturating_sub(sample_bytes) as f64;
+            let instant_rate = delta / elapsed.as_secs_f64();
+            self.bytes_per_sec = if self.bytes_per_sec == 0.0 {
+                instant_rate
+            } else {
+                self.bytes_per_sec * 0.7 + instant_rate * 0.3
+            };
+            self.rate_sample = (Instant::now(), completed);
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.layers.iter().map(|l| l.total).sum()
+    }
+
+    pub fn completed_bytes(&self) -> u64 {
+        self.layers.iter().map(|l| l.completed).sum()
+    }
+
+    pub fn ratio(&self) -> f64 {
+        let total = self.total_bytes();
+        if total == 0 {
+            0.0
+        } else {
+            (self.completed_bytes() as f64 / total as f64).min(1.0)
+        }
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        if self.bytes_per_sec <= 0.0 || self.finished_at.is_some() {
+            return None;
+        }
+        let remaining = self.total_bytes().saturating_sub(self.completed_bytes()) as f64;
+        Some(Duration::from_secs_f64(remaining / self.bytes_per_sec))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum DownloadStatus {
+    Queued,
+    Active,
+    Paused,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+pub struct DownloadJob {
+    pub id: usize,
+    pub model_name: String,
+    pub status: DownloadStatus,
+    pub progress: PullProgressState,
+    pub attempts: u32,
+    pub retry_at: Option<Instant>,
+    run: usize, // Incremented on every (re)start so stale events can be ignored
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DownloadJob {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            DownloadStatus::Completed | DownloadStatus::Failed(_) | DownloadStatus::Cancelled
+        )
+    }
+
+    fn abort(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            // Dropping the task drops the HTTP stream, which ends the pull
+            handle.abort();
+        }
+    }
+}
+
+enum JobEvent {
+    Progress(PullProgress),
+    Finished(Result<(), String>),
+}
+
+/// Something the app should tell the user about
+pub enum DownloadNotice {
+    Started(String),
+    Completed(String),
+    Retrying {
+        model: String,
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+    Failed(String, String),
+}
+
+/// Most pulls allowed to run at once
+const MAX_CONCURRENT: usize = 8;
+
+/// Background download queue for model pulls
+pub struct DownloadManager {
+    pub jobs: Vec<DownloadJob>,
+    pub selected: usize,
+    pub max_concurrent: usize,
+    pub max_retries: u32,
+    next_id: usize,
+    event_tx: mpsc::Sender<(usize, usize, JobEvent)>,
+    event_rx: mpsc::Receiver<(usize, usize, JobEvent)>,
+}
+
+impl DownloadManager {
+    pub fn new(max_concurrent: usize) -> Self {
+        let (event_tx, event_rx) = mpsc::channel(256);
+        Self {
+            jobs: Vec::new(),
+            selected: 0,
+            max_concurrent: max_concurrent.clamp(1, MAX_CONCURRENT),
+            max_retries: 3,
+            next_id: 0,
+            event_tx,
+            event_rx,
+        }
+    }
+
+    /// Queue a model for download. Returns false if it's already pending.
+    pub fn enqueue(&mut self, model_name: &str) -> bool {
+        let already_pending = self
+            .jobs
+            .iter()
+            .any(|j| j.model_name == model_name && !j.is_finished());
+        if already_pending {
+            return false;
+        }
+
+        self.jobs.push(DownloadJob {
+            id: self.next_id,
+            model_name: model_name.to_string(),
+            status: DownloadStatus::Queued,
+            progress: PullProgressState::new(),
+            attempts: 0,
+            retry_at: None,
+            run: 0,
+            handle: None,
+        });
+        self.next_id += 1;
+        true
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|j| j.status == DownloadStatus::Active)
+            .count()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.jobs.iter().filter(|j| !j.is_finished()).count()
+    }
+
+    pub fn selected_job(&self) -> Option<&DownloadJob> {
+        self.jobs.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.jobs.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn cancel_selected(&mut self) -> Option<String> {
+        let job = self.jobs.get_mut(self.selected)?;
+        if job.is_finished() {
+            return None;
+        }
+        job.abort();
+        job.status = DownloadStatus::Cancelled;
+        job.progress.status = "cancelled".to_string();
+        job.progress.finished_at = Some(Instant::now());
+        Some(job.model_name.clone())
+    }
+
+    /// Pause an active or queued job, or resume a paused one.
+    /// Ollama keeps partially downloaded blobs, so resuming picks up where it left off.
+    pub fn toggle_pause_selected(&mut self) -> Option<(String, bool)> {
+        let job = self.jobs.get_mut(self.selected)?;
+        match job.status {
+            DownloadStatus::Active | DownloadStatus::Queued => {
+                job.abort();
+                job.status = DownloadStatus::Paused;
+                job.progress.status = "paused".to_string();
+                job.progress.bytes_per_sec = 0.0;
+                Some((job.model_name.clone(), true))
+            }
+            DownloadStatus::Paused => {
+                job.status = DownloadStatus::Queued;
+                job.retry_at = None;
+                job.progress.status = "queued".to_string();
+                Some((job.model_name.clone(), false))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn retry_selected(&mut self) -> Option<String> {
+        let job = self.jobs.get_mut(self.selected)?;
+        if !matches!(
+            job.status,
+            DownloadStatus::Failed(_) | DownloadStatus::Cancelled
+        ) {
+            return None;
+        }
+        job.status = DownloadStatus::Queued;
+        job.attempts = 0;
+        job.retry_at = None;
+        job.progress = PullProgressState::new();
+        Some(job.model_name.clone())
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|j| !j.is_finished());
+        self.selected = self.selected.min(self.jobs.len().saturating_sub(1));
+    }
+
+    pub fn set_max_concurrent(&mut self, max_concurrent: usize) {
+        self.max_concurrent = max_concurrent.clamp(1, MAX_CONCURRENT);
+    }
+
+    /// Drain progress events and start queued jobs up to the concurrency limit
+    pub fn poll(&mut self, client: &OllamaClient) -> Vec<DownloadNotice> {
+        let mut notices = Vec::new();
+
+        while let Ok((id, run, event)) = self.event_rx.try_recv() {
+            let max_retries = self.max_retries;
+            let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+                continue;
+            };
+            if job.run != run || job.status != DownloadStatus::Active {
+                continue;
+            }
+
+            match event {
+                JobEvent::Progress(progress) => job.progress.apply(progress),
+                JobEvent::Finished(Ok(())) => {
+                    job.handle = None;
+                    job.status = DownloadStatus::Completed;
+                    job.progress.status = "success".to_string();
+                    job.progress.finished_at = Some(Instant::now());
+                    notices.push(DownloadNotice::Completed(job.model_name.clone()));
+                }
+                JobEvent::Finished(Err(error)) => {
+                    job.handle = None;
+                    job.attempts += 1;
+                    if job.attempts <= max_retries {
+                        // Exponential backoff: 2s, 4s, 8s... capped at a minute
+                        let delay = Duration::from_secs((1u64 << job.attempts).min(60));
+                        job.status = DownloadStatus::Queued;
+                        job.retry_at = Some(Instant::now() + delay);
+                        job.progress.status = format!("retrying: {error}");
+                        job.progress.bytes_per_sec = 0.0;
+                        notices.push(DownloadNotice::Retrying {
+                            model: job.model_name.clone(),
+                            attempt: job.attempts,
+                            delay,
+                            error,
+                        });
+                    } else {
+                        job.status = DownloadStatus::Failed(error.clone());
+                        job.progress.status = format!("failed: {error}");
+                        job.progress.finished_at = Some(Instant::now());
+                        notices.push(DownloadNotice::Failed(job.model_name.clone(), error));
+                    }
+                }
+            }
+        }
+
+        let mut free_slots = self.max_concurrent.saturating_sub(self.active_count());
+        let now = Instant::now();
+        for job in &mut self.jobs {
+            if free_slots == 0 {
+                break;
+            }
+            let ready = job.status == DownloadStatus::Queued
+                && job.retry_at.is_none_or(|retry_at| retry_at <= now);
+            if ready {
+                Self::start(job, client.clone(), self.event_tx.clone());
+                notices.push(DownloadNotice::Started(job.model_name.clone()));
+                free_slots -= 1;
+            }
+        }
+
+        notices
+    }
+
+    fn start(
+        job: &mut DownloadJob,
+        client: OllamaClient,
+        events: mpsc::Sender<(usize, usize, JobEvent)>,
+    ) {
+        job.run += 1;
+        job.status = DownloadStatus::Active;
+        job.retry_at = None;
+        job.progress.status = "starting".to_string();
+        job.progress.finished_at = None;
+
+        let (id, run) = (job.id, job.run);
+        let model_name = job.model_name.clone();
+
+        job.handle = Some(tokio::spawn(async move {
+            let (tx, mut rx) = mpsc::channel(100);
+            let pull = client.pull_model(&model_name, tx);
+            let forward = async {
+                while let Some(progress) = rx.recv().await {
+                    let _ = events.send((id, run, JobEvent::Progress(progress))).await;
+                }
+            };
+
+            let (result, _) = tokio::join!(pull, forward);
+            let _ = events
+                .send((
+                    id,
+                    run,
+                    JobEvent::Finished(result.map_err(|e| e.to_string())),
+                ))
+                .await;
+        }));
+    }
+}
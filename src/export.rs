@@ -0,0 +1,422 @@
+use crate::api::ToolCall;
+use crate::chat::{new_session_id, ChatMessage, ChatSession, MessageRole};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+const MARKDOWN_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+const SYSTEM_PROMPT_HEADER: &str = "System Prompt";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.extension()?.to_str()?)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Render a session in the given format
+pub fn export_session(session: &ChatSession, format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => to_markdown(session),
+        ExportFormat::Json => serde_json::to_string_pretty(session)?,
+        ExportFormat::Jsonl => to_jsonl(session)?,
+    })
+}
+
+/// One `{"messages": [...]}` line with the whole transcript. Unlike a chat
+/// request nothing is left out to fit a context window, and images stay out:
+/// the format has no place for them.
+fn to_jsonl(session: &ChatSession) -> Result<String> {
+    let system = session
+        .system_prompt
+        .iter()
+        .map(|prompt| serde_json::json!({ "role": "system", "content": prompt }));
+    let conversation = session.messages.iter().filter_map(|msg| {
+        let role = match msg.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::Tool => "tool",
+            MessageRole::Notice => return None,
+        };
+        // Attached files are part of what the model read
+        let mut message = serde_json::json!({ "role": role, "content": msg.api_content() });
+        if !msg.tool_calls.is_empty() {
+            message["tool_calls"] = serde_json::json!(msg.tool_calls);
+        }
+        if let Some(name) = &msg.tool_name {
+            message["tool_name"] = serde_json::json!(name);
+        }
+        Some(message)
+    });
+    let messages: Vec<serde_json::Value> = system.chain(conversation).collect();
+    let line = serde_json::json!({ "messages": messages });
+    Ok(format!("{}\n", serde_json::to_string(&line)?))
+}
+
+/// File name used when no path is given, e.g. `llamaloop-20250101-120000-fix-the-parser.md`
+pub fn default_export_path(session: &ChatSession, format: ExportFormat) -> PathBuf {
+    let slug: String = session
+        .display_title()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .take(6)
+        .collect::<Vec<_>>()
+        .join("-");
+
+    PathBuf::from(format!(
+        "llamaloop-{}-{slug}.{}",
+        session.session_started.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    ))
+}
+
+fn to_markdown(session: &ChatSession) -> String {
+    let mut out = format!("# {}\n\n", session.display_title());
+    out.push_str(&format!("- **Model:** {}\n", session.current_model));
+    out.push_str(&format!(
+        "- **Started:** {}\n",
+        session.session_started.format(MARKDOWN_TIME_FORMAT)
+    ));
+    out.push_str(&format!("- **Tokens:** {}\n", session.total_tokens));
+    let options = session.options_summary();
+    if !options.is_empty() {
+        out.push_str(&format!("- **Options:** {options}\n"));
+    }
+
+    if let Some(prompt) = &session.system_prompt {
+        out.push_str(&format!(
+            "\n## {SYSTEM_PROMPT_HEADER}\n\n{}\n",
+            prompt.trim_end()
+        ));
+    }
+
+    for msg in &session.messages {
+        out.push_str(&format!(
+            "\n## {}{}{} — {}\n\n{}\n",
+            role_header(msg.role),
+            match &msg.tool_name {
+                Some(name) => format!(" ({name})"),
+                None => String::new(),
+            },
+            if msg.interrupted {
+                " (interrupted)"
+            } else {
+                ""
+            },
+            msg.timestamp.format(MARKDOWN_TIME_FORMAT),
+            // Exactly what the model was sent, attached files first; an
+            // import keeps it all as message text
+            msg.api_content().trim_end()
+        ));
+        for path in &msg.images {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            out.push_str(&format!("\n![{name}](<{}>)\n", path.display()));
+        }
+    }
+    out
+}
+
+fn role_header(role: MessageRole) -> &'static str {
+    match role {
+        MessageRole::Notice => "Notice",
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+        MessageRole::Tool => "Tool",
+    }
+}
+
+/// Rebuild sessions from an exported file. A JSONL file yields one session per line.
+pub fn import_sessions(path: &Path, default_model: &str) -> Result<Vec<ChatSession>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    let format = ExportFormat::from_path(path)
+        .or_else(|| {
+            content
+                .trim_start()
+                .starts_with('{')
+                .then_some(ExportFormat::Json)
+        })
+        .unwrap_or(ExportFormat::Markdown);
+
+    let mut sessions = match format {
+        ExportFormat::Markdown => vec![from_markdown(&content, default_model)?],
+        ExportFormat::Json => match serde_json::from_str::<ChatSession>(&content) {
+            Ok(session) => vec![session],
+            // A single-line JSONL file saved with a .json extension
+            Err(_) => from_jsonl(&content, default_model)?,
+        },
+        ExportFormat::Jsonl => from_jsonl(&content, default_model)?,
+    };
+
+    // Imports are copies, so they never overwrite the session they came from
+    for session in &mut sessions {
+        session.id = new_session_id();
+    }
+    Ok(sessions)
+}
+
+fn from_markdown(content: &str, default_model: &str) -> Result<ChatSession> {
+    let mut session = ChatSession::new(default_model.to_string());
+    let mut title = None;
+    let mut current: Option<ChatMessage> = None;
+    let mut body: Vec<&str> = Vec::new();
+    let mut in_system_prompt = false;
+
+    for line in content.lines() {
+        if let Some(header) = parse_message_header(line) {
+            finish_system_prompt(&mut in_system_prompt, &mut body, &mut session);
+            finish_message(current.take(), &mut body, &mut session);
+            current = Some(header);
+            continue;
+        }
+
+        if current.is_none() && line.trim() == format!("## {SYSTEM_PROMPT_HEADER}") {
+            in_system_prompt = true;
+            continue;
+        }
+
+        if current.is_some() || in_system_prompt {
+            body.push(line);
+        } else if let Some(heading) = line.strip_prefix("# ") {
+            title = Some(heading.trim().to_string());
+        } else if let Some(model) = line.strip_prefix("- **Model:** ") {
+            session.current_model = model.trim().to_string();
+        } else if let Some(started) = line.strip_prefix("- **Started:** ") {
+            if let Ok(time) = DateTime::parse_from_str(started.trim(), MARKDOWN_TIME_FORMAT) {
+                session.session_started = time.with_timezone(&Local);
+            }
+        } else if let Some(tokens) = line.strip_prefix("- **Tokens:** ") {
+            session.total_tokens = tokens.trim().parse().unwrap_or(0);
+        } else if let Some(options) = line.strip_prefix("- **Options:** ") {
+            parse_options(options, &mut session);
+        }
+    }
+    finish_system_prompt(&mut in_system_prompt, &mut body, &mut session);
+    finish_message(current.take(), &mut body, &mut session);
+
+    if session.messages.is_empty() {
+        bail!("no `## User` / `## Assistant` sections found");
+    }
+
+    rebuild_input_history(&mut session);
+    // Keep a custom title; one derived from the first question is rebuilt anyway
+    if title
+        .as_ref()
+        .is_some_and(|t| *t != session.display_title())
+    {
+        session.title = title;
+    }
+    Ok(session)
+}
+
+/// Read back `key=value` pairs. Stop sequences may contain spaces, so a
+/// value runs until the next known option name.
+fn parse_options(line: &str, session: &mut ChatSession) {
+    let mut pending: Option<(&str, String)> = None;
+    for word in line.split(' ') {
+        let option = word
+            .split_once('=')
+            .filter(|(key, _)| ChatSession::option_keys().any(|k| k == *key));
+        match (option, &mut pending) {
+            (Some((key, value)), _) => {
+                if let Some((key, value)) = pending.take() {
+                    let _ = session.set_option(key, &value);
+                }
+                pending = Some((key, value.to_string()));
+            }
+            (None, Some((_, value))) => {
+                value.push(' ');
+                value.push_str(word);
+            }
+            (None, None) => {}
+        }
+    }
+    if let Some((key, value)) = pending {
+        let _ = session.set_option(key, &value);
+    }
+}
+
+fn finish_system_prompt(active: &mut bool, body: &mut Vec<&str>, session: &mut ChatSession) {
+    if *active {
+        session.system_prompt = Some(body.join("\n").trim().to_string());
+        body.clear();
+        *active = false;
+    }
+}
+
+fn finish_message(current: Option<ChatMessage>, body: &mut Vec<&str>, session: &mut ChatSession) {
+    if let Some(mut msg) = current {
+        // Attached images are written as image links after the text
+        loop {
+            while body.last().is_some_and(|line| line.trim().is_empty()) {
+                body.pop();
+            }
+            let Some(path) = body.last().and_then(|line| image_link(line)) else {
+                break;
+            };
+            msg.images.insert(0, path);
+            body.pop();
+        }
+        msg.content = body.join("\n").trim().to_string();
+        session.messages.push_back(msg);
+    }
+    body.clear();
+}
+
+/// The path in `![name](<path>)`; the brackets allow spaces in the path
+fn image_link(line: &str) -> Option<PathBuf> {
+    let rest = line.trim().strip_prefix("![")?;
+    let (_, path) = rest.split_once("](")?;
+    let path = path.strip_suffix(')')?;
+    let path = path
+        .strip_prefix('<')
+        .and_then(|p| p.strip_suffix('>'))
+        .unwrap_or(path);
+    Some(PathBuf::from(path))
+}
+
+fn parse_message_header(line: &str) -> Option<ChatMessage> {
+    let rest = line.strip_prefix("## ")?;
+    let (role, time) = rest.split_once(" — ")?;
+    let (role, interrupted) = match role.trim().strip_suffix(" (interrupted)") {
+        Some(role) => (role, true),
+        None => (role.trim(), false),
+    };
+    // Tool results carry the tool's name: `Tool (read_file)`
+    let (role, tool_name) = match role.strip_suffix(')').and_then(|r| r.split_once(" (")) {
+        Some((role, name)) => (role, Some(name.to_string())),
+        None => (role, None),
+    };
+    let role = match role {
+        "Notice" => MessageRole::Notice,
+        "User" => MessageRole::User,
+        "Assistant" => MessageRole::Assistant,
+        "Tool" => MessageRole::Tool,
+        _ => return None,
+    };
+    let timestamp = DateTime::parse_from_str(time.trim(), MARKDOWN_TIME_FORMAT)
+        .ok()?
+        .with_timezone(&Local);
+    Some(ChatMessage {
+        role,
+        content: String::new(),
+        timestamp,
+        interrupted,
+        branches: Vec::new(),
+        branch: 0,
+        metrics: None,
+        tool_calls: Vec::new(),
+        tool_name,
+        images: Vec::new(),
+        files: Vec::new(),
+    })
+}
+
+#[derive(Deserialize)]
+struct JsonlConversation {
+    messages: Vec<JsonlMessage>,
+}
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+    #[serde(default)]
+    tool_name: Option<String>,
+}
+
+fn from_jsonl(content: &str, default_model: &str) -> Result<Vec<ChatSession>> {
+    let mut sessions = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let conversation: JsonlConversation = serde_json::from_str(line)
+            .with_context(|| format!("line {}: expected {{\"messages\": [...]}}", i + 1))?;
+
+        let mut session = ChatSession::new(default_model.to_string());
+        session.system_prompt = conversation
+            .messages
+            .iter()
+            .find(|msg| msg.role == "system")
+            .map(|msg| msg.content.clone());
+        session.messages = conversation
+            .messages
+            .into_iter()
+            .filter_map(|msg| {
+                let role = match msg.role.as_str() {
+                    "user" => MessageRole::User,
+                    "assistant" => MessageRole::Assistant,
+                    "tool" => MessageRole::Tool,
+                    _ => return None,
+                };
+                Some(ChatMessage {
+                    role,
+                    content: msg.content,
+                    timestamp: session.session_started,
+                    interrupted: false,
+                    branches: Vec::new(),
+                    branch: 0,
+                    metrics: None,
+                    tool_calls: msg.tool_calls,
+                    tool_name: msg.tool_name,
+                    images: Vec::new(),
+                    files: Vec::new(),
+                })
+            })
+            .collect::<VecDeque<_>>();
+        rebuild_input_history(&mut session);
+        sessions.push(session);
+    }
+
+    if sessions.is_empty() {
+        bail!("no conversations found");
+    }
+    Ok(sessions)
+}
+
+/// Up-arrow history is newest first
+fn rebuild_input_history(session: &mut ChatSession) {
+    session.message_history = session
+        .messages
+        .iter()
+        .rev()
+        .filter(|m| m.role == MessageRole::User)
+        .take(50)
+        .map(|m| m.content.clone())
+        .collect();
+}
@@ -0,0 +1,263 @@
+use crate::chat::estimate_tokens;
+use anyhow::{bail, Context, Result};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::sync::oneshot;
+
+/// Bigger files are left out; they'd crowd out the conversation anyway
+const MAX_FILE_SIZE: u64 = 512 * 1024;
+
+/// Most files one command may attach
+pub const MAX_FILES: usize = 200;
+
+/// A file's text, attached to a message and sent ahead of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileContext {
+    pub path: String, // As typed, or relative to the working directory
+    pub content: String,
+    /// First and last line when this is an excerpt found by retrieval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<(usize, usize)>,
+}
+
+impl FileContext {
+    pub fn read(path: &Path) -> Result<Self> {
+        let size = std::fs::metadata(path)
+            .with_context(|| format!("can't read {}", path.display()))?
+            .len();
+        if size > MAX_FILE_SIZE {
+            bail!(
+                "{} is too big ({} KB, the limit is {} KB)",
+                path.display(),
+                size / 1024,
+                MAX_FILE_SIZE / 1024
+            );
+        }
+        let bytes =
+            std::fs::read(path).with_context(|| format!("can't read {}", path.display()))?;
+        if bytes.contains(&0) {
+            bail!("{} is a binary file", path.display());
+        }
+        let content = String::from_utf8(bytes)
+            .map_err(|_| anyhow::anyhow!("{} is not UTF-8 text", path.display()))?;
+        Ok(Self {
+            path: display_path(path),
+            content,
+            lines: None,
+        })
+    }
+
+    /// The path as a header, then the contents fenced with more backticks
+    /// than any run inside them
+    pub fn render(&self) -> String {
+        let longest_run = self
+            .content
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        let lang = Path::new(&self.path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        format!(
+            "File: {}\n{fence}{lang}\n{}\n{fence}",
+            self.label(),
+            self.content.trim_end()
+        )
+    }
+
+    /// The path, with the line range for an excerpt: `src/app.rs:120-160`
+    pub fn label(&self) -> String {
+        match self.lines {
+            Some((first, last)) => format!("{}:{first}-{last}", self.path),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Tokens `render` comes to, without building it
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.content) + estimate_tokens(&self.path)
+    }
+}
+
+/// Files a walk found, and how many entries it couldn't read on the way
+/// (permission denied, broken symlinks)
+#[derive(Debug, Default)]
+pub struct Found {
+    pub paths: Vec<PathBuf>,
+    pub unreadable: usize,
+    pub too_many: bool, // More than the limit matched; the walk stopped there
+}
+
+/// Files for `/file`: a file, a directory, or a glob such as `src/**/*.rs`,
+/// which is matched from its directory part down. At most `limit` are found.
+pub fn find_files(pattern: &str, limit: usize) -> Result<Found> {
+    if !is_glob(pattern) {
+        let path = PathBuf::from(pattern);
+        if path.is_dir() {
+            return walk(&path, None, limit);
+        }
+        if !path.exists() {
+            bail!("{pattern} doesn't exist");
+        }
+        return Ok(Found {
+            paths: vec![path],
+            ..Found::default()
+        });
+    }
+
+    let mut root = PathBuf::new();
+    let mut rest = Vec::new();
+    for part in pattern.split('/') {
+        if rest.is_empty() && !is_glob(part) {
+            root.push(if part.is_empty() { "/" } else { part });
+        } else {
+            rest.push(part);
+        }
+    }
+    if root.as_os_str().is_empty() {
+        root.push(".");
+    }
+    // Anchored, so `*.rs` only means the files directly in `root`
+    walk(&root, Some(&format!("/{}", rest.join("/"))), limit)
+}
+
+/// Files under `dir` for `/dir`, optionally only those matching `glob`
+/// (e.g. `*.rs`) at any depth. At most `limit` are found.
+pub fn find_in_dir(dir: &Path, glob: Option<&str>, limit: usize) -> Result<Found> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    walk(dir, glob, limit)
+}
+
+/// Walk `root` the way git sees it: hidden files and anything in
+/// `.gitignore`, `.ignore` or the global excludes are skipped, and so are
+/// entries that can't be read. Stops once more than `limit` files match.
+fn walk(root: &Path, glob: Option<&str>, limit: usize) -> Result<Found> {
+    let mut builder = WalkBuilder::new(root);
+    builder.require_git(false);
+    if let Some(glob) = glob {
+        let mut overrides = OverrideBuilder::new(root);
+        overrides.add(glob)?;
+        builder.overrides(overrides.build()?);
+    }
+
+    let mut found = Found::default();
+    for entry in builder.build() {
+        let Ok(entry) = entry else {
+            found.unreadable += 1;
+            continue;
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if found.paths.len() == limit {
+            found.too_many = true;
+            break;
+        }
+        found.paths.push(entry.into_path());
+    }
+    found.paths.sort();
+    Ok(found)
+}
+
+/// Files read for attaching, and what was passed over
+#[derive(Debug)]
+pub struct Collected {
+    pub files: Vec<FileContext>,
+    pub skipped: usize,    // Binary or oversized
+    pub unreadable: usize, // Entries the walk couldn't read
+}
+
+/// Read the files a search found. A single file has to be text; in a
+/// directory, binary and oversized files are just passed over.
+fn collect(found: Found) -> Result<Collected> {
+    let Found {
+        paths,
+        unreadable,
+        too_many,
+    } = found;
+    if too_many {
+        bail!("More than {MAX_FILES} files matched; narrow it down with a glob");
+    }
+    if paths.is_empty() && unreadable > 0 {
+        bail!("No files matched; {unreadable} entries couldn't be read");
+    }
+    if paths.is_empty() {
+        bail!("No files matched");
+    }
+
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    for path in &paths {
+        match FileContext::read(path) {
+            Ok(file) => files.push(file),
+            Err(e) if paths.len() == 1 => return Err(e),
+            Err(_) => skipped += 1,
+        }
+    }
+    if files.is_empty() {
+        bail!("None of the {} matching files are text", paths.len());
+    }
+    Ok(Collected {
+        files,
+        skipped,
+        unreadable,
+    })
+}
+
+/// A `/file` or `/dir` search, walked and read off the UI task, for the
+/// session that asked for it
+pub struct FileSearch {
+    pub session_id: String,
+    receiver: oneshot::Receiver<Result<Collected>>,
+}
+
+impl FileSearch {
+    /// Run `find` with `MAX_FILES` as its limit, then read what it found
+    pub fn start(
+        session_id: String,
+        find: impl FnOnce(usize) -> Result<Found> + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(find(MAX_FILES).and_then(collect));
+        });
+        Self {
+            session_id,
+            receiver: rx,
+        }
+    }
+
+    /// The files read, once the search is over
+    pub fn poll(&mut self) -> Option<Result<Collected>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Some(Err(anyhow::anyhow!("the search stopped unexpectedly")))
+            }
+        }
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// `path` relative to the working directory when it's inside it
+pub fn display_path(path: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf));
+    let path = relative.as_deref().unwrap_or(path);
+    path.strip_prefix("./")
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
@@ -1,14 +1,34 @@
 mod api;
 mod app;
 mod chat;
+mod clipboard;
+mod compare;
+mod downloads;
+mod export;
+mod files;
+mod markdown;
+mod modelfile;
+mod modelfile_editor;
+mod presets;
+mod rag;
+mod storage;
 mod theme;
+mod tool_check;
+mod tools;
 mod ui;
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
@@ -29,18 +49,35 @@ async fn main() -> Result<()> {
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    // Lets terminals that support it report Shift+Enter distinctly from Enter
+    let keyboard_enhancement = supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhancement {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let app = App::new();
     let res = run_app(&mut terminal, app).await;
 
+    if keyboard_enhancement {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -57,12 +94,20 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
     loop {
         terminal.draw(|f| ui::draw(f, &mut app))?;
 
-        // Process chat responses immediately if streaming (for smooth experience)
-        if app.current_screen == CurrentScreen::Chat
-            && app.chat_state.current_session().is_streaming
-        {
-            app.process_chat_response().await;
-        }
+        // Chat sessions keep generating in the background, whichever screen is shown
+        app.process_chat_responses().await;
+        app.process_compare_responses();
+        app.process_tool_batches().await;
+        app.process_tool_check();
+        app.process_indexing();
+        app.process_retrievals().await;
+        app.process_file_search();
+        app.process_model_inspector();
+        app.process_model_info();
+
+        // Drain pull progress events so downloads never block the UI
+        app.process_downloads().await;
+        app.process_model_create().await;
 
         tokio::select! {
             _ = tick_interval.tick() => {
@@ -70,56 +115,143 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
             }
             _ = tokio::time::sleep(Duration::from_millis(10)) => {
                 if event::poll(Duration::from_millis(0))? {
-                    if let Event::Key(key) = event::read()? {
+                    let event = event::read()?;
+                    match &event {
+                        Event::Paste(text) => app.handle_paste(text),
+                        Event::Mouse(mouse) => app.handle_mouse(*mouse),
+                        _ => {}
+                    }
+                    if let Event::Key(key) = event {
                         match app.current_screen {
                             CurrentScreen::Help => {
                                 if key.code == KeyCode::Esc || key.code == KeyCode::Char('q') {
                                     app.current_screen = CurrentScreen::Dashboard;
                                 }
                             }
+                            CurrentScreen::Chat if app.pending_tool_batch().is_some() => {
+                                app.handle_tool_approval_key(key);
+                            }
+                            CurrentScreen::Chat if app.image_picker.visible => {
+                                app.handle_image_picker_key(key);
+                            }
+                            CurrentScreen::Chat if app.session_browser.visible => {
+                                app.handle_session_browser_key(key);
+                            }
+                            CurrentScreen::Chat if app.preset_picker.visible => {
+                                app.handle_preset_picker_key(key);
+                            }
+                            CurrentScreen::Chat if app.options_panel.visible => {
+                                app.handle_options_panel_key(key);
+                            }
+                            CurrentScreen::Chat if app.code_block_picker.visible => {
+                                app.handle_code_block_picker_key(key);
+                            }
+                            CurrentScreen::Chat
+                                if app.chat_state.view.search.as_ref().is_some_and(|s| s.editing) =>
+                            {
+                                app.handle_transcript_search_key(key);
+                            }
+                            CurrentScreen::Chat
+                                if key.code == KeyCode::Char('c')
+                                    && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                            {
+                                app.stop_generation();
+                            }
                             CurrentScreen::Chat => {
                                 // Handle chat-specific input
                                 match app.chat_state.input_mode {
                                     InputMode::Editing => {
+                                        app.handle_chat_input_key(key).await;
+                                    }
+                                    InputMode::Normal => {
+                                        let searching = app.chat_state.view.search.is_some();
                                         match key.code {
-                                            KeyCode::Enter => {
-                                                app.chat_state.current_session().reset_history_navigation();
-                                                app.send_chat_message().await;
+                                            KeyCode::PageUp => {
+                                                app.chat_state.view.page(-1);
                                             }
-                                            KeyCode::Esc => {
-                                                app.chat_state.current_session().reset_history_navigation();
-                                                app.toggle_chat_input_mode();
+                                            KeyCode::PageDown => {
+                                                app.chat_state.view.page(1);
                                             }
-                                            KeyCode::Backspace => {
-                                                app.chat_state.current_session().reset_history_navigation();
-                                                app.handle_chat_backspace();
+                                            KeyCode::Home => {
+                                                app.chat_state.view.scroll_to_top();
                                             }
-                                            KeyCode::Up => {
-                                                app.chat_state.current_session().navigate_history_up();
+                                            KeyCode::End => {
+                                                app.chat_state.view.scroll = None;
                                             }
-                                            KeyCode::Down => {
-                                                app.chat_state.current_session().navigate_history_down();
+                                            KeyCode::Char('/') => {
+                                                app.chat_state.view.start_search();
                                             }
-                                            KeyCode::Char(c) => {
-                                                app.chat_state.current_session().reset_history_navigation();
-                                                app.handle_chat_input(c);
+                                            KeyCode::Char('n') if searching => {
+                                                app.chat_state.view.next_match(1);
+                                            }
+                                            KeyCode::Char('N') if searching => {
+                                                app.chat_state.view.next_match(-1);
+                                            }
+                                            KeyCode::Esc if searching => {
+                                                app.chat_state.view.search = None;
                                             }
-                                            _ => {}
-                                        }
-                                    }
-                                    InputMode::Normal => {
-                                        match key.code {
                                             KeyCode::Char('i' | 'e') => {
                                                 app.toggle_chat_input_mode();
                                             }
                                             KeyCode::Char('c') => {
-                                                app.chat_state.current_session().clear_session();
+                                                app.clear_chat_session();
                                             }
                                             KeyCode::Char('m') => {
                                                 app.chat_state.toggle_model_selector();
                                             }
                                             KeyCode::Char('n') => {
-                                                app.chat_state.new_session();
+                                                app.new_chat_session();
+                                            }
+                                            KeyCode::Char('b') => {
+                                                app.toggle_session_browser();
+                                            }
+                                            KeyCode::Char('s') => {
+                                                app.chat_state.show_sidebar = !app.chat_state.show_sidebar;
+                                            }
+                                            KeyCode::Char('P') => {
+                                                app.toggle_preset_picker();
+                                            }
+                                            KeyCode::Char('o') => {
+                                                app.toggle_options_panel();
+                                            }
+                                            KeyCode::Char('y') => {
+                                                app.toggle_code_block_picker();
+                                            }
+                                            KeyCode::Char('a') => {
+                                                app.open_image_picker();
+                                            }
+                                            KeyCode::Char('g') => {
+                                                app.regenerate_reply().await;
+                                            }
+                                            KeyCode::Char('C') => {
+                                                app.open_compare();
+                                            }
+                                            KeyCode::Char('E') => {
+                                                app.edit_message_in_view();
+                                            }
+                                            KeyCode::Char(',') => {
+                                                app.switch_branch_in_view(-1);
+                                            }
+                                            KeyCode::Char('.') => {
+                                                app.switch_branch_in_view(1);
+                                            }
+                                            KeyCode::Char(']') => {
+                                                app.switch_chat_session(1);
+                                            }
+                                            KeyCode::Char('[') => {
+                                                app.switch_chat_session(-1);
+                                            }
+                                            KeyCode::Char('J') => {
+                                                app.move_chat_session(1);
+                                            }
+                                            KeyCode::Char('K') => {
+                                                app.move_chat_session(-1);
+                                            }
+                                            KeyCode::Char('w') => {
+                                                app.close_chat_session();
+                                            }
+                                            KeyCode::Char('d') => {
+                                                app.duplicate_chat_session();
                                             }
                                             KeyCode::Tab => {
                                                 app.next_tab();
@@ -132,18 +264,14 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                     }
                                     InputMode::ModelSelection => {
                                         match key.code {
-                                            KeyCode::Up => {
-                                                if app.chat_state.selected_model_index > 0 {
-                                                    app.chat_state.selected_model_index -= 1;
-                                                }
+                                            KeyCode::Up if app.chat_state.selected_model_index > 0 => {
+                                                app.chat_state.selected_model_index -= 1;
                                             }
-                                            KeyCode::Down => {
-                                                if app.chat_state.selected_model_index < app.chat_state.available_models.len() - 1 {
-                                                    app.chat_state.selected_model_index += 1;
-                                                }
+                                            KeyCode::Down if app.chat_state.selected_model_index + 1 < app.chat_state.available_models.len() => {
+                                                app.chat_state.selected_model_index += 1;
                                             }
                                             KeyCode::Enter => {
-                                                app.chat_state.select_model();
+                                                app.select_chat_model();
                                             }
                                             KeyCode::Esc => {
                                                 app.chat_state.toggle_model_selector();
@@ -153,6 +281,64 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                     }
                                 }
                             }
+                            CurrentScreen::Compare if app.compare.picker.is_some() => {
+                                app.handle_compare_picker_key(key);
+                            }
+                            CurrentScreen::Compare => {
+                                app.handle_compare_key(key);
+                            }
+                            CurrentScreen::ToolCheck => {
+                                app.handle_tool_check_key(key);
+                            }
+                            CurrentScreen::ModelfileEditor
+                                if app
+                                    .modelfile_editor
+                                    .as_ref()
+                                    .is_some_and(|e| e.library.is_some()) =>
+                            {
+                                app.handle_modelfile_library_key(key);
+                            }
+                            CurrentScreen::ModelfileEditor => {
+                                match key.code {
+                                    KeyCode::Esc => {
+                                        app.close_modelfile_editor();
+                                    }
+                                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                        app.create_model_from_editor();
+                                    }
+                                    KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                        app.open_modelfile_library();
+                                    }
+                                    KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                        app.format_modelfile();
+                                    }
+                                    KeyCode::Tab => {
+                                        if let Some(editor) = &mut app.modelfile_editor {
+                                            editor.toggle_focus();
+                                        }
+                                    }
+                                    _ => {
+                                        app.handle_modelfile_editor_key(key);
+                                    }
+                                }
+                            }
+                            _ if app.show_pull_dialog => {
+                                match key.code {
+                                    KeyCode::Enter => {
+                                        app.on_enter();
+                                    }
+                                    KeyCode::Esc => {
+                                        app.show_pull_dialog = false;
+                                    }
+                                    KeyCode::Backspace => {
+                                        app.pull_model_name.pop();
+                                    }
+                                    KeyCode::Char(c) => {
+                                        app.pull_model_name.push(c);
+                                    }
+                                    _ => {}
+                                }
+                            }
                             _ => {
                                 match key.code {
                                     KeyCode::Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
@@ -205,6 +391,7 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                             }
                                         } else {
                                             app.on_up();
+                                            app.sync_model_inspector();
                                         }
                                     }
                                     KeyCode::Down => {
@@ -221,6 +408,7 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                             }
                                         } else {
                                             app.on_down();
+                                            app.sync_model_inspector();
                                         }
                                     }
                                     KeyCode::Enter => {
@@ -229,7 +417,7 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                         } else if app.current_screen == CurrentScreen::Models && app.models_tab_view == app::ModelsTabView::ApiExplorer {
                                             app.execute_selected_endpoint().await;
                                         } else {
-                                            app.on_enter().await;
+                                            app.on_enter();
                                         }
                                     }
                                     KeyCode::Char('r') => {
@@ -243,7 +431,13 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                         app.request_delete_model();
                                     }
                                     KeyCode::Char('i') if app.current_screen == CurrentScreen::Models => {
-                                        app.install_selected_available_model().await;
+                                        app.install_selected_available_model();
+                                    }
+                                    KeyCode::Char('e') if app.current_screen == CurrentScreen::Models && app.models_tab_view == app::ModelsTabView::Library => {
+                                        app.open_modelfile_editor();
+                                    }
+                                    KeyCode::Char('T') if app.current_screen == CurrentScreen::Models && app.models_tab_view == app::ModelsTabView::Library => {
+                                        app.open_tool_check();
                                     }
                                     KeyCode::Char('t') if app.current_screen == CurrentScreen::Models => {
                                         app.toggle_models_tab();
@@ -257,6 +451,30 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                     KeyCode::Char('c') if app.current_screen == CurrentScreen::Models && app.models_tab_view == app::ModelsTabView::ApiExplorer => {
                                         app.api_explorer_state.response_body.clear();
                                     }
+                                    KeyCode::Char('[') if app.current_screen == CurrentScreen::Models => {
+                                        app.downloads.select_prev();
+                                    }
+                                    KeyCode::Char(']') if app.current_screen == CurrentScreen::Models => {
+                                        app.downloads.select_next();
+                                    }
+                                    KeyCode::Char('x') if app.current_screen == CurrentScreen::Models => {
+                                        app.cancel_selected_download();
+                                    }
+                                    KeyCode::Char('z') if app.current_screen == CurrentScreen::Models => {
+                                        app.toggle_pause_selected_download();
+                                    }
+                                    KeyCode::Char('R') if app.current_screen == CurrentScreen::Models => {
+                                        app.retry_selected_download();
+                                    }
+                                    KeyCode::Char('X') if app.current_screen == CurrentScreen::Models => {
+                                        app.downloads.clear_finished();
+                                    }
+                                    KeyCode::Char('+') if app.current_screen == CurrentScreen::Models => {
+                                        app.change_download_concurrency(1);
+                                    }
+                                    KeyCode::Char('-') if app.current_screen == CurrentScreen::Models => {
+                                        app.change_download_concurrency(-1);
+                                    }
                                     KeyCode::Char('v') if app.current_screen == CurrentScreen::Models => {
                                         app.toggle_models_view();
                                     }
@@ -265,8 +483,16 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result
                                             app.show_pull_dialog = false;
                                         } else if app.show_delete_confirmation {
                                             app.cancel_delete();
+                                        } else if app.model_inspector.visible {
+                                            app.model_inspector.visible = false;
                                         }
                                     }
+                                    KeyCode::PageUp if app.model_inspector.visible => {
+                                        app.scroll_model_inspector(-10);
+                                    }
+                                    KeyCode::PageDown if app.model_inspector.visible => {
+                                        app.scroll_model_inspector(10);
+                                    }
                                     KeyCode::Char('y' | 'Y') if app.show_delete_confirmation => {
                                         app.confirm_delete_model().await;
                                     }
@@ -0,0 +1,1504 @@
+use crate::theme::TokyoNight;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// One rendered Markdown line, wrapped to the screen width at draw time.
+///
+/// `prefix` starts the first row (list bullet, quote bar, code gutter) and
+/// `continuation` starts every row after it, so wrapped text stays aligned.
+#[derive(Debug, Clone, Default)]
+pub struct MdLine {
+    prefix: Vec<Span<'static>>,
+    continuation: Vec<Span<'static>>,
+    spans: Vec<Span<'static>>,
+    rule: bool, // Horizontal rule, drawn across the full width
+}
+
+impl MdLine {
+    fn new(spans: Vec<Span<'static>>) -> Self {
+        Self {
+            spans,
+            ..Self::default()
+        }
+    }
+
+    fn with_prefix(mut self, prefix: Vec<Span<'static>>, continuation: Vec<Span<'static>>) -> Self {
+        self.prefix = prefix;
+        self.continuation = continuation;
+        self
+    }
+}
+
+/// Render a Markdown document. Unterminated constructs (an open code fence,
+/// a half-written table) render as far as they go, so partial responses work.
+pub fn render(src: &str) -> Vec<MdLine> {
+    let mut renderer = Renderer::default();
+    for line in src.lines() {
+        renderer.push_line(line);
+    }
+    renderer.finish()
+}
+
+/// Like [`render`], labelling code blocks `#first`, `#first + 1`, ... to match
+/// [`code_blocks`]. Returns the next unused number.
+pub fn render_numbered(src: &str, first: usize) -> (Vec<MdLine>, usize) {
+    let mut renderer = Renderer {
+        next_block: Some(first),
+        ..Renderer::default()
+    };
+    for line in src.lines() {
+        renderer.push_line(line);
+    }
+    let next = renderer.next_block.unwrap_or(first);
+    (renderer.finish(), next)
+}
+
+/// A fenced code block and its info-string language
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    pub lang: String,
+    pub code: String,
+}
+
+impl CodeBlock {
+    /// File extension for saving the block, from its language
+    pub fn extension(&self) -> &str {
+        match self.lang.as_str() {
+            "rust" => "rs",
+            "python" | "python3" => "py",
+            "javascript" => "js",
+            "typescript" => "ts",
+            "golang" => "go",
+            "bash" | "shell" | "zsh" | "console" => "sh",
+            "yml" => "yaml",
+            "c++" => "cpp",
+            "csharp" => "cs",
+            "" | "text" | "plaintext" => "txt",
+            lang => lang,
+        }
+    }
+}
+
+/// Every fenced code block in `src`, in order. An unterminated block at the
+/// end counts, the same as when it is rendered.
+pub fn code_blocks(src: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<((char, usize), CodeBlock)> = None;
+
+    for line in src.lines() {
+        let trimmed = line.trim();
+        match &mut open {
+            Some((marker, _)) if closes_fence(trimmed, *marker) => {
+                blocks.extend(open.take().map(|(_, block)| block));
+            }
+            Some((_, block)) => {
+                block.code.push_str(line);
+                block.code.push('\n');
+            }
+            None => {
+                if let Some((marker, info)) = opens_fence(trimmed) {
+                    let lang = info.split_whitespace().next().unwrap_or("").to_lowercase();
+                    open = Some((
+                        marker,
+                        CodeBlock {
+                            lang,
+                            code: String::new(),
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    blocks.extend(open.map(|(_, block)| block));
+    blocks
+}
+
+/// Markdown for a response that is still streaming.
+///
+/// Text before the last blank line outside a code fence can't change as more
+/// tokens arrive, so it is rendered once and cached; each frame only
+/// re-renders the paragraph being written.
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownStream {
+    rendered_len: usize,
+    lines: Vec<MdLine>,
+}
+
+impl MarkdownStream {
+    /// Cache everything in `src` that is complete
+    pub fn update(&mut self, src: &str) {
+        if src.len() < self.rendered_len {
+            *self = Self::default();
+        }
+        let boundary = stable_boundary(src, self.rendered_len);
+        if boundary > self.rendered_len {
+            self.lines.extend(render(&src[self.rendered_len..boundary]));
+            self.rendered_len = boundary;
+        }
+    }
+
+    pub fn lines(&self, src: &str) -> Vec<MdLine> {
+        let mut lines = self.lines.clone();
+        if let Some(tail) = src.get(self.rendered_len..) {
+            lines.extend(render(tail));
+        }
+        lines
+    }
+}
+
+/// Byte offset just past the last complete blank line that isn't inside a
+/// code fence, scanning from `start` (which is always such a boundary)
+fn stable_boundary(src: &str, start: usize) -> usize {
+    let mut boundary = start;
+    let mut fence: Option<(char, usize)> = None;
+    let mut offset = start;
+
+    for line in src[start..].split_inclusive('\n') {
+        offset += line.len();
+        if !line.ends_with('\n') {
+            break;
+        }
+        let trimmed = line.trim();
+        match fence {
+            Some(open) if closes_fence(trimmed, open) => fence = None,
+            Some(_) => {}
+            None => {
+                if let Some((marker, _)) = opens_fence(trimmed) {
+                    fence = Some(marker);
+                } else if trimmed.is_empty() {
+                    boundary = offset;
+                }
+            }
+        }
+    }
+    boundary
+}
+
+/// Finished replies, rendered and wrapped, so a long transcript isn't parsed
+/// and highlighted again on every frame. Keyed by a hash of the text, the
+/// first code block number and the width; entries a frame didn't use are
+/// dropped by `sweep`.
+#[derive(Debug, Clone, Default)]
+pub struct RenderCache {
+    entries: HashMap<(u64, usize, usize), CachedRender>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedRender {
+    lines: Vec<Line<'static>>,
+    next_block: usize,
+    used: bool,
+}
+
+impl RenderCache {
+    /// `render_numbered` wrapped to `width`, and the next code block number
+    pub fn get(&mut self, src: &str, first: usize, width: usize) -> (&[Line<'static>], usize) {
+        let mut hasher = DefaultHasher::new();
+        src.hash(&mut hasher);
+        let entry = self
+            .entries
+            .entry((hasher.finish(), first, width))
+            .or_insert_with(|| {
+                let (lines, next_block) = render_numbered(src, first);
+                CachedRender {
+                    lines: wrap(&lines, width),
+                    next_block,
+                    used: false,
+                }
+            });
+        entry.used = true;
+        (&entry.lines, entry.next_block)
+    }
+
+    /// Forget what wasn't drawn since the last sweep
+    pub fn sweep(&mut self) {
+        self.entries
+            .retain(|_, entry| std::mem::take(&mut entry.used));
+    }
+}
+
+/// Wrap rendered lines to `width` columns
+pub fn wrap(lines: &[MdLine], width: usize) -> Vec<Line<'static>> {
+    let mut out = Vec::new();
+    for line in lines {
+        wrap_line(line, width.max(8), &mut out);
+    }
+    out
+}
+
+fn wrap_line(line: &MdLine, width: usize, out: &mut Vec<Line<'static>>) {
+    if line.rule {
+        out.push(Line::from(Span::styled(
+            "─".repeat(width),
+            Style::default().fg(TokyoNight::DARK3),
+        )));
+        return;
+    }
+
+    let prefix_width = spans_width(&line.prefix);
+    let mut row: Vec<Span<'static>> = line.prefix.clone();
+    let mut row_width = prefix_width;
+    let mut row_start = prefix_width;
+
+    for span in &line.spans {
+        for piece in split_words(&span.content) {
+            let piece_width = piece.width();
+            let is_space = piece.chars().all(char::is_whitespace);
+
+            if row_width + piece_width > width && row_width > row_start {
+                out.push(Line::from(std::mem::take(&mut row)));
+                row = line.continuation.clone();
+                row_width = spans_width(&row);
+                row_start = row_width;
+                if is_space {
+                    continue;
+                }
+            }
+
+            if row_width + piece_width <= width {
+                row.push(Span::styled(piece.to_string(), span.style));
+                row_width += piece_width;
+                continue;
+            }
+
+            // A single word wider than the row: break it between characters
+            let mut chunk = String::new();
+            for c in piece.chars() {
+                let w = c.width().unwrap_or(0);
+                if row_width + w > width && row_width > row_start {
+                    row.push(Span::styled(std::mem::take(&mut chunk), span.style));
+                    out.push(Line::from(std::mem::take(&mut row)));
+                    row = line.continuation.clone();
+                    row_width = spans_width(&row);
+                    row_start = row_width;
+                }
+                chunk.push(c);
+                row_width += w;
+            }
+            row.push(Span::styled(chunk, span.style));
+        }
+    }
+    out.push(Line::from(row));
+}
+
+/// Split text into alternating runs of whitespace and non-whitespace
+fn split_words(text: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|s| s != space) {
+            pieces.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+fn spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(|s| s.content.width()).sum()
+}
+
+fn opens_fence(trimmed: &str) -> Option<((char, usize), &str)> {
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+    (len >= 3).then(|| ((marker, len), trimmed[len..].trim()))
+}
+
+fn closes_fence(trimmed: &str, (marker, len): (char, usize)) -> bool {
+    trimmed.chars().take_while(|c| *c == marker).count() >= len
+        && trimmed.chars().all(|c| c == marker)
+}
+
+struct Fence {
+    marker: (char, usize),
+    syntax: &'static Syntax,
+    state: CodeState,
+}
+
+#[derive(Default)]
+struct Renderer {
+    out: Vec<MdLine>,
+    fence: Option<Fence>,
+    table: Vec<String>,
+    next_block: Option<usize>, // Number for the next code block, when numbering
+}
+
+impl Renderer {
+    fn push_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+
+        if let Some(fence) = &mut self.fence {
+            if closes_fence(trimmed, fence.marker) {
+                self.fence = None;
+                self.out.push(MdLine::new(vec![Span::styled(
+                    "╰─",
+                    Style::default().fg(TokyoNight::DARK3),
+                )]));
+            } else {
+                let code = line.replace('\t', "    ");
+                let spans = highlight(&code, fence.syntax, &mut fence.state);
+                let gutter = vec![Span::styled("│ ", Style::default().fg(TokyoNight::DARK3))];
+                self.out
+                    .push(MdLine::new(spans).with_prefix(gutter.clone(), gutter));
+            }
+            return;
+        }
+
+        if let Some((marker, info)) = opens_fence(trimmed) {
+            self.flush_table();
+            let lang = info.split_whitespace().next().unwrap_or("").to_lowercase();
+            let mut header = vec![Span::styled("╭─", Style::default().fg(TokyoNight::DARK3))];
+            if let Some(number) = &mut self.next_block {
+                header.push(Span::styled(
+                    format!(" #{number}"),
+                    Style::default()
+                        .fg(TokyoNight::YELLOW)
+                        .add_modifier(Modifier::BOLD),
+                ));
+                *number += 1;
+            }
+            if !lang.is_empty() {
+                header.push(Span::styled(
+                    format!(" {lang}"),
+                    Style::default().fg(TokyoNight::DARK5),
+                ));
+            }
+            self.out.push(MdLine::new(header));
+            self.fence = Some(Fence {
+                marker,
+                syntax: syntax_for(&lang),
+                state: CodeState::Normal,
+            });
+            return;
+        }
+
+        if trimmed.starts_with('|') {
+            self.table.push(trimmed.to_string());
+            return;
+        }
+        self.flush_table();
+        self.push_block_line(line);
+    }
+
+    fn push_block_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            self.out.push(MdLine::default());
+            return;
+        }
+
+        // Headings
+        let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes)
+            && trimmed[hashes..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        {
+            let style = match hashes {
+                1 => Style::default()
+                    .fg(TokyoNight::MAGENTA)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                2 => Style::default()
+                    .fg(TokyoNight::BLUE)
+                    .add_modifier(Modifier::BOLD),
+                _ => Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD),
+            };
+            self.out
+                .push(MdLine::new(inline(trimmed[hashes..].trim(), style)));
+            return;
+        }
+
+        // Horizontal rules
+        let rule_chars: String = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+        if rule_chars.len() >= 3
+            && ['-', '*', '_']
+                .iter()
+                .any(|m| rule_chars.chars().all(|c| c == *m))
+        {
+            self.out.push(MdLine {
+                rule: true,
+                ..MdLine::default()
+            });
+            return;
+        }
+
+        // Block quotes, possibly nested
+        if trimmed.starts_with('>') {
+            let mut rest = trimmed;
+            let mut depth = 0;
+            while let Some(r) = rest.strip_prefix('>') {
+                depth += 1;
+                rest = r.trim_start();
+            }
+            let bar = vec![Span::styled(
+                "▎ ".repeat(depth),
+                Style::default().fg(TokyoNight::PURPLE),
+            )];
+            let style = Style::default()
+                .fg(TokyoNight::FG_DARK)
+                .add_modifier(Modifier::ITALIC);
+            self.out
+                .push(MdLine::new(inline(rest, style)).with_prefix(bar.clone(), bar));
+            return;
+        }
+
+        // Lists
+        let indent = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum::<usize>();
+        if let Some((marker, rest)) = list_item(trimmed, indent) {
+            let pad = " ".repeat(indent / 2 * 2);
+            let hanging = " ".repeat(pad.len() + marker.width());
+            self.out
+                .push(MdLine::new(inline(rest, Style::default())).with_prefix(
+                    vec![Span::styled(
+                        format!("{pad}{marker}"),
+                        Style::default().fg(TokyoNight::CYAN),
+                    )],
+                    vec![Span::raw(hanging)],
+                ));
+            return;
+        }
+
+        self.out
+            .push(MdLine::new(inline(trimmed, Style::default())));
+    }
+
+    fn flush_table(&mut self) {
+        let rows = std::mem::take(&mut self.table);
+        if rows.is_empty() {
+            return;
+        }
+        let cells: Vec<Vec<String>> = rows.iter().map(|row| split_row(row)).collect();
+        let has_header = cells.get(1).is_some_and(|row| is_separator_row(row));
+        if !has_header {
+            for row in rows {
+                self.push_block_line(&row);
+            }
+            return;
+        }
+
+        let align: Vec<Align> = cells[1].iter().map(|c| Align::from_separator(c)).collect();
+        let body: Vec<(bool, Vec<Vec<Span<'static>>>)> = cells
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(i, row)| {
+                let style = if i == 0 {
+                    Style::default()
+                        .fg(TokyoNight::BLUE)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                (i == 0, row.iter().map(|cell| inline(cell, style)).collect())
+            })
+            .collect();
+
+        let columns = body.iter().map(|(_, row)| row.len()).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for (_, row) in &body {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(spans_width(cell));
+            }
+        }
+
+        let border = Style::default().fg(TokyoNight::DARK3);
+        for (is_header, row) in body {
+            let mut spans = Vec::new();
+            for (i, width) in widths.iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(" │ ", border));
+                }
+                let cell = row.get(i).cloned().unwrap_or_default();
+                let gap = width - spans_width(&cell);
+                let (left, right) = match align.get(i).copied().unwrap_or(Align::Left) {
+                    Align::Left => (0, gap),
+                    Align::Right => (gap, 0),
+                    Align::Center => (gap / 2, gap - gap / 2),
+                };
+                spans.push(Span::raw(" ".repeat(left)));
+                spans.extend(cell);
+                spans.push(Span::raw(" ".repeat(right)));
+            }
+            self.out.push(MdLine::new(spans));
+
+            if is_header {
+                let rule = widths
+                    .iter()
+                    .map(|w| "─".repeat(*w))
+                    .collect::<Vec<_>>()
+                    .join("─┼─");
+                self.out.push(MdLine::new(vec![Span::styled(rule, border)]));
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<MdLine> {
+        self.flush_table();
+        self.out
+    }
+}
+
+/// The bullet to draw and the item text, for `- item`, `1. item` and `- [x] task`
+fn list_item(trimmed: &str, indent: usize) -> Option<(String, &str)> {
+    let (marker, rest) = if let Some(rest) = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))
+    {
+        let bullet = if (indent / 2).is_multiple_of(2) {
+            "• "
+        } else {
+            "◦ "
+        };
+        (bullet.to_string(), rest)
+    } else {
+        let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+        let rest = trimmed.get(digits..)?;
+        let rest = rest
+            .strip_prefix(". ")
+            .or_else(|| rest.strip_prefix(") "))?;
+        if digits == 0 || digits > 9 {
+            return None;
+        }
+        (format!("{}. ", &trimmed[..digits]), rest)
+    };
+
+    if let Some(task) = rest.strip_prefix("[ ] ") {
+        return Some((format!("{marker}☐ "), task));
+    }
+    if let Some(task) = rest
+        .strip_prefix("[x] ")
+        .or_else(|| rest.strip_prefix("[X] "))
+    {
+        return Some((format!("{marker}☑ "), task));
+    }
+    Some((marker, rest))
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    fn from_separator(cell: &str) -> Self {
+        match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => Self::Center,
+            (false, true) => Self::Right,
+            _ => Self::Left,
+        }
+    }
+}
+
+fn split_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = row.strip_suffix('|').unwrap_or(row);
+    row.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+fn is_separator_row(cells: &[String]) -> bool {
+    cells.iter().all(|cell| {
+        !cell.is_empty() && cell.contains('-') && cell.chars().all(|c| matches!(c, '-' | ':'))
+    })
+}
+
+/// Bold, italic, strikethrough, inline code and links
+fn inline(text: &str, base: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut buf = String::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+
+    let style = |bold: bool, italic: bool, strike: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if strike {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    };
+    let flush = |buf: &mut String, spans: &mut Vec<Span<'static>>, style: Style| {
+        if !buf.is_empty() {
+            spans.push(Span::styled(std::mem::take(buf), style));
+        }
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(char::is_ascii_punctuation) => {
+                buf.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|c| **c == '`').count();
+                let close = (i + run..chars.len()).find(|&j| {
+                    chars[j..].iter().take_while(|c| **c == '`').count() == run
+                        && chars[j - 1] != '`'
+                });
+                if let Some(end) = close {
+                    flush(&mut buf, &mut spans, style(bold, italic, strike));
+                    let code: String = chars[i + run..end].iter().collect();
+                    spans.push(Span::styled(
+                        code,
+                        Style::default()
+                            .fg(TokyoNight::GREEN1)
+                            .bg(TokyoNight::BG_HIGHLIGHT),
+                    ));
+                    i = end + run;
+                } else {
+                    buf.extend(&chars[i..i + run]);
+                    i += run;
+                }
+                continue;
+            }
+            '*' | '_' | '~' => {
+                let run = chars[i..].iter().take_while(|ch| **ch == c).count();
+                let next = chars.get(i + run).copied();
+                let (can_open, can_close) = flanking(c, prev, next);
+                // A lone `*` in `2*3 = 6` would otherwise run to the end of the line
+                let can_open = can_open && has_closer(&chars, i + run, c);
+
+                let toggles = |on: bool| if on { can_close } else { can_open };
+                let before = style(bold, italic, strike);
+                let mut used = 0;
+                if c == '~' {
+                    if run == 2 && toggles(strike) {
+                        strike = !strike;
+                        used = 2;
+                    }
+                } else {
+                    if run >= 2 && toggles(bold) {
+                        bold = !bold;
+                        used += 2;
+                    }
+                    if run % 2 == 1 && toggles(italic) {
+                        italic = !italic;
+                        used += 1;
+                    }
+                }
+
+                if used == 0 {
+                    buf.extend(&chars[i..i + run]);
+                } else {
+                    flush(&mut buf, &mut spans, before);
+                    buf.extend(&chars[i + used..i + run]);
+                }
+                i += run;
+                continue;
+            }
+            '[' => {
+                if let Some((label, url, len)) = parse_link(&chars[i..]) {
+                    flush(&mut buf, &mut spans, style(bold, italic, strike));
+                    let link = Style::default()
+                        .fg(TokyoNight::BLUE)
+                        .add_modifier(Modifier::UNDERLINED);
+                    spans.push(Span::styled(label.clone(), link));
+                    if url != label {
+                        spans.push(Span::styled(
+                            format!(" ({url})"),
+                            Style::default().fg(TokyoNight::DARK5),
+                        ));
+                    }
+                    i += len;
+                    continue;
+                }
+                buf.push(c);
+            }
+            _ => buf.push(c),
+        }
+        i += 1;
+    }
+    flush(&mut buf, &mut spans, style(bold, italic, strike));
+    spans
+}
+
+/// Whether a run of `c` between `prev` and `next` can open and close emphasis
+fn flanking(c: char, prev: Option<char>, next: Option<char>) -> (bool, bool) {
+    let mut can_open = next.is_some_and(|n| !n.is_whitespace());
+    let mut can_close = prev.is_some_and(|p| !p.is_whitespace());
+    match c {
+        // snake_case isn't emphasis
+        '_' => {
+            can_open &= !prev.is_some_and(char::is_alphanumeric);
+            can_close &= !next.is_some_and(char::is_alphanumeric);
+        }
+        // Nor is 2*3*4
+        '*' if prev.is_some_and(char::is_alphanumeric)
+            && next.is_some_and(char::is_alphanumeric) =>
+        {
+            return (false, false);
+        }
+        _ => {}
+    }
+    (can_open, can_close)
+}
+
+/// Whether a run of `c` from `from` on could close emphasis
+fn has_closer(chars: &[char], from: usize, c: char) -> bool {
+    let mut j = from;
+    while j < chars.len() {
+        let run = chars[j..].iter().take_while(|ch| **ch == c).count();
+        if run == 0 {
+            j += 1;
+            continue;
+        }
+        if flanking(c, chars.get(j - 1).copied(), chars.get(j + run).copied()).1 {
+            return true;
+        }
+        j += run;
+    }
+    false
+}
+
+/// `[label](url)` at the start of `chars`, with the number of chars it spans
+fn parse_link(chars: &[char]) -> Option<(String, String, usize)> {
+    let label_end = chars.iter().position(|c| *c == ']')?;
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+    let url_len = chars[label_end + 2..].iter().position(|c| *c == ')')?;
+    let label: String = chars[1..label_end].iter().collect();
+    let url: String = chars[label_end + 2..label_end + 2 + url_len]
+        .iter()
+        .collect();
+    Some((label, url, label_end + 3 + url_len))
+}
+
+/// How to highlight one language. Deliberately small: enough to make code
+/// answers readable, not a full grammar.
+struct Syntax {
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    multiline_strings: &'static [&'static str],
+    /// Keys before this character are highlighted (JSON, YAML, TOML)
+    key_separator: Option<char>,
+    case_insensitive: bool,
+    /// `$NAME` is a variable
+    variables: bool,
+    /// Lifetimes, char literals and `macro!` calls
+    rust: bool,
+}
+
+const PLAIN: Syntax = Syntax {
+    keywords: &[],
+    literals: &[],
+    line_comments: &[],
+    block_comment: None,
+    quotes: &[],
+    multiline_strings: &[],
+    key_separator: None,
+    case_insensitive: false,
+    variables: false,
+    rust: false,
+};
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    literals: &["true", "false"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+    rust: true,
+    ..PLAIN
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+        "yield",
+    ],
+    literals: &["True", "False", "None", "self"],
+    line_comments: &["#"],
+    quotes: &['"', '\''],
+    multiline_strings: &["\"\"\"", "'''"],
+    ..PLAIN
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    keywords: &[
+        "as",
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "implements",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "of",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "return",
+        "static",
+        "switch",
+        "this",
+        "throw",
+        "try",
+        "type",
+        "typeof",
+        "var",
+        "while",
+        "yield",
+    ],
+    literals: &["true", "false", "null", "undefined", "NaN"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    multiline_strings: &["`"],
+    ..PLAIN
+};
+
+const GO: Syntax = Syntax {
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "fallthrough",
+        "for",
+        "func",
+        "go",
+        "goto",
+        "if",
+        "import",
+        "interface",
+        "map",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "type",
+        "var",
+    ],
+    literals: &["true", "false", "nil", "iota"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    multiline_strings: &["`"],
+    ..PLAIN
+};
+
+const C_LIKE: Syntax = Syntax {
+    keywords: &[
+        "abstract",
+        "auto",
+        "bool",
+        "boolean",
+        "break",
+        "case",
+        "catch",
+        "char",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "double",
+        "else",
+        "enum",
+        "extends",
+        "extern",
+        "final",
+        "float",
+        "for",
+        "goto",
+        "if",
+        "implements",
+        "import",
+        "int",
+        "interface",
+        "long",
+        "namespace",
+        "new",
+        "override",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "short",
+        "signed",
+        "sizeof",
+        "static",
+        "struct",
+        "switch",
+        "template",
+        "this",
+        "throw",
+        "try",
+        "typedef",
+        "typename",
+        "union",
+        "unsigned",
+        "using",
+        "var",
+        "virtual",
+        "void",
+        "volatile",
+        "while",
+    ],
+    literals: &["true", "false", "null", "nullptr", "NULL"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+    ..PLAIN
+};
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "until", "while",
+    ],
+    literals: &["true", "false"],
+    line_comments: &["#"],
+    quotes: &['"', '\''],
+    variables: true,
+    ..PLAIN
+};
+
+const JSON: Syntax = Syntax {
+    literals: &["true", "false", "null"],
+    quotes: &['"'],
+    key_separator: Some(':'),
+    ..PLAIN
+};
+
+const YAML: Syntax = Syntax {
+    literals: &["true", "false", "null", "yes", "no", "~"],
+    line_comments: &["#"],
+    quotes: &['"', '\''],
+    key_separator: Some(':'),
+    ..PLAIN
+};
+
+const TOML: Syntax = Syntax {
+    literals: &["true", "false"],
+    line_comments: &["#", ";"],
+    quotes: &['"', '\''],
+    multiline_strings: &["\"\"\""],
+    key_separator: Some('='),
+    ..PLAIN
+};
+
+const SQL: Syntax = Syntax {
+    keywords: &[
+        "all",
+        "alter",
+        "and",
+        "as",
+        "asc",
+        "by",
+        "create",
+        "delete",
+        "desc",
+        "distinct",
+        "drop",
+        "exists",
+        "foreign",
+        "from",
+        "group",
+        "having",
+        "in",
+        "index",
+        "inner",
+        "insert",
+        "into",
+        "is",
+        "join",
+        "key",
+        "left",
+        "like",
+        "limit",
+        "not",
+        "on",
+        "or",
+        "order",
+        "outer",
+        "primary",
+        "references",
+        "right",
+        "select",
+        "set",
+        "table",
+        "union",
+        "update",
+        "values",
+        "where",
+        "with",
+    ],
+    literals: &["true", "false", "null"],
+    line_comments: &["--"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['\''],
+    case_insensitive: true,
+    ..PLAIN
+};
+
+fn syntax_for(lang: &str) -> &'static Syntax {
+    match lang {
+        "rust" | "rs" => &RUST,
+        "python" | "py" | "python3" => &PYTHON,
+        "javascript" | "js" | "jsx" | "typescript" | "ts" | "tsx" | "mjs" => &JAVASCRIPT,
+        "go" | "golang" => &GO,
+        "c" | "h" | "cpp" | "c++" | "cc" | "hpp" | "java" | "cs" | "csharp" | "kotlin" => &C_LIKE,
+        "sh" | "bash" | "shell" | "zsh" | "console" => &SHELL,
+        "json" | "jsonc" => &JSON,
+        "yaml" | "yml" => &YAML,
+        "toml" | "ini" => &TOML,
+        "sql" => &SQL,
+        _ => &PLAIN,
+    }
+}
+
+/// Constructs that continue onto the next line of a code block
+enum CodeState {
+    Normal,
+    BlockComment(&'static str),
+    String(&'static str),
+}
+
+fn highlight(line: &str, syntax: &Syntax, state: &mut CodeState) -> Vec<Span<'static>> {
+    let comment = Style::default()
+        .fg(TokyoNight::COMMENT)
+        .add_modifier(Modifier::ITALIC);
+    let string = Style::default().fg(TokyoNight::GREEN1);
+    let plain = Style::default().fg(TokyoNight::FG);
+
+    let mut spans = Vec::new();
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        match *state {
+            CodeState::BlockComment(end) => {
+                let len = rest.find(end).map_or(rest.len(), |i| {
+                    *state = CodeState::Normal;
+                    i + end.len()
+                });
+                spans.push(Span::styled(rest[..len].to_string(), comment));
+                rest = &rest[len..];
+                continue;
+            }
+            CodeState::String(delim) => {
+                let len = rest.find(delim).map_or(rest.len(), |i| {
+                    *state = CodeState::Normal;
+                    i + delim.len()
+                });
+                spans.push(Span::styled(rest[..len].to_string(), string));
+                rest = &rest[len..];
+                continue;
+            }
+            CodeState::Normal => {}
+        }
+
+        if syntax.line_comments.iter().any(|p| rest.starts_with(p)) {
+            spans.push(Span::styled(rest.to_string(), comment));
+            break;
+        }
+        if let Some((start, end)) = syntax.block_comment.filter(|(s, _)| rest.starts_with(s)) {
+            spans.push(Span::styled(start.to_string(), comment));
+            rest = &rest[start.len()..];
+            *state = CodeState::BlockComment(end);
+            continue;
+        }
+        if let Some(delim) = syntax
+            .multiline_strings
+            .iter()
+            .find(|d| rest.starts_with(*d))
+        {
+            spans.push(Span::styled(delim.to_string(), string));
+            rest = &rest[delim.len()..];
+            *state = CodeState::String(delim);
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or(' ');
+
+        if syntax.rust && c == '\'' {
+            // 'a' and '\n' are chars; 'a on its own is a lifetime
+            let chars: Vec<char> = rest.chars().take(4).collect();
+            let char_len = match chars.as_slice() {
+                ['\'', '\\', _, '\'', ..] => Some(4),
+                ['\'', _, '\'', ..] => Some(3),
+                _ => None,
+            };
+            if let Some(n) = char_len {
+                let len: usize = rest.chars().take(n).map(char::len_utf8).sum();
+                spans.push(Span::styled(rest[..len].to_string(), string));
+                rest = &rest[len..];
+            } else {
+                let len = 1 + word_len(&rest[1..]);
+                spans.push(Span::styled(
+                    rest[..len].to_string(),
+                    Style::default()
+                        .fg(TokyoNight::ORANGE)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+                rest = &rest[len..];
+            }
+            continue;
+        }
+
+        if syntax.quotes.contains(&c) {
+            let len = quoted_len(rest, c);
+            let style = if is_key(&rest[len..], syntax) {
+                Style::default().fg(TokyoNight::BLUE)
+            } else {
+                string
+            };
+            spans.push(Span::styled(rest[..len].to_string(), style));
+            rest = &rest[len..];
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let len = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+            spans.push(Span::styled(
+                rest[..len].to_string(),
+                Style::default().fg(TokyoNight::ORANGE),
+            ));
+            rest = &rest[len..];
+            continue;
+        }
+
+        if c == '$' && syntax.variables {
+            let len = 1 + rest[1..]
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '{' || ch == '}'))
+                .unwrap_or(rest.len() - 1);
+            spans.push(Span::styled(
+                rest[..len].to_string(),
+                Style::default().fg(TokyoNight::CYAN),
+            ));
+            rest = &rest[len..];
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' || c == '~' {
+            let len = word_len(rest).max(c.len_utf8());
+            let word = &rest[..len];
+            let after = &rest[len..];
+            let matches = |list: &[&str]| {
+                list.iter().any(|k| {
+                    if syntax.case_insensitive {
+                        k.eq_ignore_ascii_case(word)
+                    } else {
+                        *k == word
+                    }
+                })
+            };
+
+            let (len, style) = if matches(syntax.keywords) {
+                (len, Style::default().fg(TokyoNight::MAGENTA))
+            } else if matches(syntax.literals) {
+                (len, Style::default().fg(TokyoNight::ORANGE))
+            } else if syntax.rust && after.starts_with('!') {
+                (len + 1, Style::default().fg(TokyoNight::CYAN))
+            } else if is_key(after, syntax) || after.trim_start().starts_with('(') {
+                (len, Style::default().fg(TokyoNight::BLUE))
+            } else if c.is_uppercase() && !syntax.keywords.is_empty() {
+                (len, Style::default().fg(TokyoNight::CYAN))
+            } else {
+                (len, plain)
+            };
+            spans.push(Span::styled(rest[..len].to_string(), style));
+            rest = &rest[len..];
+            continue;
+        }
+
+        let len = c.len_utf8();
+        let style = if "=+-*/<>!&|%^?:".contains(c) {
+            Style::default().fg(TokyoNight::BLUE5)
+        } else {
+            plain
+        };
+        spans.push(Span::styled(rest[..len].to_string(), style));
+        rest = &rest[len..];
+    }
+    spans
+}
+
+fn word_len(s: &str) -> usize {
+    s.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(s.len())
+}
+
+/// Length of a string literal starting at `s`, or the rest of the line if unterminated
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return i + c.len_utf8(),
+            _ => escaped = false,
+        }
+    }
+    s.len()
+}
+
+fn is_key(after: &str, syntax: &Syntax) -> bool {
+    syntax
+        .key_separator
+        .is_some_and(|sep| after.trim_start().starts_with(sep))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = "# Plan\n\nSome **bold** text and `code`, then a list:\n\n- one\n  - nested *item*\n- [x] done\n\n```rust\nfn main() {\n\n    println!(\"hi\"); // not a boundary\n}\n```\n\n| Name | Count |\n|:-----|------:|\n| a    | 1     |\n| bb   | 22    |\n\n> quoted\n> > twice\n\n---\n\nThe end, with a [link](https://example.com).\n";
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(text).collect()
+    }
+
+    fn italic(src: &str) -> Vec<String> {
+        inline(src, Style::default())
+            .into_iter()
+            .filter(|s| s.style.add_modifier.contains(Modifier::ITALIC))
+            .map(|s| s.content.to_string())
+            .collect()
+    }
+
+    /// Feed `src` to a stream `size` bytes at a time (rounded up to a char
+    /// boundary), checking every step against a full render
+    fn assert_streams_like_render(src: &str, size: usize) {
+        let mut stream = MarkdownStream::default();
+        let mut end = 0;
+        while end < src.len() {
+            end = (end + size).min(src.len());
+            while !src.is_char_boundary(end) {
+                end += 1;
+            }
+            let partial = &src[..end];
+            stream.update(partial);
+            assert_eq!(
+                wrap(&stream.lines(partial), 40),
+                wrap(&render(partial), 40),
+                "{size}-byte pieces, after {partial:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn streaming_matches_rendering_at_once() {
+        let unterminated = "Intro\n\n```py\nx = 1\n\n\ny = 2\n";
+        let wide = "## 名前\n\n| 名前 | ✓ |\n|---|---|\n| 東京 | y |\n\nemoji 🦙 and *ü*\n";
+        for src in [REPLY, unterminated, wide] {
+            for size in [1, 2, 3, 7, 16, 64, src.len()] {
+                assert_streams_like_render(src, size);
+            }
+        }
+    }
+
+    #[test]
+    fn stable_boundary_skips_blank_lines_in_fences() {
+        let src = "a\n\n```\nx\n\ny\n";
+        assert_eq!(stable_boundary(src, 0), 3);
+        let closed = "a\n\n```\nx\n\ny\n```\n\nb";
+        assert_eq!(stable_boundary(closed, 0), closed.len() - 1);
+        // The blank line has to be complete
+        assert_eq!(stable_boundary("a\n", 0), 0);
+    }
+
+    #[test]
+    fn intraword_markers_are_not_emphasis() {
+        assert!(italic("call snake_case_idents here").is_empty());
+        assert!(italic("2*3*4 is 24").is_empty());
+        assert!(italic("2*3 = 6, not 5").is_empty());
+        assert!(italic("a * b * c").is_empty());
+        assert_eq!(
+            italic("this is *italic* and _this_ too"),
+            ["italic", "this"]
+        );
+        assert_eq!(italic("**bold** then *it*"), ["it"]);
+    }
+
+    #[test]
+    fn unterminated_fence_renders_as_code() {
+        let src = "Look:\n\n```rust\nfn main() {\n    let x = 1;";
+        let lines = texts(&wrap(&render(src), 80));
+        assert_eq!(lines[2], "╭─ rust");
+        assert_eq!(lines[3], "│ fn main() {");
+        assert_eq!(lines[4], "│     let x = 1;");
+        assert_eq!(lines.len(), 5, "no closing rule: {lines:?}");
+
+        let blocks = code_blocks(src);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].code, "fn main() {\n    let x = 1;\n");
+    }
+
+    #[test]
+    fn longer_fences_contain_shorter_ones() {
+        let src = "````markdown\n```rust\nfn f() {}\n```\n````\n~~~\n```\n~~~\nafter";
+        let blocks = code_blocks(src);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].lang, "markdown");
+        assert_eq!(blocks[0].code, "```rust\nfn f() {}\n```\n");
+        assert_eq!(blocks[1].code, "```\n");
+
+        let lines = texts(&wrap(&render(src), 80));
+        assert_eq!(
+            lines,
+            [
+                "╭─ markdown",
+                "│ ```rust",
+                "│ fn f() {}",
+                "│ ```",
+                "╰─",
+                "╭─",
+                "│ ```",
+                "╰─",
+                "after",
+            ]
+        );
+    }
+
+    #[test]
+    fn table_columns_line_up_with_wide_characters() {
+        let src = "| 名前 | Count | ✓ |\n|:---:|---:|---|\n| 東京タワー | 1 | 🦙 |\n| a | 22 | x |";
+        let lines = wrap(&render(src), 80);
+        assert_eq!(lines.len(), 4);
+        let widths: Vec<usize> = lines.iter().map(Line::width).collect();
+        assert!(widths.iter().all(|w| *w == widths[0]), "{widths:?}");
+
+        // Every row's separators sit in the same columns as the header rule's crossings
+        let rule = text(&lines[1]);
+        let crossings: Vec<usize> = rule
+            .char_indices()
+            .filter(|(_, c)| *c == '┼')
+            .map(|(i, _)| rule[..i].width())
+            .collect();
+        for line in [&lines[0], &lines[2], &lines[3]] {
+            let row = text(line);
+            let bars: Vec<usize> = row
+                .char_indices()
+                .filter(|(_, c)| *c == '│')
+                .map(|(i, _)| row[..i].width())
+                .collect();
+            assert_eq!(bars, crossings, "{row:?}");
+        }
+    }
+
+    #[test]
+    fn wrap_line_at_width_one_puts_one_char_per_row() {
+        let mut out = Vec::new();
+        wrap_line(&MdLine::new(vec![Span::raw("ab cd")]), 1, &mut out);
+        assert_eq!(texts(&out), ["a", "b", "c", "d"]);
+
+        // Too wide to fit, but still shown, one per row
+        let mut out = Vec::new();
+        wrap_line(&MdLine::new(vec![Span::raw("名前")]), 1, &mut out);
+        assert_eq!(texts(&out), ["名", "前"]);
+
+        // The prefix alone is wider than the row; each row still takes a char
+        let bullet = vec![Span::raw("• ")];
+        let line = MdLine::new(vec![Span::raw("xy")]).with_prefix(bullet, vec![Span::raw("  ")]);
+        let mut out = Vec::new();
+        wrap_line(&line, 1, &mut out);
+        assert_eq!(texts(&out), ["• x", "  y"]);
+
+        let mut out = Vec::new();
+        wrap_line(&MdLine::new(Vec::new()), 1, &mut out);
+        assert_eq!(texts(&out), [""]);
+    }
+
+    #[test]
+    fn numbering_matches_the_code_block_picker() {
+        let sources = [
+            REPLY,
+            "```\nplain\n```\n\ntext\n\n~~~python\nx = 1\n~~~\n",
+            "````md\n```rust\ninner\n```\n````\n\n```sh\nls\n",
+            "| a |\n|---|\n```json\n{}\n```",
+            "no code here",
+        ];
+        for src in sources {
+            let (lines, next) = render_numbered(src, 3);
+            let headers: Vec<String> = texts(&wrap(&lines, 80))
+                .into_iter()
+                .filter(|line| line.starts_with("╭─"))
+                .collect();
+            let expected: Vec<String> = code_blocks(src)
+                .iter()
+                .enumerate()
+                .map(|(i, block)| match block.lang.as_str() {
+                    "" => format!("╭─ #{}", i + 3),
+                    lang => format!("╭─ #{} {lang}", i + 3),
+                })
+                .collect();
+            assert_eq!(headers, expected, "{src:?}");
+            assert_eq!(next, 3 + expected.len());
+        }
+    }
+}
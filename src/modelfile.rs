@@ -0,0 +1,1154 @@
+//! Parser and serializer for Ollama Modelfiles.
+//!
+//! Every node keeps the exact source text it was parsed from, so
+//! `parse(src).to_string() == src` for any input, comments and odd
+//! spacing included. Nodes built in code are rendered canonically.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Instructions understood by Ollama, with a one-line description
+pub const INSTRUCTIONS: &[(&str, &str)] = &[
+    (
+        "FROM",
+        "Base model, GGUF file or Safetensors directory to build on (required)",
+    ),
+    (
+        "PARAMETER",
+        "Default generation parameter: PARAMETER <key> <value>",
+    ),
+    ("TEMPLATE", "Full prompt template in Go template syntax"),
+    ("SYSTEM", "System prompt applied to every conversation"),
+    (
+        "ADAPTER",
+        "Path to a (Q)LoRA adapter applied to the base model",
+    ),
+    ("LICENSE", "License text shipped with the model"),
+    (
+        "MESSAGE",
+        "Seed conversation turn: MESSAGE <system|user|assistant> <text>",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    Int,
+    Float,
+    Bool,
+    Text,
+}
+
+/// Parameters Ollama accepts in a Modelfile, with their value type
+pub const PARAMETERS: &[(&str, ParamKind, &str)] = &[
+    (
+        "mirostat",
+        ParamKind::Int,
+        "Mirostat sampling (0 = off, 1 = v1, 2 = v2)",
+    ),
+    ("mirostat_eta", ParamKind::Float, "Mirostat learning rate"),
+    (
+        "mirostat_tau",
+        ParamKind::Float,
+        "Mirostat target entropy (coherence vs diversity)",
+    ),
+    ("num_ctx", ParamKind::Int, "Context window size in tokens"),
+    (
+        "repeat_last_n",
+        ParamKind::Int,
+        "How far back to look to prevent repetition",
+    ),
+    (
+        "repeat_penalty",
+        ParamKind::Float,
+        "Penalty applied to repeated tokens",
+    ),
+    (
+        "presence_penalty",
+        ParamKind::Float,
+        "Penalty for tokens already present",
+    ),
+    (
+        "frequency_penalty",
+        ParamKind::Float,
+        "Penalty scaled by token frequency",
+    ),
+    (
+        "temperature",
+        ParamKind::Float,
+        "Sampling temperature, higher is more creative",
+    ),
+    (
+        "seed",
+        ParamKind::Int,
+        "Random seed for reproducible output",
+    ),
+    (
+        "stop",
+        ParamKind::Text,
+        "Stop sequence (repeat the line for several)",
+    ),
+    (
+        "num_predict",
+        ParamKind::Int,
+        "Maximum tokens to generate (-1 = unlimited)",
+    ),
+    (
+        "num_keep",
+        ParamKind::Int,
+        "Tokens of the prompt to keep when the context fills",
+    ),
+    (
+        "top_k",
+        ParamKind::Int,
+        "Sample only from the k most likely tokens",
+    ),
+    (
+        "top_p",
+        ParamKind::Float,
+        "Nucleus sampling probability mass",
+    ),
+    (
+        "min_p",
+        ParamKind::Float,
+        "Minimum probability relative to the top token",
+    ),
+    ("tfs_z", ParamKind::Float, "Tail free sampling"),
+    ("typical_p", ParamKind::Float, "Locally typical sampling"),
+    ("num_gpu", ParamKind::Int, "Layers to offload to the GPU"),
+    ("main_gpu", ParamKind::Int, "GPU used for small tensors"),
+    (
+        "num_thread",
+        ParamKind::Int,
+        "CPU threads used for generation",
+    ),
+    ("num_batch", ParamKind::Int, "Prompt processing batch size"),
+    ("use_mmap", ParamKind::Bool, "Memory-map the model file"),
+    ("use_mlock", ParamKind::Bool, "Lock the model in memory"),
+    ("numa", ParamKind::Bool, "Enable NUMA support"),
+    (
+        "penalize_newline",
+        ParamKind::Bool,
+        "Apply repeat penalty to newlines",
+    ),
+];
+
+/// Parameters older Ollama releases accepted and current ones ignore
+pub const DEPRECATED_PARAMETERS: &[&str] = &[
+    "embedding_only",
+    "f16_kv",
+    "logits_all",
+    "low_vram",
+    "num_gqa",
+    "rms_norm_eps",
+    "rope_frequency_base",
+    "rope_frequency_scale",
+    "vocab_only",
+];
+
+pub fn is_deprecated_parameter(key: &str) -> bool {
+    DEPRECATED_PARAMETERS.contains(&key)
+}
+
+pub fn parameter_kind(key: &str) -> Option<ParamKind> {
+    PARAMETERS
+        .iter()
+        .find(|(name, _, _)| *name == key)
+        .map(|(_, kind, _)| *kind)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl ParameterValue {
+    /// Best guess at the type of a value for a key we have no schema for
+    fn infer(raw: &str) -> Self {
+        [ParamKind::Int, ParamKind::Float, ParamKind::Bool]
+            .into_iter()
+            .find_map(|kind| Self::parse(kind, raw))
+            .unwrap_or_else(|| Self::Text(raw.to_string()))
+    }
+
+    fn parse(kind: ParamKind, raw: &str) -> Option<Self> {
+        match kind {
+            ParamKind::Int => raw.parse().ok().map(Self::Int),
+            ParamKind::Float => raw.parse().ok().map(Self::Float),
+            ParamKind::Bool => raw.parse().ok().map(Self::Bool),
+            ParamKind::Text => (!raw.is_empty()).then(|| Self::Text(raw.to_string())),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Int(v) => (*v).into(),
+            Self::Float(v) => (*v).into(),
+            Self::Bool(v) => (*v).into(),
+            Self::Text(v) => v.clone().into(),
+        }
+    }
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(v) => write!(f, "{v}"),
+            Self::Float(v) => write!(f, "{v}"),
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::Text(v) => write!(f, "{}", quote_if_needed(v)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quoting {
+    Bare,
+    Double,
+    Triple,
+}
+
+/// A string argument along with how it was quoted in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextValue {
+    pub text: String,
+    pub quoting: Quoting,
+}
+
+impl TextValue {
+    pub fn triple(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            quoting: Quoting::Triple,
+        }
+    }
+}
+
+impl fmt::Display for TextValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quoting {
+            Quoting::Bare => write!(f, "{}", self.text),
+            Quoting::Double => write!(f, "\"{}\"", escape(&self.text)),
+            Quoting::Triple => write!(f, "\"\"\"{}\"\"\"", self.text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    From(String),
+    Parameter { key: String, value: ParameterValue },
+    System(TextValue),
+    Template(TextValue),
+    Adapter(String),
+    License(TextValue),
+    Message { role: Role, content: TextValue },
+}
+
+impl Instruction {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Instruction::From(_) => "FROM",
+            Instruction::Parameter { .. } => "PARAMETER",
+            Instruction::System(_) => "SYSTEM",
+            Instruction::Template(_) => "TEMPLATE",
+            Instruction::Adapter(_) => "ADAPTER",
+            Instruction::License(_) => "LICENSE",
+            Instruction::Message { .. } => "MESSAGE",
+        }
+    }
+
+    /// Canonical source form of this instruction
+    pub fn render(&self) -> String {
+        match self {
+            Instruction::From(model) => format!("FROM {model}"),
+            Instruction::Parameter { key, value } => format!("PARAMETER {key} {value}"),
+            Instruction::System(text) => format!("SYSTEM {text}"),
+            Instruction::Template(text) => format!("TEMPLATE {text}"),
+            Instruction::Adapter(path) => format!("ADAPTER {path}"),
+            Instruction::License(text) => format!("LICENSE {text}"),
+            Instruction::Message { role, content } => {
+                format!("MESSAGE {} {content}", role.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Instruction(Instruction),
+    /// Comment text after the `#`
+    Comment(String),
+    Blank,
+    /// Anything that failed to parse, kept so the file still round-trips
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub item: Item,
+    /// 1-based position of the first non-whitespace character
+    pub line: usize,
+    pub column: usize,
+    /// Number of source lines this node spans
+    pub line_count: usize,
+    raw: String,
+}
+
+impl Node {
+    pub fn instruction(&self) -> Option<&Instruction> {
+        match &self.item {
+            Item::Instruction(inst) => Some(inst),
+            _ => None,
+        }
+    }
+}
+
+fn render_item(item: &Item) -> String {
+    match item {
+        Item::Instruction(inst) => inst.render(),
+        Item::Comment(text) => format!("#{text}"),
+        Item::Blank => String::new(),
+        Item::Invalid(raw) => raw.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// What a diagnostic is about, so callers can react to a problem without
+/// matching on its wording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagnosticKind {
+    Syntax,
+    UnknownInstruction,
+    UnknownParameter,
+    MissingValue,
+    InvalidValue,
+    MissingFrom,
+    MultipleFrom,
+    Empty,
+    Deprecated,
+    DuplicateParameter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Modelfile {
+    pub nodes: Vec<Node>,
+    trailing_newline: bool,
+}
+
+impl Modelfile {
+    pub fn instructions(&self) -> impl Iterator<Item = (&Node, &Instruction)> {
+        self.nodes
+            .iter()
+            .filter_map(|node| node.instruction().map(|inst| (node, inst)))
+    }
+
+    /// The effective base model (the last FROM wins)
+    pub fn from(&self) -> Option<&str> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::From(model) => Some(model.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+
+    pub fn system(&self) -> Option<&str> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::System(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+
+    pub fn template(&self) -> Option<&str> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::Template(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+
+    pub fn license(&self) -> Option<&str> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::License(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+
+    pub fn adapters(&self) -> Vec<&str> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::Adapter(path) => Some(path.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn messages(&self) -> Vec<(Role, &str)> {
+        self.instructions()
+            .filter_map(|(_, inst)| match inst {
+                Instruction::Message { role, content } => Some((*role, content.text.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Parameters by key. `stop` may repeat, so every key maps to a list.
+    pub fn parameters(&self) -> BTreeMap<&str, Vec<&ParameterValue>> {
+        let mut parameters: BTreeMap<&str, Vec<&ParameterValue>> = BTreeMap::new();
+        for (_, inst) in self.instructions() {
+            if let Instruction::Parameter { key, value } = inst {
+                let values = parameters.entry(key.as_str()).or_default();
+                if key != "stop" {
+                    values.clear();
+                }
+                values.push(value);
+            }
+        }
+        parameters
+    }
+
+    /// Re-render every instruction canonically, keeping comments and blank lines
+    pub fn normalized(&self) -> Self {
+        Self {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|node| match &node.item {
+                    Item::Instruction(_) | Item::Comment(_) => Node {
+                        raw: render_item(&node.item),
+                        ..node.clone()
+                    },
+                    _ => node.clone(),
+                })
+                .collect(),
+            trailing_newline: true,
+        }
+    }
+}
+
+impl fmt::Display for Modelfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", node.raw)?;
+        }
+        if self.trailing_newline {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a Modelfile, collecting errors instead of stopping at the first one
+pub fn parse(source: &str) -> (Modelfile, Vec<Diagnostic>) {
+    let lines: Vec<&str> = source.split('\n').collect();
+    let trailing_newline = source.ends_with('\n');
+    // A trailing newline leaves an empty final element that isn't a real line
+    let line_total = if trailing_newline {
+        lines.len() - 1
+    } else {
+        lines.len()
+    };
+
+    let mut nodes = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut i = 0;
+
+    while i < line_total {
+        let line = lines[i];
+        let line_no = i + 1;
+        let indent = line.len() - line.trim_start().len();
+        let column = indent + 1;
+        let content = line.trim();
+
+        if content.is_empty() {
+            nodes.push(Node {
+                item: Item::Blank,
+                line: line_no,
+                column,
+                line_count: 1,
+                raw: line.to_string(),
+            });
+            i += 1;
+            continue;
+        }
+
+        if let Some(comment) = line.trim_start().strip_prefix('#') {
+            nodes.push(Node {
+                item: Item::Comment(comment.trim_end_matches('\r').to_string()),
+                line: line_no,
+                column,
+                line_count: 1,
+                raw: line.to_string(),
+            });
+            i += 1;
+            continue;
+        }
+
+        let body = line.trim_start();
+        let keyword_len = body.find(char::is_whitespace).unwrap_or(body.len());
+        let keyword = &body[..keyword_len];
+        let after_keyword = &body[keyword_len..];
+        let args_offset =
+            indent + keyword_len + (after_keyword.len() - after_keyword.trim_start().len());
+        let args_column = args_offset + 1;
+        let args = after_keyword.trim_start();
+
+        let mut ctx = LineContext {
+            lines: &lines[..line_total],
+            start: i,
+            end: i,
+            diagnostics: &mut diagnostics,
+        };
+
+        let item = match keyword.to_uppercase().as_str() {
+            "FROM" => ctx
+                .bare(
+                    args,
+                    args_column,
+                    DiagnosticKind::MissingFrom,
+                    "FROM needs a model name or path",
+                )
+                .map(Instruction::From),
+            "ADAPTER" => ctx
+                .bare(
+                    args,
+                    args_column,
+                    DiagnosticKind::MissingValue,
+                    "ADAPTER needs a path",
+                )
+                .map(Instruction::Adapter),
+            "SYSTEM" => ctx.text(args, args_column).map(Instruction::System),
+            "TEMPLATE" => ctx.text(args, args_column).map(Instruction::Template),
+            "LICENSE" => ctx.text(args, args_column).map(Instruction::License),
+            "PARAMETER" => ctx.parameter(args, args_column),
+            "MESSAGE" => ctx.message(args, args_column),
+            _ => {
+                ctx.error(
+                    line_no,
+                    column,
+                    DiagnosticKind::UnknownInstruction,
+                    format!("Unknown instruction `{keyword}`"),
+                );
+                None
+            }
+        };
+
+        let end = ctx.end;
+        let raw = lines[i..=end].join("\n");
+        nodes.push(Node {
+            item: item.map_or_else(|| Item::Invalid(raw.clone()), Item::Instruction),
+            line: line_no,
+            column,
+            line_count: end - i + 1,
+            raw,
+        });
+        i = end + 1;
+    }
+
+    let modelfile = Modelfile {
+        nodes,
+        trailing_newline,
+    };
+    (modelfile, diagnostics)
+}
+
+/// Parse plus semantic checks that Ollama would reject or that are likely mistakes
+pub fn lint(source: &str) -> (Modelfile, Vec<Diagnostic>) {
+    let (modelfile, mut diagnostics) = parse(source);
+
+    let froms: Vec<&Node> = modelfile
+        .instructions()
+        .filter(|(_, inst)| matches!(inst, Instruction::From(_)))
+        .map(|(node, _)| node)
+        .collect();
+
+    // A FROM without a model has already been reported
+    if froms.is_empty()
+        && !diagnostics
+            .iter()
+            .any(|d| d.kind == DiagnosticKind::MissingFrom)
+    {
+        diagnostics.push(Diagnostic {
+            line: 1,
+            column: 1,
+            severity: Severity::Error,
+            kind: DiagnosticKind::MissingFrom,
+            message: "Missing FROM instruction".to_string(),
+        });
+    }
+    for node in froms.iter().skip(1) {
+        diagnostics.push(warning(
+            node,
+            DiagnosticKind::MultipleFrom,
+            "Multiple FROM instructions, the last one wins".to_string(),
+        ));
+    }
+
+    let mut seen_params = BTreeSet::new();
+    for (node, inst) in modelfile.instructions() {
+        match inst {
+            Instruction::System(text)
+            | Instruction::Template(text)
+            | Instruction::License(text)
+                if text.text.trim().is_empty() =>
+            {
+                diagnostics.push(warning(
+                    node,
+                    DiagnosticKind::Empty,
+                    format!("Empty {}", inst.keyword()),
+                ));
+            }
+            Instruction::Parameter { key, .. } if is_deprecated_parameter(key) => {
+                diagnostics.push(warning(
+                    node,
+                    DiagnosticKind::Deprecated,
+                    format!("`{key}` is no longer supported by Ollama and is ignored"),
+                ));
+            }
+            Instruction::Parameter { key, .. }
+                if key != "stop" && !seen_params.insert(key.as_str()) =>
+            {
+                diagnostics.push(warning(
+                    node,
+                    DiagnosticKind::DuplicateParameter,
+                    format!("`{key}` is set more than once, the last value wins"),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    (modelfile, diagnostics)
+}
+
+fn warning(node: &Node, kind: DiagnosticKind, message: String) -> Diagnostic {
+    Diagnostic {
+        line: node.line,
+        column: node.column,
+        severity: Severity::Warning,
+        kind,
+        message,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One semantic difference between two Modelfiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Compare what two Modelfiles mean, ignoring comments, ordering and formatting
+pub fn diff(old: &Modelfile, new: &Modelfile) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    let mut compare = |field: &str, a: Option<String>, b: Option<String>| {
+        let kind = match (&a, &b) {
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (Some(x), Some(y)) if x != y => ChangeKind::Changed,
+            _ => return,
+        };
+        changes.push(Change {
+            kind,
+            field: field.to_string(),
+            old: a,
+            new: b,
+        });
+    };
+
+    compare(
+        "FROM",
+        old.from().map(String::from),
+        new.from().map(String::from),
+    );
+    compare(
+        "SYSTEM",
+        old.system().map(String::from),
+        new.system().map(String::from),
+    );
+    compare(
+        "TEMPLATE",
+        old.template().map(String::from),
+        new.template().map(String::from),
+    );
+    compare(
+        "LICENSE",
+        old.license().map(String::from),
+        new.license().map(String::from),
+    );
+
+    let join = |values: Vec<&str>| (!values.is_empty()).then(|| values.join(", "));
+    compare("ADAPTER", join(old.adapters()), join(new.adapters()));
+
+    let old_params = old.parameters();
+    let new_params = new.parameters();
+    let keys: BTreeSet<&str> = old_params
+        .keys()
+        .chain(new_params.keys())
+        .copied()
+        .collect();
+    for key in keys {
+        let render = |values: Option<&Vec<&ParameterValue>>| {
+            values.map(|values| {
+                values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+        };
+        compare(
+            &format!("PARAMETER {key}"),
+            render(old_params.get(key)),
+            render(new_params.get(key)),
+        );
+    }
+
+    let render_messages = |modelfile: &Modelfile| {
+        let messages = modelfile.messages();
+        (!messages.is_empty()).then(|| {
+            messages
+                .iter()
+                .map(|(role, text)| format!("{}: {text}", role.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    };
+    compare("MESSAGE", render_messages(old), render_messages(new));
+
+    changes
+}
+
+/// Cursor over the source lines for one instruction, which may consume
+/// following lines for a `"""` block
+struct LineContext<'a, 'd> {
+    lines: &'a [&'a str],
+    start: usize,
+    end: usize,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl LineContext<'_, '_> {
+    fn error(&mut self, line: usize, column: usize, kind: DiagnosticKind, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            column,
+            severity: Severity::Error,
+            kind,
+            message,
+        });
+    }
+
+    fn line_no(&self) -> usize {
+        self.start + 1
+    }
+
+    fn bare(
+        &mut self,
+        args: &str,
+        column: usize,
+        kind: DiagnosticKind,
+        missing: &str,
+    ) -> Option<String> {
+        let value = args.trim_end();
+        if value.is_empty() {
+            self.error(self.line_no(), column, kind, missing.to_string());
+            return None;
+        }
+        match self.text(value, column)? {
+            TextValue { text, .. } if !text.is_empty() => Some(text),
+            _ => {
+                self.error(self.line_no(), column, kind, missing.to_string());
+                None
+            }
+        }
+    }
+
+    /// A possibly quoted string argument. Triple-quoted strings may span lines.
+    fn text(&mut self, args: &str, column: usize) -> Option<TextValue> {
+        if let Some(rest) = args.strip_prefix("\"\"\"") {
+            if let Some(close) = rest.find("\"\"\"") {
+                self.expect_nothing_after(&rest[close + 3..], column + 3 + close + 3)?;
+                return Some(TextValue::triple(&rest[..close]));
+            }
+
+            let mut parts = vec![rest.to_string()];
+            for idx in self.start + 1..self.lines.len() {
+                let line = self.lines[idx];
+                if let Some(close) = line.find("\"\"\"") {
+                    parts.push(line[..close].to_string());
+                    self.end = idx;
+                    self.expect_nothing_after_at(&line[close + 3..], idx + 1, close + 4)?;
+                    return Some(TextValue::triple(&parts.join("\n")));
+                }
+                parts.push(line.to_string());
+            }
+
+            self.end = self.lines.len() - 1;
+            self.error(
+                self.line_no(),
+                column,
+                DiagnosticKind::Syntax,
+                "Unterminated \"\"\" string".to_string(),
+            );
+            return None;
+        }
+
+        let trimmed = args.trim_end();
+        if let Some(rest) = trimmed.strip_prefix('"') {
+            let mut text = String::new();
+            let mut chars = rest.char_indices();
+            while let Some((idx, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            text.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        self.expect_nothing_after(&rest[idx + 1..], column + idx + 2)?;
+                        return Some(TextValue {
+                            text,
+                            quoting: Quoting::Double,
+                        });
+                    }
+                    c => text.push(c),
+                }
+            }
+            self.error(
+                self.line_no(),
+                column,
+                DiagnosticKind::Syntax,
+                "Unterminated \" string".to_string(),
+            );
+            return None;
+        }
+
+        Some(TextValue {
+            text: trimmed.to_string(),
+            quoting: Quoting::Bare,
+        })
+    }
+
+    fn expect_nothing_after(&mut self, rest: &str, column: usize) -> Option<()> {
+        self.expect_nothing_after_at(rest, self.line_no(), column)
+    }
+
+    fn expect_nothing_after_at(&mut self, rest: &str, line: usize, column: usize) -> Option<()> {
+        if rest.trim().is_empty() {
+            Some(())
+        } else {
+            let offset = rest.len() - rest.trim_start().len();
+            self.error(
+                line,
+                column + offset,
+                DiagnosticKind::Syntax,
+                "Unexpected text after closing quotes".to_string(),
+            );
+            None
+        }
+    }
+
+    fn parameter(&mut self, args: &str, column: usize) -> Option<Instruction> {
+        let args = args.trim_end();
+        let Some((key, value)) = args.split_once(char::is_whitespace) else {
+            self.error(
+                self.line_no(),
+                column,
+                DiagnosticKind::MissingValue,
+                "PARAMETER needs a key and a value".to_string(),
+            );
+            return None;
+        };
+
+        let value_offset = args.len() - args[key.len()..].trim_start().len();
+        let value_column = column + value_offset;
+
+        let text = self.text(value.trim_start(), value_column)?;
+
+        let Some(kind) = parameter_kind(key) else {
+            if is_deprecated_parameter(key) {
+                // Kept in the AST so lint can warn about it instead of failing
+                return Some(Instruction::Parameter {
+                    key: key.to_string(),
+                    value: ParameterValue::infer(&text.text),
+                });
+            }
+            self.error(
+                self.line_no(),
+                column,
+                DiagnosticKind::UnknownParameter,
+                format!("Unknown parameter `{key}`"),
+            );
+            return None;
+        };
+
+        match ParameterValue::parse(kind, &text.text) {
+            Some(value) => Some(Instruction::Parameter {
+                key: key.to_string(),
+                value,
+            }),
+            None => {
+                let expected = match kind {
+                    ParamKind::Int => "an integer",
+                    ParamKind::Float => "a number",
+                    ParamKind::Bool => "true or false",
+                    ParamKind::Text => "a non-empty string",
+                };
+                self.error(
+                    self.line_no(),
+                    value_column,
+                    DiagnosticKind::InvalidValue,
+                    format!("`{key}` expects {expected}, got `{}`", text.text),
+                );
+                None
+            }
+        }
+    }
+
+    fn message(&mut self, args: &str, column: usize) -> Option<Instruction> {
+        let (role, content) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let role = match role.trim() {
+            "system" => Role::System,
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            other => {
+                self.error(
+                    self.line_no(),
+                    column,
+                    DiagnosticKind::InvalidValue,
+                    format!("MESSAGE role must be system, user or assistant, got `{other}`"),
+                );
+                return None;
+            }
+        };
+
+        let content_offset = args.len() - content.trim_start().len();
+        let content = self.text(content.trim_start(), column + content_offset)?;
+        Some(Instruction::Message { role, content })
+    }
+}
+
+fn quote_if_needed(value: &str) -> String {
+    if value.is_empty() || value.contains(char::is_whitespace) || value.contains('"') {
+        format!("\"{}\"", escape(value))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Backslash-escape a double-quoted string, the reverse of how `text` reads one
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format once, then check that formatting again changes nothing and
+    /// that the result means the same as the source
+    fn assert_formats_stably(source: &str) -> String {
+        let (original, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let formatted = original.normalized().to_string();
+        let (reparsed, diagnostics) = parse(&formatted);
+        assert!(diagnostics.is_empty(), "{formatted}: {diagnostics:?}");
+        assert_eq!(reparsed.normalized().to_string(), formatted);
+        assert!(diff(&original, &reparsed).is_empty(), "{formatted}");
+        formatted
+    }
+
+    fn kinds(source: &str) -> Vec<DiagnosticKind> {
+        lint(source).1.into_iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn source_round_trips_exactly() {
+        let sources = [
+            "FROM llama3\n",
+            "FROM llama3",
+            "# comment\n\n  FROM   llama3  \nPARAMETER temperature 0.7\r\n",
+            "FROM llama3\nSYSTEM \"\"\"\nline one\n  line two\n\"\"\"\n\n\n",
+            "FROM llama3\nBOGUS line\nPARAMETER stop\n",
+        ];
+        for source in sources {
+            assert_eq!(parse(source).0.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn quoting_survives_formatting() {
+        let formatted = assert_formats_stably(
+            "FROM llama3\nSYSTEM \"Say \\\"hi\\\" politely\"\nMESSAGE user \"hello there\"\n",
+        );
+        let (modelfile, _) = parse(&formatted);
+        assert_eq!(modelfile.system(), Some("Say \"hi\" politely"));
+        assert_eq!(modelfile.messages(), vec![(Role::User, "hello there")]);
+    }
+
+    #[test]
+    fn backslashes_survive_formatting() {
+        let formatted = assert_formats_stably(
+            "FROM C:\\models\\base.gguf\nSYSTEM \"Paths look like C:\\\\Users\\\\me\"\nPARAMETER stop \"\\\\n User:\"\n",
+        );
+        let (modelfile, _) = parse(&formatted);
+        assert_eq!(modelfile.from(), Some("C:\\models\\base.gguf"));
+        assert_eq!(modelfile.system(), Some("Paths look like C:\\Users\\me"));
+        assert_eq!(
+            modelfile.parameters()["stop"],
+            vec![&ParameterValue::Text("\\n User:".to_string())]
+        );
+    }
+
+    #[test]
+    fn multiline_strings_survive_formatting() {
+        let template =
+            "{{ if .System }}<|system|>\n{{ .System }}{{ end }}\n<|user|>\n{{ .Prompt }}";
+        let formatted =
+            assert_formats_stably(&format!("FROM llama3\nTEMPLATE \"\"\"{template}\"\"\"\n"));
+        assert_eq!(parse(&formatted).0.template(), Some(template));
+    }
+
+    #[test]
+    fn repeated_stop_parameters_are_all_kept() {
+        let formatted = assert_formats_stably(
+            "FROM llama3\nPARAMETER stop <|end|>\nPARAMETER temperature 0.2\nPARAMETER stop \"User:\"\n",
+        );
+        let (modelfile, _) = parse(&formatted);
+        let parameters = modelfile.parameters();
+        assert_eq!(
+            parameters["stop"],
+            vec![
+                &ParameterValue::Text("<|end|>".to_string()),
+                &ParameterValue::Text("User:".to_string()),
+            ]
+        );
+        assert_eq!(parameters["temperature"], vec![&ParameterValue::Float(0.2)]);
+    }
+
+    #[test]
+    fn lint_accepts_a_clean_modelfile() {
+        assert!(kinds("FROM llama3\nPARAMETER num_ctx 4096\nSYSTEM You are terse.\n").is_empty());
+    }
+
+    #[test]
+    fn lint_reports_missing_from_once() {
+        assert_eq!(kinds("SYSTEM hi\n"), vec![DiagnosticKind::MissingFrom]);
+        assert_eq!(kinds("FROM\n"), vec![DiagnosticKind::MissingFrom]);
+    }
+
+    #[test]
+    fn lint_reports_each_problem() {
+        let cases = [
+            ("FROM a\nFROM b\n", DiagnosticKind::MultipleFrom),
+            ("FROM a\nFETCH b\n", DiagnosticKind::UnknownInstruction),
+            (
+                "FROM a\nPARAMETER colour red\n",
+                DiagnosticKind::UnknownParameter,
+            ),
+            (
+                "FROM a\nPARAMETER num_ctx lots\n",
+                DiagnosticKind::InvalidValue,
+            ),
+            ("FROM a\nMESSAGE robot hi\n", DiagnosticKind::InvalidValue),
+            (
+                "FROM a\nPARAMETER temperature\n",
+                DiagnosticKind::MissingValue,
+            ),
+            ("FROM a\nSYSTEM \"\"\n", DiagnosticKind::Empty),
+            (
+                "FROM a\nPARAMETER f16_kv true\n",
+                DiagnosticKind::Deprecated,
+            ),
+            (
+                "FROM a\nPARAMETER top_k 10\nPARAMETER top_k 20\n",
+                DiagnosticKind::DuplicateParameter,
+            ),
+            ("FROM a\nSYSTEM \"\"\"open\n", DiagnosticKind::Syntax),
+            ("FROM a\nSYSTEM \"hi\" there\n", DiagnosticKind::Syntax),
+        ];
+        for (source, kind) in cases {
+            assert_eq!(kinds(source), vec![kind], "{source}");
+        }
+    }
+
+    #[test]
+    fn diff_ignores_formatting_and_comments() {
+        let (old, _) = parse("FROM llama3\nPARAMETER temperature 0.7\n");
+        let (same, _) = parse("# tuned\nFROM   llama3\n\nPARAMETER temperature \"0.7\"\n");
+        assert!(diff(&old, &same).is_empty());
+
+        let (new, _) = parse("FROM llama3\nPARAMETER temperature 0.2\nSYSTEM hi\n");
+        let changes: Vec<(ChangeKind, String)> = diff(&old, &new)
+            .into_iter()
+            .map(|c| (c.kind, c.field))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (ChangeKind::Added, "SYSTEM".to_string()),
+                (ChangeKind::Changed, "PARAMETER temperature".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use crate::api::{CreateModelRequest, CreateProgress};
+use crate::modelfile::{
+    self, Change, Diagnostic, Instruction, Item, Modelfile, ParamKind, Severity, INSTRUCTIONS,
+    PARAMETERS,
+};
+use crate::theme::TokyoNight;
+use ratatui::style::Style;
+use ratatui::widgets::{Block, BorderType, Borders};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tui_textarea::TextArea;
+
+/// One instruction as it appears in the editor, for the outline pane
+#[derive(Clone)]
+pub struct OutlineEntry {
+    pub line: usize,
+    pub keyword: String,
+    pub summary: String,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum EditorFocus {
+    Content,
+    Name,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum CreateStatus {
+    Idle,
+    Running,
+    Succeeded,
+    Failed(String),
+}
+
+/// State of the Modelfile editor screen
+pub struct ModelfileEditor {
+    pub textarea: TextArea<'static>,
+    pub model_name: String,
+    pub source_model: Option<String>,
+    pub focus: EditorFocus,
+    pub parsed: Modelfile,
+    pub diagnostics: Vec<Diagnostic>,
+    pub outline: Vec<OutlineEntry>,
+    pub library: Option<ModelfileLibrary>,
+    pub create_status: CreateStatus,
+    pub create_log: Vec<String>,
+    pub loading: bool, // Waiting on the source model's Modelfile from `/api/show`
+    create_receiver: Option<mpsc::Receiver<CreateProgress>>,
+}
+
+fn new_textarea(content: &str) -> TextArea<'static> {
+    let mut textarea = TextArea::new(content.lines().map(String::from).collect());
+    textarea.set_line_number_style(Style::default().fg(TokyoNight::DARK3));
+    textarea.set_cursor_line_style(Style::default().bg(TokyoNight::BG_HIGHLIGHT));
+    textarea.set_style(Style::default().fg(TokyoNight::FG));
+    textarea.set_block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().bg(TokyoNight::BG_DARK)),
+    );
+    textarea
+}
+
+impl ModelfileEditor {
+    pub fn new(content: &str, model_name: String, source_model: Option<String>) -> Self {
+        let mut editor = Self {
+            textarea: new_textarea(content),
+            model_name,
+            source_model,
+            focus: EditorFocus::Content,
+            parsed: Modelfile::default(),
+            diagnostics: Vec::new(),
+            outline: Vec::new(),
+            library: None,
+            create_status: CreateStatus::Idle,
+            create_log: Vec::new(),
+            loading: false,
+            create_receiver: None,
+        };
+        editor.validate();
+        editor
+    }
+
+    /// An empty editor for `source_model`, filled in by `finish_loading`
+    /// once its Modelfile arrives
+    pub fn loading(model_name: String, source_model: String) -> Self {
+        let mut editor = Self::new("", model_name, Some(source_model));
+        editor.loading = true;
+        // An empty buffer's missing FROM isn't worth reporting yet
+        editor.diagnostics.clear();
+        editor
+    }
+
+    /// Put the source model's Modelfile in the buffer, as if the editor had
+    /// opened with it, unless something else was loaded in the meantime
+    pub fn finish_loading(&mut self, content: &str) {
+        if !self.loading {
+            return;
+        }
+        self.loading = false;
+        let block = self.textarea.block().cloned();
+        self.textarea = new_textarea(content);
+        if let Some(block) = block {
+            self.textarea.set_block(block);
+        }
+        self.validate();
+    }
+
+    pub fn content(&self) -> String {
+        self.textarea.lines().join("\n")
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            EditorFocus::Content => EditorFocus::Name,
+            EditorFocus::Name => EditorFocus::Content,
+        };
+        let border = if self.focus == EditorFocus::Content {
+            TokyoNight::CYAN
+        } else {
+            TokyoNight::TERMINAL_BLACK
+        };
+        if let Some(block) = self.textarea.block().cloned() {
+            self.textarea
+                .set_block(block.border_style(Style::default().fg(border)));
+        }
+    }
+
+    /// Re-parse the buffer, rebuilding diagnostics and the outline
+    pub fn validate(&mut self) {
+        let (parsed, diagnostics) = modelfile::lint(&self.content());
+
+        self.outline = parsed
+            .nodes
+            .iter()
+            .filter_map(|node| {
+                let (keyword, summary) = match &node.item {
+                    Item::Instruction(inst) => (inst.keyword().to_string(), summarize(inst)),
+                    Item::Invalid(raw) => {
+                        let raw = raw.trim();
+                        let (keyword, rest) =
+                            raw.split_once(char::is_whitespace).unwrap_or((raw, ""));
+                        (keyword.to_uppercase(), first_line_preview(rest))
+                    }
+                    _ => return None,
+                };
+                let summary = if node.line_count > 1 {
+                    format!("{summary} ({} lines)", node.line_count)
+                } else {
+                    summary
+                };
+                Some(OutlineEntry {
+                    line: node.line,
+                    keyword,
+                    summary,
+                })
+            })
+            .collect();
+
+        self.parsed = parsed;
+        self.diagnostics = diagnostics;
+    }
+
+    /// Replace the buffer, keeping the undo history
+    pub fn set_content(&mut self, content: &str) {
+        self.loading = false;
+        self.textarea.select_all();
+        self.textarea.cut();
+        self.textarea.insert_str(content.trim_end_matches('\n'));
+        self.textarea.move_cursor(tui_textarea::CursorMove::Top);
+        self.validate();
+    }
+
+    /// Rewrite every instruction in canonical form. Refuses while the buffer has errors.
+    pub fn format(&mut self) -> bool {
+        self.validate();
+        if self.has_errors() {
+            return false;
+        }
+        let formatted = self.parsed.normalized().to_string();
+        if formatted.trim_end() != self.content().trim_end() {
+            let (row, col) = self.textarea.cursor();
+            self.set_content(&formatted);
+            self.textarea
+                .move_cursor(tui_textarea::CursorMove::Jump(row as u16, col as u16));
+        }
+        true
+    }
+
+    /// Help text for whatever is under the cursor
+    pub fn help_for_cursor(&self) -> Option<String> {
+        let line = self.textarea.cursor().0 + 1;
+        let node = self
+            .parsed
+            .nodes
+            .iter()
+            .find(|n| n.line <= line && line < n.line + n.line_count)?;
+        let inst = node.instruction()?;
+
+        if let Instruction::Parameter { key, .. } = inst {
+            if let Some((name, kind, doc)) = PARAMETERS.iter().find(|(name, _, _)| name == key) {
+                let kind = match kind {
+                    ParamKind::Int => "integer",
+                    ParamKind::Float => "float",
+                    ParamKind::Bool => "bool",
+                    ParamKind::Text => "string",
+                };
+                return Some(format!("PARAMETER {name} ({kind}): {doc}"));
+            }
+        }
+
+        INSTRUCTIONS
+            .iter()
+            .find(|(name, _)| *name == inst.keyword())
+            .map(|(name, doc)| format!("{name}: {doc}"))
+    }
+
+    /// Build the `/api/create` body from the current buffer. ADAPTER is
+    /// refused: Ollama takes adapters only as uploaded blobs, and a request
+    /// without them would quietly create the base model.
+    pub fn build_request(&self) -> anyhow::Result<CreateModelRequest> {
+        let parsed = &self.parsed;
+        if let Some(adapter) = parsed.adapters().first() {
+            anyhow::bail!(
+                "ADAPTER isn't supported here yet; create the model with `ollama create` to apply {adapter}"
+            );
+        }
+        let mut request = CreateModelRequest {
+            model: self.model_name.trim().to_string(),
+            name: self.model_name.trim().to_string(),
+            modelfile: self.content(),
+            stream: true,
+            from: parsed.from().map(String::from),
+            system: parsed.system().map(String::from),
+            template: parsed.template().map(String::from),
+            license: parsed.license().map(String::from),
+            ..Default::default()
+        };
+
+        for (key, values) in parsed.parameters() {
+            if modelfile::is_deprecated_parameter(key) {
+                continue;
+            }
+            let json_value = if key == "stop" {
+                // Stop sequences accumulate into a list
+                values.iter().map(|v| v.to_json()).collect()
+            } else {
+                values[0].to_json()
+            };
+            request.parameters.insert(key.to_string(), json_value);
+        }
+
+        request.messages = parsed
+            .messages()
+            .into_iter()
+            .map(|(role, content)| {
+                serde_json::json!({
+                    "role": role.as_str(),
+                    "content": content,
+                })
+            })
+            .collect();
+
+        Ok(request)
+    }
+
+    /// Show the Modelfiles shipped in `dir`, linted and ready to load or diff
+    pub fn open_library(&mut self, dir: &Path) -> anyhow::Result<()> {
+        self.library = Some(ModelfileLibrary::scan(dir)?);
+        Ok(())
+    }
+
+    /// Load the selected library file into the buffer, named after the file
+    pub fn load_selected_library_file(&mut self) -> Option<String> {
+        let library = self.library.take()?;
+        let entry = library.entries.get(library.selected)?;
+        self.set_content(&entry.source);
+        self.model_name = entry.name.clone();
+        self.source_model = None;
+        Some(entry.path.display().to_string())
+    }
+
+    /// What changes if the selected library file were replaced by the buffer
+    pub fn library_diff(&self) -> Vec<Change> {
+        self.library
+            .as_ref()
+            .and_then(|library| library.entries.get(library.selected))
+            .map(|entry| modelfile::diff(&entry.modelfile, &self.parsed))
+            .unwrap_or_default()
+    }
+
+    pub fn start_create(&mut self, receiver: mpsc::Receiver<CreateProgress>) {
+        self.create_status = CreateStatus::Running;
+        self.create_log.clear();
+        self.create_receiver = Some(receiver);
+    }
+
+    /// Drain create progress; returns the final status once the stream ends
+    pub fn poll_create(&mut self) -> Option<CreateStatus> {
+        let receiver = self.create_receiver.as_mut()?;
+        loop {
+            match receiver.try_recv() {
+                Ok(progress) => {
+                    if let Some(error) = progress.error {
+                        self.create_status = CreateStatus::Failed(error);
+                    } else {
+                        if progress.status == "success" {
+                            self.create_status = CreateStatus::Succeeded;
+                        }
+                        if self.create_log.last() != Some(&progress.status) {
+                            self.create_log.push(progress.status);
+                        }
+                    }
+                }
+                Err(mpsc::error::TryRecvError::Empty) => return None,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.create_receiver = None;
+                    if self.create_status == CreateStatus::Running {
+                        self.create_status =
+                            CreateStatus::Failed("stream ended before completion".to_string());
+                    }
+                    return Some(self.create_status.clone());
+                }
+            }
+        }
+    }
+}
+
+/// A Modelfile on disk, parsed and linted
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub source: String,
+    pub modelfile: Modelfile,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LibraryEntry {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+/// The `*.Modelfile` files in a directory, for the editor's file picker
+pub struct ModelfileLibrary {
+    pub dir: PathBuf,
+    pub entries: Vec<LibraryEntry>,
+    pub selected: usize,
+    pub show_diff: bool,
+}
+
+impl ModelfileLibrary {
+    pub fn scan(dir: &Path) -> anyhow::Result<Self> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_modelfile = path.extension().is_some_and(|ext| ext == "Modelfile")
+                || path.file_name().is_some_and(|name| name == "Modelfile");
+            if !is_modelfile {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path)?;
+            let (modelfile, diagnostics) = modelfile::lint(&source);
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            entries.push(LibraryEntry {
+                path,
+                name,
+                source,
+                modelfile,
+                diagnostics,
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries,
+            selected: 0,
+            show_diff: false,
+        })
+    }
+
+    pub fn selected_entry(&self) -> Option<&LibraryEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
+/// Where the shipped Modelfiles live; `LLAMALOOP_MODELFILES_DIR` overrides
+pub fn modelfiles_dir() -> PathBuf {
+    std::env::var_os("LLAMALOOP_MODELFILES_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("modelfiles"))
+}
+
+fn summarize(inst: &Instruction) -> String {
+    match inst {
+        Instruction::From(model) => model.clone(),
+        Instruction::Adapter(path) => path.clone(),
+        Instruction::Parameter { key, value } => format!("{key} {value}"),
+        Instruction::System(text) | Instruction::Template(text) | Instruction::License(text) => {
+            first_line_preview(&text.text)
+        }
+        Instruction::Message { role, content } => {
+            format!("{} {}", role.as_str(), first_line_preview(&content.text))
+        }
+    }
+}
+
+fn first_line_preview(text: &str) -> String {
+    let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let preview: String = first.trim().chars().take(40).collect();
+    if first.trim().chars().count() > 40 {
+        format!("{preview}…")
+    } else {
+        preview
+    }
+}
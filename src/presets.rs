@@ -0,0 +1,113 @@
+use crate::modelfile;
+use crate::storage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A named system prompt that can be applied to any chat session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub prompt: String,
+}
+
+const BUILTIN_PRESETS: &[(&str, &str)] = &[
+    (
+        "concise",
+        "You are a helpful assistant. Answer concisely and skip preamble.",
+    ),
+    (
+        "code-reviewer",
+        "You are a senior engineer reviewing code. Point out bugs, unclear naming and \
+         missing error handling first, then style issues. Quote the lines you refer to.",
+    ),
+    (
+        "rust-expert",
+        "You are an expert Rust programmer. Prefer idiomatic, safe Rust, explain borrow \
+         checker issues plainly, and show complete compiling examples.",
+    ),
+];
+
+/// Prompt presets stored as `<data dir>/presets.json`
+pub struct PresetStore {
+    path: PathBuf,
+    pub presets: Vec<Preset>,
+}
+
+impl PresetStore {
+    /// Load presets, seeding the file on first run from the built-ins and the
+    /// SYSTEM prompts of any Modelfiles in `modelfiles_dir`
+    pub fn load(root: &Path, modelfiles_dir: &Path) -> Self {
+        let path = root.join("presets.json");
+        let presets = match storage::read_json::<Vec<Preset>>(&path) {
+            Ok(presets) => presets,
+            Err(_) if !path.exists() => {
+                let presets = seed_presets(modelfiles_dir);
+                let _ = storage::write_json(&path, &presets);
+                presets
+            }
+            // Never overwrite a file we couldn't parse
+            Err(_) => Vec::new(),
+        };
+        Self { path, presets }
+    }
+
+    fn save(&self) -> Result<()> {
+        storage::write_json(&self.path, &self.presets)
+    }
+
+    /// Add a preset, replacing any existing one with the same name
+    pub fn upsert(&mut self, name: &str, prompt: &str) -> Result<()> {
+        let preset = Preset {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+        };
+        match self.presets.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        self.save()
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<Option<Preset>> {
+        if index >= self.presets.len() {
+            return Ok(None);
+        }
+        let removed = self.presets.remove(index);
+        self.save()?;
+        Ok(Some(removed))
+    }
+}
+
+fn seed_presets(modelfiles_dir: &Path) -> Vec<Preset> {
+    let mut presets: Vec<Preset> = BUILTIN_PRESETS
+        .iter()
+        .map(|(name, prompt)| Preset {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+        })
+        .collect();
+
+    let Ok(entries) = std::fs::read_dir(modelfiles_dir) else {
+        return presets;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "Modelfile"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let (parsed, _) = modelfile::parse(&source);
+        if let (Some(stem), Some(system)) = (path.file_stem(), parsed.system()) {
+            presets.push(Preset {
+                name: stem.to_string_lossy().to_string(),
+                prompt: system.trim().to_string(),
+            });
+        }
+    }
+    presets
+}
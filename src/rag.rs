@@ -0,0 +1,473 @@
+use crate::api::{Capability, OllamaClient};
+use crate::files::{self, FileContext};
+use crate::storage;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Characters per chunk, cut at line breaks; about 400 tokens
+const CHUNK_CHARS: usize = 1600;
+
+/// Lines repeated at the start of the next chunk, so code that straddles a
+/// cut is still found
+const CHUNK_OVERLAP: usize = 3;
+
+/// Chunks sent in one `/api/embed` request
+const EMBED_BATCH: usize = 32;
+
+/// Batches between saves while indexing
+const SAVE_EVERY: usize = 4;
+
+/// Excerpts added to each question
+pub const TOP_K: usize = 5;
+
+/// Where a session's questions are looked up: an indexed folder and the
+/// embedding model its index was built with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RagSource {
+    pub root: PathBuf,
+    pub model: String,
+}
+
+impl RagSource {
+    /// `indexes/<folder>-<hash>.json` in the data directory, one per folder and model
+    pub fn index_path(&self) -> PathBuf {
+        let name = self
+            .root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "root".to_string());
+        let key = format!("{}\n{}", self.root.display(), self.model);
+        storage::data_dir()
+            .join("indexes")
+            .join(format!("{name}-{:016x}.json", fnv1a(key.as_bytes())))
+    }
+}
+
+/// Embedded chunks of every text file in a folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub source: RagSource,
+    pub files: BTreeMap<String, IndexedFile>, // Keyed by path relative to the root
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexedFile {
+    modified: u64, // Milliseconds since the epoch
+    size: u64,
+    hash: String,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    first_line: usize,
+    last_line: usize,
+    text: String,
+    embedding: Vec<f32>,
+}
+
+/// What an indexing run did
+#[derive(Debug, Clone, Default)]
+pub struct IndexStats {
+    pub files: usize,
+    pub unchanged: usize,
+    pub embedded: usize, // New or changed files
+    pub removed: usize,
+    pub skipped: usize, // Binary, oversized or unreadable
+    pub chunks: usize,  // Chunks embedded this run
+}
+
+impl Index {
+    pub fn load(source: &RagSource) -> Result<Self> {
+        storage::read_json(&source.index_path())
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.files.values().map(|f| f.chunks.len()).sum()
+    }
+
+    /// The `k` chunks closest to `query`, best first, as excerpts to attach
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<FileContext> {
+        let mut scored: Vec<(f32, &String, &Chunk)> = self
+            .files
+            .iter()
+            .flat_map(|(path, file)| file.chunks.iter().map(move |chunk| (path, chunk)))
+            .map(|(path, chunk)| (cosine(query, &chunk.embedding), path, chunk))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, path, chunk)| FileContext {
+                path: files::display_path(&self.source.root.join(path)),
+                content: chunk.text.clone(),
+                lines: Some((chunk.first_line, chunk.last_line)),
+            })
+            .collect()
+    }
+}
+
+pub enum IndexEvent {
+    Progress { done: usize, total: usize },
+    Finished(Result<(Index, IndexStats), String>),
+}
+
+/// An indexing run in the background, for the session that asked for it
+pub struct IndexJob {
+    pub session_id: String,
+    pub root: PathBuf,
+    pub done: usize,
+    pub total: usize, // Chunks to embed; 0 while the folder is being read
+    receiver: mpsc::Receiver<IndexEvent>,
+    handle: JoinHandle<()>,
+}
+
+impl IndexJob {
+    /// Index `root` with `model`, or when none is named, with the first of
+    /// `installed` that lists embedding among its capabilities
+    pub fn start(
+        client: &OllamaClient,
+        session_id: String,
+        root: PathBuf,
+        model: Option<String>,
+        installed: Vec<String>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(16);
+        let client = client.clone();
+        let job_root = root.clone();
+        let handle = tokio::spawn(async move {
+            let result = async {
+                let model = match model {
+                    Some(model) => model,
+                    None => find_embedding_model(&client, installed).await?,
+                };
+                let source = RagSource {
+                    root: job_root,
+                    model,
+                };
+                build(&client, source, &tx).await
+            }
+            .await;
+            let _ = tx
+                .send(IndexEvent::Finished(result.map_err(|e| format!("{e:#}"))))
+                .await;
+        });
+        Self {
+            session_id,
+            root,
+            done: 0,
+            total: 0,
+            receiver: rx,
+            handle,
+        }
+    }
+
+    /// The finished index, once the run is over
+    pub fn poll(&mut self) -> Option<Result<(Index, IndexStats), String>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(IndexEvent::Progress { done, total }) => {
+                    self.done = done;
+                    self.total = total;
+                }
+                Ok(IndexEvent::Finished(result)) => return Some(result),
+                Err(mpsc::error::TryRecvError::Empty) => return None,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    return Some(Err("indexing stopped unexpectedly".to_string()));
+                }
+            }
+        }
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+/// A question being looked up in a session's index, off the UI thread
+pub struct Retrieval {
+    pub session_id: String,
+    receiver: oneshot::Receiver<Result<(Arc<Index>, Vec<FileContext>)>>,
+    handle: JoinHandle<()>,
+}
+
+impl Retrieval {
+    /// Embed `question` and find the closest chunks, loading the index
+    /// first unless it's `cached`
+    pub fn start(
+        client: &OllamaClient,
+        session_id: String,
+        source: RagSource,
+        cached: Option<Arc<Index>>,
+        question: String,
+    ) -> Self {
+        let (tx, rx) = oneshot::channel();
+        let client = client.clone();
+        let handle = tokio::spawn(async move {
+            let result = async {
+                let index = match cached {
+                    Some(index) => index,
+                    None => {
+                        let load = source.clone();
+                        let index = tokio::task::spawn_blocking(move || Index::load(&load))
+                            .await?
+                            .map_err(|_| {
+                                anyhow::anyhow!(
+                                    "{} has no index; run /index again",
+                                    source.root.display()
+                                )
+                            })?;
+                        Arc::new(index)
+                    }
+                };
+                let embedding = client
+                    .embed(&source.model, vec![question])
+                    .await?
+                    .pop()
+                    .unwrap_or_default();
+                let excerpts = index.search(&embedding, TOP_K);
+                Ok((index, excerpts))
+            }
+            .await;
+            let _ = tx.send(result);
+        });
+        Self {
+            session_id,
+            receiver: rx,
+            handle,
+        }
+    }
+
+    /// The index and the excerpts found, once the lookup is over
+    pub fn poll(&mut self) -> Option<Result<(Arc<Index>, Vec<FileContext>)>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Some(Err(anyhow::anyhow!("the lookup stopped unexpectedly")))
+            }
+        }
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+/// The first of `models` that lists embedding among its capabilities. One
+/// whose details can't be loaded is passed over.
+async fn find_embedding_model(client: &OllamaClient, models: Vec<String>) -> Result<String> {
+    for model in models {
+        let Ok(info) = client.show_model(&model).await else {
+            continue;
+        };
+        if info
+            .effective_capabilities()
+            .contains(&Capability::Embedding)
+        {
+            return Ok(model);
+        }
+    }
+    anyhow::bail!(
+        "No embedding model installed. Pull one (e.g. nomic-embed-text) or name it: /index <dir> <model>"
+    )
+}
+
+/// Bring the folder's index up to date. Files whose size and modification
+/// time match the saved index are kept as they are, as are files whose
+/// contents hash the same; everything else is chunked and embedded again.
+async fn build(
+    client: &OllamaClient,
+    source: RagSource,
+    progress: &mpsc::Sender<IndexEvent>,
+) -> Result<(Index, IndexStats)> {
+    let previous = Index::load(&source)
+        .ok()
+        .filter(|index| index.source == source);
+    let mut previous = previous.map(|index| index.files).unwrap_or_default();
+    let found = files::find_in_dir(&source.root, None, usize::MAX)?;
+
+    let mut stats = IndexStats {
+        skipped: found.unreadable,
+        ..IndexStats::default()
+    };
+    let mut indexed = BTreeMap::new();
+    let mut pending: Vec<(String, IndexedFile)> = Vec::new();
+    for path in found.paths {
+        let Ok(relative) = path.strip_prefix(&source.root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().to_string();
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64);
+        let size = metadata.len();
+
+        let old = previous.remove(&relative);
+        if let Some(old) = old
+            .as_ref()
+            .filter(|f| f.modified == modified && f.size == size)
+        {
+            stats.unchanged += 1;
+            indexed.insert(relative, old.clone());
+            continue;
+        }
+        let Ok(file) = FileContext::read(&path) else {
+            stats.skipped += 1;
+            continue;
+        };
+        let hash = format!("{:016x}", fnv1a(file.content.as_bytes()));
+        match old {
+            // Touched but not changed
+            Some(old) if old.hash == hash => {
+                stats.unchanged += 1;
+                indexed.insert(
+                    relative,
+                    IndexedFile {
+                        modified,
+                        size,
+                        ..old
+                    },
+                );
+            }
+            _ => pending.push((
+                relative,
+                IndexedFile {
+                    modified,
+                    size,
+                    hash,
+                    chunks: chunk(&file.content),
+                },
+            )),
+        }
+    }
+    stats.removed = previous.len();
+    stats.embedded = pending.len();
+
+    // Embed every new chunk, a batch at a time; the path helps the match.
+    // Finished files are saved as they go, so a failed or stopped run
+    // doesn't have to embed them again.
+    let mut index = Index {
+        source,
+        files: indexed,
+    };
+    let path = index.source.index_path();
+    let jobs: Vec<(usize, usize)> = pending
+        .iter()
+        .enumerate()
+        .flat_map(|(file, (_, indexed))| (0..indexed.chunks.len()).map(move |c| (file, c)))
+        .collect();
+    let total = jobs.len();
+    let mut done = 0;
+    let mut saved = 0; // Files in `pending` before this one are in the index
+    let _ = progress.send(IndexEvent::Progress { done, total }).await;
+    for (n, batch) in jobs.chunks(EMBED_BATCH).enumerate() {
+        let input = batch
+            .iter()
+            .map(|&(file, c)| {
+                let (path, indexed) = &pending[file];
+                format!("{path}\n\n{}", indexed.chunks[c].text)
+            })
+            .collect();
+        let embeddings = match client.embed(&index.source.model, input).await {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                let _ = storage::write_json(&path, &index);
+                return Err(e);
+            }
+        };
+        for (&(file, c), embedding) in batch.iter().zip(embeddings) {
+            pending[file].1.chunks[c].embedding = embedding;
+        }
+        done += batch.len();
+        let _ = progress.send(IndexEvent::Progress { done, total }).await;
+
+        // Files whose last chunk was in this batch are complete
+        let complete = jobs
+            .get((n + 1) * EMBED_BATCH)
+            .map_or(pending.len(), |&(file, _)| file);
+        index
+            .files
+            .extend(pending[saved..complete].iter_mut().map(std::mem::take));
+        saved = complete;
+        if n % SAVE_EVERY == SAVE_EVERY - 1 {
+            storage::write_json(&path, &index)?;
+        }
+    }
+    stats.chunks = total;
+
+    index
+        .files
+        .extend(pending[saved..].iter_mut().map(std::mem::take));
+    stats.files = index.files.len();
+    storage::write_json(&path, &index)?;
+    Ok((index, stats))
+}
+
+/// Split text into chunks of about `CHUNK_CHARS`, at line breaks, with a
+/// few lines of overlap. Lines are numbered from 1.
+fn chunk(text: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len() && (end == start || size + lines[end].len() < CHUNK_CHARS) {
+            size += lines[end].len() + 1;
+            end += 1;
+        }
+        let text = lines[start..end].join("\n");
+        if !text.trim().is_empty() {
+            chunks.push(Chunk {
+                first_line: start + 1,
+                last_line: end,
+                text,
+                embedding: Vec::new(),
+            });
+        }
+        if end == lines.len() {
+            break;
+        }
+        start = end.saturating_sub(CHUNK_OVERLAP).max(start + 1);
+    }
+    chunks
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// 64-bit FNV-1a; stable across builds, unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `root` as an absolute path, so the index is found from anywhere
+pub fn resolve_root(root: &Path) -> Result<PathBuf> {
+    let root = root.canonicalize()?;
+    if !root.is_dir() {
+        anyhow::bail!("{} is not a directory", root.display());
+    }
+    Ok(root)
+}
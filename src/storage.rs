@@ -0,0 +1,233 @@
+use crate::chat::ChatSession;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where llamaloop keeps its data: `$XDG_DATA_HOME/llamaloop`,
+/// falling back to `~/.local/share/llamaloop`
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("llamaloop")
+}
+
+/// Write a file so readers only ever see the old or the new contents:
+/// write a temp file next to it, flush it to disk, then rename over the target.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().context("path has no parent directory")?;
+    fs::create_dir_all(dir)?;
+
+    let file_name = path
+        .file_name()
+        .context("path has no file name")?
+        .to_string_lossy();
+    let tmp_path = dir.join(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_atomic(path, &serde_json::to_vec_pretty(value)?)
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Which sessions were open when llamaloop last ran
+#[derive(Serialize, Deserialize, Default)]
+pub struct OpenSessions {
+    pub ids: Vec<String>,
+    pub active_id: Option<String>,
+}
+
+/// A saved session as listed in the session browser
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    pub model: String,
+    pub message_count: usize,
+    pub updated_at: DateTime<Local>,
+}
+
+impl SessionSummary {
+    fn of(session: &ChatSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            title: session.display_title(),
+            model: session.current_model.clone(),
+            message_count: session.messages.len(),
+            updated_at: session.last_activity(),
+        }
+    }
+}
+
+/// A summary in `index.json`, along with the modification time of the
+/// session file it was taken from so edits made elsewhere are noticed
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    modified_ms: u64,
+    #[serde(flatten)]
+    summary: SessionSummary,
+}
+
+type SessionIndex = BTreeMap<String, IndexEntry>;
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// One JSON file per chat session under `<data dir>/sessions`
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            dir: root.join("sessions"),
+        }
+    }
+
+    fn session_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn open_sessions_path(&self) -> PathBuf {
+        self.dir.join("open.json")
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn load_index(&self) -> SessionIndex {
+        read_json(&self.index_path()).unwrap_or_default()
+    }
+
+    /// The index is only a cache, so failing to write it isn't an error:
+    /// `list` rebuilds whatever is missing or stale
+    fn save_index(&self, index: &SessionIndex) {
+        let _ = write_json(&self.index_path(), index);
+    }
+
+    pub fn save(&self, session: &ChatSession) -> Result<()> {
+        let path = self.session_path(&session.id);
+        write_json(&path, session)?;
+
+        let mut index = self.load_index();
+        index.insert(
+            session.id.clone(),
+            IndexEntry {
+                modified_ms: modified_ms(&path).unwrap_or_default(),
+                summary: SessionSummary::of(session),
+            },
+        );
+        self.save_index(&index);
+        Ok(())
+    }
+
+    pub fn load(&self, id: &str) -> Result<ChatSession> {
+        read_json(&self.session_path(id))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        match fs::remove_file(self.session_path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        let mut index = self.load_index();
+        if index.remove(id).is_some() {
+            self.save_index(&index);
+        }
+        Ok(())
+    }
+
+    /// All saved sessions, most recently active first. Summaries come from
+    /// `index.json`; only session files that are new or changed since it was
+    /// written get read. Unreadable files are skipped.
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut cached = self.load_index();
+        let mut index = SessionIndex::new();
+        let mut changed = false;
+
+        let paths = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path());
+        for path in paths {
+            let is_session = path.extension().is_some_and(|ext| ext == "json")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name != "open.json" && name != "index.json");
+            if !is_session {
+                continue;
+            }
+            let Some(id) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+
+            let modified = modified_ms(&path).unwrap_or_default();
+            let entry = match cached.remove(&id) {
+                Some(entry) if entry.modified_ms == modified => entry,
+                _ => {
+                    changed = true;
+                    let Ok(session) = read_json::<ChatSession>(&path) else {
+                        continue;
+                    };
+                    IndexEntry {
+                        modified_ms: modified,
+                        summary: SessionSummary::of(&session),
+                    }
+                }
+            };
+            index.insert(id, entry);
+        }
+
+        // Anything left over belongs to a session file that's gone
+        if changed || !cached.is_empty() {
+            self.save_index(&index);
+        }
+
+        let mut summaries: Vec<SessionSummary> =
+            index.into_values().map(|entry| entry.summary).collect();
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        summaries
+    }
+
+    pub fn save_open_sessions(&self, open: &OpenSessions) -> Result<()> {
+        write_json(&self.open_sessions_path(), open)
+    }
+
+    pub fn load_open_sessions(&self) -> OpenSessions {
+        read_json(&self.open_sessions_path()).unwrap_or_default()
+    }
+}
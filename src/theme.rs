@@ -22,6 +22,7 @@ impl TokyoNight {
     pub const PURPLE: Color = Color::Rgb(157, 124, 216);
 
     pub const YELLOW: Color = Color::Rgb(224, 175, 104);
+    pub const ORANGE: Color = Color::Rgb(255, 158, 100);
 
     pub const GREEN: Color = Color::Rgb(115, 218, 202);
     pub const GREEN1: Color = Color::Rgb(158, 206, 106);
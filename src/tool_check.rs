@@ -0,0 +1,503 @@
+use crate::api::{Capability, ChatMessage, GenerationOptions, OllamaClient};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Prompts every model is given, modelled on `scripts/test_ollama_tools.sh`
+pub const CASES: &[ToolCase] = &[
+    ToolCase {
+        name: "weather",
+        prompt: "What is the weather in San Francisco right now?",
+        tools: &["get_weather"],
+        expect: Some(("get_weather", "location")),
+    },
+    ToolCase {
+        name: "math",
+        prompt: "Use the calculator to work out 42 * 17.",
+        tools: &["calculate"],
+        expect: Some(("calculate", "expression")),
+    },
+    ToolCase {
+        name: "choice",
+        prompt: "Search the web for the React 19 release notes.",
+        tools: &["get_weather", "calculate", "web_search"],
+        expect: Some(("web_search", "query")),
+    },
+    ToolCase {
+        name: "restraint",
+        prompt: "Reply with just the word hello.",
+        tools: &["get_weather", "calculate"],
+        expect: None,
+    },
+];
+
+/// Replies are only judged on how they start
+const MAX_REPLY_TOKENS: i64 = 512;
+
+/// Longest reply kept in the report
+const REPLY_EXCERPT: usize = 400;
+
+pub struct ToolCase {
+    pub name: &'static str,
+    pub prompt: &'static str,
+    tools: &'static [&'static str],
+    /// The tool that should be called and an argument it needs, or `None`
+    /// when the model should answer without a tool
+    pub expect: Option<(&'static str, &'static str)>,
+}
+
+/// How a model answered one case
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The expected call, in the response's `tool_calls`
+    Native {
+        call: String,
+    },
+    /// The expected call, written into the reply text instead
+    Fallback {
+        call: String,
+        format: &'static str,
+    },
+    /// A call to the wrong tool, or without the arguments it needs
+    WrongCall {
+        call: String,
+    },
+    /// No call at all
+    NoCall,
+    Failed {
+        error: String,
+    },
+}
+
+impl Outcome {
+    /// 2 for the right behaviour natively, 1 for the right call in the text
+    pub fn points(&self, case: &ToolCase) -> u32 {
+        match (self, case.expect) {
+            (Outcome::Native { .. }, Some(_)) => 2,
+            (Outcome::Fallback { .. }, Some(_)) => 1,
+            (Outcome::NoCall, None) => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Native { .. } => "native",
+            Outcome::Fallback { format, .. } => format,
+            Outcome::WrongCall { .. } => "wrong call",
+            Outcome::NoCall => "no call",
+            Outcome::Failed { .. } => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub case: &'static str,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub duration_ms: u64,
+    pub reply: String,
+}
+
+/// Everything one model has answered so far
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelReport {
+    pub model: String,
+    /// Whether Ollama accepts `tools` for the model; `None` until it's asked
+    pub native_tools: Option<bool>,
+    pub results: Vec<Option<CaseResult>>,
+    /// Why the model wasn't asked, e.g. an embedding model that can't chat
+    pub skipped: Option<String>,
+}
+
+impl ModelReport {
+    /// Points out of the maximum, as a percentage
+    pub fn score(&self) -> u32 {
+        let points: u32 = CASES
+            .iter()
+            .zip(&self.results)
+            .filter_map(|(case, result)| Some(result.as_ref()?.outcome.points(case)))
+            .sum();
+        points * 100 / (CASES.len() as u32 * 2)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.skipped.is_some() || self.results.iter().all(Option::is_some)
+    }
+}
+
+enum CheckEvent {
+    Case {
+        model: usize,
+        case: usize,
+        native_tools: bool,
+        result: CaseResult,
+    },
+    Skipped {
+        model: usize,
+        reason: String,
+    },
+}
+
+/// Runs the cases against each model in turn, one request at a time so only
+/// one model needs to be loaded
+#[derive(Default)]
+pub struct ToolCheck {
+    pub models: Vec<ModelReport>,
+    pub selected: usize,
+    pub started: Option<DateTime<Local>>,
+    pub report_path: Option<PathBuf>,
+    receiver: Option<mpsc::Receiver<CheckEvent>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ToolCheck {
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub fn start(&mut self, client: &OllamaClient, models: Vec<String>) {
+        self.stop();
+        self.models = models
+            .iter()
+            .map(|model| ModelReport {
+                model: model.clone(),
+                native_tools: None,
+                results: vec![None; CASES.len()],
+                skipped: None,
+            })
+            .collect();
+        self.selected = 0;
+        self.started = Some(Local::now());
+        self.report_path = None;
+
+        let (tx, rx) = mpsc::channel(16);
+        let client = client.clone();
+        self.receiver = Some(rx);
+        self.handle = Some(tokio::spawn(async move {
+            for (model_index, model) in models.iter().enumerate() {
+                if let Some(reason) = skip_reason(&client, model).await {
+                    let event = CheckEvent::Skipped {
+                        model: model_index,
+                        reason,
+                    };
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                let mut native_tools = true;
+                for (case_index, case) in CASES.iter().enumerate() {
+                    let started = Instant::now();
+                    let mut reply = run_case(&client, model, case, native_tools).await;
+                    // Models without a tool template reject `tools`; give them
+                    // the definitions in the prompt to see what they write
+                    if native_tools && reply.as_ref().is_err_and(is_unsupported) {
+                        native_tools = false;
+                        reply = run_case(&client, model, case, false).await;
+                    }
+                    let (outcome, reply) = match reply {
+                        Ok(message) => (judge(case, &message), excerpt(&message.content)),
+                        Err(e) => (
+                            Outcome::Failed {
+                                error: e.to_string(),
+                            },
+                            String::new(),
+                        ),
+                    };
+                    let event = CheckEvent::Case {
+                        model: model_index,
+                        case: case_index,
+                        native_tools,
+                        result: CaseResult {
+                            case: case.name,
+                            outcome,
+                            duration_ms: started.elapsed().as_millis() as u64,
+                            reply,
+                        },
+                    };
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }));
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+        self.receiver = None;
+    }
+
+    /// Take in finished cases; true once the run has ended
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &mut self.receiver else {
+            return false;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok(CheckEvent::Case {
+                    model,
+                    case,
+                    native_tools,
+                    result,
+                }) => {
+                    if let Some(report) = self.models.get_mut(model) {
+                        report.native_tools = Some(native_tools);
+                        report.results[case] = Some(result);
+                    }
+                }
+                Ok(CheckEvent::Skipped { model, reason }) => {
+                    if let Some(report) = self.models.get_mut(model) {
+                        report.skipped = Some(reason);
+                    }
+                }
+                Err(mpsc::error::TryRecvError::Empty) => return false,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    self.handle = None;
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// (model, case) waiting on a reply
+    pub fn current(&self) -> Option<(usize, usize)> {
+        if !self.is_running() {
+            return None;
+        }
+        self.models.iter().enumerate().find_map(|(m, report)| {
+            if report.skipped.is_some() {
+                return None;
+            }
+            let case = report.results.iter().position(Option::is_none)?;
+            Some((m, case))
+        })
+    }
+
+    /// Cases answered, counting every case of a skipped model
+    pub fn finished_cases(&self) -> usize {
+        self.models
+            .iter()
+            .map(|m| match m.skipped {
+                Some(_) => CASES.len(),
+                None => m.results.iter().filter(|r| r.is_some()).count(),
+            })
+            .sum()
+    }
+
+    /// The JSON report: the cases, then each model's results and score
+    pub fn report(&self) -> Value {
+        json!({
+            "started": self.started.map(|t| t.to_rfc3339()),
+            "cases": CASES.iter().map(|case| json!({
+                "name": case.name,
+                "prompt": case.prompt,
+                "tools": case.tools,
+                "expect": case.expect.map(|(tool, _)| tool),
+            })).collect::<Vec<_>>(),
+            "models": self.models.iter().map(|report| json!({
+                "model": report.model,
+                "native_tools": report.native_tools,
+                "skipped": report.skipped,
+                "score": report.skipped.is_none().then(|| report.score()),
+                "results": report.results,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Why `model` can't take the check, going by the capabilities `/api/show`
+/// lists. A model that can't be inspected is tried anyway.
+async fn skip_reason(client: &OllamaClient, model: &str) -> Option<String> {
+    let capabilities = client
+        .show_model(model)
+        .await
+        .ok()?
+        .effective_capabilities();
+    if capabilities.contains(&Capability::Completion) {
+        return None;
+    }
+    Some(if capabilities.contains(&Capability::Embedding) {
+        "embedding model, it can't chat".to_string()
+    } else {
+        "it doesn't list completion among its capabilities".to_string()
+    })
+}
+
+async fn run_case(
+    client: &OllamaClient,
+    model: &str,
+    case: &ToolCase,
+    native_tools: bool,
+) -> anyhow::Result<ChatMessage> {
+    let tools: Vec<Value> = case.tools.iter().map(|name| definition(name)).collect();
+    // Without native tools the definitions go in the user message, leaving
+    // any SYSTEM prompt from the Modelfile to describe the call format
+    let (prompt, tools) = if native_tools {
+        (case.prompt.to_string(), tools)
+    } else {
+        (
+            format!(
+                "{}\n\nAvailable tools:\n{}",
+                case.prompt,
+                serde_json::to_string_pretty(&tools)?
+            ),
+            Vec::new(),
+        )
+    };
+    let options = GenerationOptions {
+        temperature: Some(0.0),
+        num_predict: Some(MAX_REPLY_TOKENS),
+        ..Default::default()
+    };
+    client
+        .complete(
+            model,
+            vec![json!({ "role": "user", "content": prompt })],
+            options,
+            None,
+            tools,
+        )
+        .await
+}
+
+fn is_unsupported(error: &anyhow::Error) -> bool {
+    error.to_string().contains("does not support tools")
+}
+
+fn definition(name: &str) -> Value {
+    let (description, argument, argument_description) = match name {
+        "get_weather" => (
+            "Get the current weather for a location",
+            "location",
+            "The city and state",
+        ),
+        "calculate" => (
+            "Evaluate a mathematical expression",
+            "expression",
+            "e.g. 2 * (3 + 4)",
+        ),
+        _ => ("Search the web", "query", "What to search for"),
+    };
+    json!({
+        "type": "function",
+        "function": {
+            "name": name,
+            "description": description,
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    argument: { "type": "string", "description": argument_description }
+                },
+                "required": [argument],
+            },
+        },
+    })
+}
+
+fn judge(case: &ToolCase, message: &ChatMessage) -> Outcome {
+    let (name, arguments, format) = if let Some(call) = message.tool_calls.first() {
+        let arguments = match &call.function.arguments {
+            Value::String(text) => serde_json::from_str(text).unwrap_or(Value::Null),
+            arguments => arguments.clone(),
+        };
+        (call.function.name.clone(), arguments, None)
+    } else if let Some((name, arguments, format)) = find_text_call(&message.content) {
+        (name, arguments, Some(format))
+    } else {
+        return Outcome::NoCall;
+    };
+
+    let call = format!("{name} {arguments}");
+    let expected = case
+        .expect
+        .is_some_and(|(tool, argument)| name == tool && !arguments[argument].is_null());
+    match (expected, format) {
+        (true, None) => Outcome::Native { call },
+        (true, Some(format)) => Outcome::Fallback { call, format },
+        (false, _) => Outcome::WrongCall { call },
+    }
+}
+
+/// A tool call written into the reply in one of the formats our Modelfiles
+/// ask for: JSON (`tool_call`, `function_call`, `tool_calls` or a bare
+/// name/arguments object), a ```tool:name block, or FUNCTION_CALL markers
+fn find_text_call(content: &str) -> Option<(String, Value, &'static str)> {
+    for (start, _) in content.match_indices('{') {
+        let mut values = serde_json::Deserializer::from_str(&content[start..]).into_iter::<Value>();
+        if let Some(Ok(value)) = values.next() {
+            if let Some((name, arguments)) = json_call(&value) {
+                return Some((name, arguments, "json"));
+            }
+        }
+    }
+
+    let mut lines = content.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(name) = line.strip_prefix("```tool:") {
+            let body = lines.by_ref().take_while(|l| !l.starts_with("```"));
+            return Some((name.trim().to_string(), key_values(body), "tool block"));
+        }
+        if line.contains("FUNCTION_CALL_START") {
+            let body: Vec<&str> = lines
+                .by_ref()
+                .take_while(|l| !l.contains("FUNCTION_CALL_END"))
+                .collect();
+            let name = body
+                .iter()
+                .find_map(|l| l.strip_prefix("function_name:"))?
+                .trim()
+                .to_string();
+            let parameters = body
+                .into_iter()
+                .filter(|l| !l.starts_with("function_name:"));
+            return Some((name, key_values(parameters), "FUNCTION_CALL"));
+        }
+    }
+    None
+}
+
+fn json_call(value: &Value) -> Option<(String, Value)> {
+    let call = if value["tool_call"].is_object() {
+        &value["tool_call"]
+    } else if value["function_call"].is_object() {
+        &value["function_call"]
+    } else if value["tool_calls"][0]["function"].is_object() {
+        &value["tool_calls"][0]["function"]
+    } else {
+        value
+    };
+    let name = call["name"].as_str()?;
+    let arguments = match call.get("arguments").or_else(|| call.get("parameters"))? {
+        Value::String(text) => serde_json::from_str(text).unwrap_or(Value::Null),
+        arguments => arguments.clone(),
+    };
+    Some((name.to_string(), arguments))
+}
+
+/// `key: value` lines as a JSON object
+fn key_values<'a>(lines: impl Iterator<Item = &'a str>) -> Value {
+    let map = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), json!(value.trim())))
+        .filter(|(_, value)| value != "" && value != "|")
+        .collect();
+    Value::Object(map)
+}
+
+fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(REPLY_EXCERPT) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,329 @@
+use crate::api::ToolCall;
+use anyhow::{anyhow, bail, Context, Result};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Longest tool output sent back to the model
+const MAX_OUTPUT: usize = 16 * 1024;
+
+/// Programs `run_command` may start unless `LLAMALOOP_TOOL_COMMANDS` lists
+/// others. Only the program is checked, not its arguments, so anything with
+/// a flag that runs other programs or writes files (`find -exec`, `rg --pre`,
+/// `tree -o`, `file -C`) is left out.
+const DEFAULT_COMMANDS: &[&str] = &[
+    "ls", "cat", "head", "tail", "wc", "grep", "du", "pwd", "date", "uname", "echo",
+];
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Tool definitions sent with chat requests, in Ollama's function format
+pub fn definitions() -> Vec<Value> {
+    vec![
+        function(
+            "read_file",
+            "Read a UTF-8 text file",
+            json!({
+                "path": { "type": "string", "description": "File path, relative to the working directory" }
+            }),
+            &["path"],
+        ),
+        function(
+            "list_directory",
+            "List the entries of a directory; directories end with /",
+            json!({
+                "path": { "type": "string", "description": "Directory path, defaults to the working directory" }
+            }),
+            &[],
+        ),
+        function(
+            "run_command",
+            &format!(
+                "Run a command without a shell (no pipes, redirection or globbing). \
+                 Allowed programs: {}",
+                allowed_commands().join(", ")
+            ),
+            json!({
+                "command": { "type": "string", "description": "Program and arguments, e.g. `grep -rn TODO src`" }
+            }),
+            &["command"],
+        ),
+        function(
+            "http_get",
+            "Fetch a URL on this machine (localhost only) with an HTTP GET",
+            json!({
+                "url": { "type": "string", "description": "e.g. http://localhost:8080/health" }
+            }),
+            &["url"],
+        ),
+    ]
+}
+
+fn function(name: &str, description: &str, properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": name,
+            "description": description,
+            "parameters": {
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            },
+        },
+    })
+}
+
+/// Programs `run_command` accepts: `LLAMALOOP_TOOL_COMMANDS` (comma
+/// separated) or the read-only defaults
+pub fn allowed_commands() -> Vec<String> {
+    let configured: Vec<String> = std::env::var("LLAMALOOP_TOOL_COMMANDS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_COMMANDS.iter().map(|c| c.to_string()).collect()
+    } else {
+        configured
+    }
+}
+
+/// Run a tool call and return the text to send back to the model. Failures
+/// are reported to the model too, so it can try something else.
+pub async fn run(call: ToolCall) -> String {
+    let args = arguments(&call.function.arguments);
+    let result = match call.function.name.as_str() {
+        "read_file" => read_file(&args).await,
+        "list_directory" => list_directory(&args).await,
+        "run_command" => run_command(&args).await,
+        "http_get" => http_get(&args).await,
+        name => Err(anyhow!("Unknown tool `{name}`")),
+    };
+    match result {
+        Ok(output) => output,
+        Err(e) => format!("Error: {e:#}"),
+    }
+}
+
+/// Arguments are an object, but some models send it JSON-encoded as a string
+fn arguments(value: &Value) -> Value {
+    match value {
+        Value::String(text) => serde_json::from_str(text).unwrap_or(Value::Null),
+        value => value.clone(),
+    }
+}
+
+fn string_arg<'a>(args: &'a Value, key: &str) -> Result<&'a str> {
+    args[key]
+        .as_str()
+        .ok_or_else(|| anyhow!("Missing string argument `{key}`"))
+}
+
+/// Reads at most `MAX_OUTPUT` bytes, so a huge file or a device like
+/// `/dev/zero` can't exhaust memory
+async fn read_file(args: &Value) -> Result<String> {
+    let path = string_arg(args, "path")?;
+    let file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("can't read {path}"))?;
+    let size = file.metadata().await?.len();
+    let mut bytes = Vec::new();
+    file.take(MAX_OUTPUT as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await
+        .with_context(|| format!("can't read {path}"))?;
+
+    let complete = bytes.len() <= MAX_OUTPUT;
+    bytes.truncate(MAX_OUTPUT);
+    let mut text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        // The cut may fall inside a character
+        Err(e) if !complete && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).expect("valid prefix")
+        }
+        Err(_) => bail!("{path} is not a UTF-8 text file"),
+    };
+    if !complete {
+        let shown = text.len();
+        match (size as usize).checked_sub(shown).filter(|_| size > 0) {
+            Some(rest) => text.push_str(&format!("\n… {rest} more bytes not shown")),
+            None => text.push_str(&format!("\n… only the first {shown} bytes shown")),
+        }
+    }
+    Ok(text)
+}
+
+async fn list_directory(args: &Value) -> Result<String> {
+    let path = args["path"].as_str().unwrap_or(".");
+    let mut dir = tokio::fs::read_dir(path)
+        .await
+        .with_context(|| format!("can't list {path}"))?;
+
+    let mut entries = Vec::new();
+    while let Some(entry) = dir.next_entry().await? {
+        let mut name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type().await.is_ok_and(|t| t.is_dir()) {
+            name.push('/');
+        }
+        entries.push(name);
+    }
+    entries.sort();
+    Ok(truncate(entries.join("\n")))
+}
+
+async fn run_command(args: &Value) -> Result<String> {
+    let words = split_command(string_arg(args, "command")?)?;
+    let Some((program, rest)) = words.split_first() else {
+        bail!("The command is empty");
+    };
+    if !allowed_commands().contains(program) {
+        bail!(
+            "`{program}` is not allowed. Allowed programs: {}",
+            allowed_commands().join(", ")
+        );
+    }
+
+    let mut child = tokio::process::Command::new(program)
+        .args(rest)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("can't run {program}"))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let output = async {
+        // Each pipe is closed once its limit is reached, which stops most
+        // writers; anything still running after that gets killed
+        let (stdout, stderr) = tokio::try_join!(read_limited(stdout), read_limited(stderr))?;
+        if stdout.len() > MAX_OUTPUT || stderr.len() > MAX_OUTPUT {
+            let _ = child.start_kill();
+        }
+        anyhow::Ok((child.wait().await?, stdout, stderr))
+    };
+    let (status, stdout, stderr) = tokio::time::timeout(COMMAND_TIMEOUT, output)
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", COMMAND_TIMEOUT.as_secs()))?
+        .with_context(|| format!("can't run {program}"))?;
+
+    let cut = stdout.len() > MAX_OUTPUT || stderr.len() > MAX_OUTPUT;
+    let mut text = format!("{status}\n");
+    text.push_str(&String::from_utf8_lossy(&stdout));
+    if !stderr.is_empty() {
+        text.push_str("\nstderr:\n");
+        text.push_str(&String::from_utf8_lossy(&stderr));
+    }
+    Ok(cut_off(text, cut))
+}
+
+/// Read up to one byte past `MAX_OUTPUT`, enough to tell whether there was more
+async fn read_limited(reader: impl AsyncRead + Unpin) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(MAX_OUTPUT as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await?;
+    Ok(bytes)
+}
+
+/// Split a command line into words, honouring quotes and backslashes the way
+/// a shell would, without interpreting anything else
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                let escaped = chars.next().ok_or_else(|| anyhow!("Trailing backslash"))?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        bail!("Unterminated quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+async fn http_get(args: &Value) -> Result<String> {
+    let url = reqwest::Url::parse(string_arg(args, "url")?)?;
+    let host = url.host_str().unwrap_or_default();
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let local = host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    if !local || !matches!(url.scheme(), "http" | "https") {
+        bail!("Only http(s) URLs on localhost are allowed");
+    }
+
+    // No redirects, so a local server can't bounce us elsewhere
+    let client = reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let response = client.get(url).send().await?;
+    let status = response.status();
+
+    // Stop reading once there's more than could be shown
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk?);
+        if body.len() > MAX_OUTPUT {
+            break;
+        }
+    }
+    let cut = body.len() > MAX_OUTPUT;
+    let body = String::from_utf8_lossy(&body);
+    Ok(cut_off(format!("HTTP {status}\n\n{body}"), cut))
+}
+
+/// Like `truncate`, for output that was only read up to the limit, so how
+/// much is missing isn't known
+fn cut_off(mut output: String, cut: bool) -> String {
+    if output.len() > MAX_OUTPUT || cut {
+        let mut end = MAX_OUTPUT.min(output.len());
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        output.truncate(end);
+        output.push_str("\n… output cut off here");
+    }
+    output
+}
+
+fn truncate(mut output: String) -> String {
+    if output.len() > MAX_OUTPUT {
+        let mut end = MAX_OUTPUT;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        let dropped = output.len() - end;
+        output.truncate(end);
+        output.push_str(&format!("\n… {dropped} more bytes not shown"));
+    }
+    output
+}
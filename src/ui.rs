@@ -1,14 +1,29 @@
+use crate::api::{Capability, GenerationMetrics};
 use crate::app::{App, CurrentScreen, LogLevel, ModelsTabView, ModelsViewMode};
-use crate::chat::{InputMode, MessageRole};
+use crate::chat::{ChatSession, InputMode, MessageRole, TranscriptSearch, TranscriptView};
+use crate::compare::CompareColumn;
+use crate::downloads::{DownloadJob, DownloadManager, DownloadStatus};
+use crate::files::FileContext;
+use crate::markdown;
+use crate::modelfile::{ChangeKind, Severity};
+use crate::modelfile_editor::{CreateStatus, EditorFocus, ModelfileEditor, ModelfileLibrary};
+use crate::rag::IndexJob;
 use crate::theme::{self, TokyoNight};
+use crate::tool_check::{Outcome, CASES};
 use humansize::{format_size, BINARY};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Clear, Gauge, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, Gauge, LineGauge, List, ListItem, ListState, Paragraph,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Tabs, Wrap,
+    },
     Frame,
 };
+use std::path::PathBuf;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     // Set background
@@ -32,6 +47,9 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         CurrentScreen::Logs => draw_logs(f, app, chunks[1]),
         CurrentScreen::Chat => draw_chat(f, app, chunks[1]),
         CurrentScreen::Help => draw_help(f, chunks[1]),
+        CurrentScreen::ModelfileEditor => draw_modelfile_editor(f, app, chunks[1]),
+        CurrentScreen::Compare => draw_compare(f, app, chunks[1]),
+        CurrentScreen::ToolCheck => draw_tool_check(f, app, chunks[1]),
     }
 
     draw_footer(f, app, chunks[2]);
@@ -121,9 +139,14 @@ fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
                     ("t", "Switch Tab"),
                     ("↑↓", "Navigate"),
                     ("v", "View Mode"),
+                    ("Enter", "Details"),
+                    ("T", "Tool Check"),
+                    ("e", "Edit Modelfile"),
                     ("i", "Install"),
                     ("p", "Pull"),
-                    ("d", "Delete"),
+                    ("[ ]", "Job"),
+                    ("x/z/R", "Cancel/Pause/Retry"),
+                    ("D", "Delete"),
                     ("?", "Help"),
                 ]
             }
@@ -149,6 +172,57 @@ fn draw_footer(f: &mut Frame, app: &App, area: Rect) {
                 ]
             }
         },
+        CurrentScreen::ModelfileEditor
+            if app
+                .modelfile_editor
+                .as_ref()
+                .is_some_and(|e| e.library.is_some()) =>
+        {
+            vec![
+                ("↑↓", "Select"),
+                ("Enter", "Load"),
+                ("d", "Diff vs Buffer"),
+                ("Esc", "Close"),
+            ]
+        }
+        CurrentScreen::ModelfileEditor => {
+            vec![
+                ("^S", "Create Model"),
+                ("^O", "Open modelfiles/"),
+                ("^F", "Format"),
+                ("Tab", "Name/Content"),
+                ("^Z/^Y", "Undo/Redo"),
+                ("Esc", "Back"),
+            ]
+        }
+        CurrentScreen::Compare if app.compare.picker.is_some() => {
+            vec![
+                ("↑↓", "Select"),
+                ("Space", "Pick"),
+                ("Enter", "Compare"),
+                ("Esc", "Cancel"),
+            ]
+        }
+        CurrentScreen::Compare => {
+            vec![
+                ("i", "Prompt"),
+                ("m", "Models"),
+                ("p", "Parallel/Sequential"),
+                ("t", "Multi-turn"),
+                ("c", "Clear"),
+                ("^C", "Stop"),
+                ("PgUp/PgDn", "Scroll"),
+                ("Esc", "Back to Chat"),
+            ]
+        }
+        CurrentScreen::ToolCheck => {
+            vec![
+                ("↑↓", "Select Model"),
+                ("r", "Run Again"),
+                ("^C", "Stop"),
+                ("Esc", "Back to Models"),
+            ]
+        }
         CurrentScreen::Dashboard => {
             vec![
                 ("Tab", "Next"),
@@ -488,7 +562,27 @@ fn draw_models_unified(f: &mut Frame, app: &mut App, area: Rect) {
                 .split(chunks[1]);
 
             draw_view_mode_selector(f, app, lib_chunks[0]);
-            draw_models_list(f, app, lib_chunks[1]);
+
+            if app.downloads.jobs.is_empty() {
+                draw_models_with_inspector(f, app, lib_chunks[1]);
+            } else {
+                // Status + overall gauge + rate line + one row per layer
+                let layer_rows = app.downloads.selected_job().map_or(0, |job| {
+                    let layers = job.progress.layers.len();
+                    layers.min(MAX_LAYER_ROWS) + usize::from(layers > MAX_LAYER_ROWS)
+                });
+                let detail_height = 5 + layer_rows;
+                let queue_height = app.downloads.jobs.len().min(MAX_QUEUE_ROWS) + 2;
+                let panel_height = detail_height.max(queue_height) as u16;
+
+                let split = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(panel_height)])
+                    .split(lib_chunks[1]);
+
+                draw_models_with_inspector(f, app, split[0]);
+                draw_downloads_panel(f, &app.downloads, app.animation_tick, split[1]);
+            }
         }
         ModelsTabView::ApiExplorer => {
             draw_api_explorer_view(f, app, chunks[1]);
@@ -749,8 +843,6 @@ fn draw_network_instructions(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(instructions, area);
 }
 
-
-
 fn draw_view_mode_selector(f: &mut Frame, app: &App, area: Rect) {
     let mode_text = match app.models_view_mode {
         ModelsViewMode::All => "📦 All Models",
@@ -798,6 +890,164 @@ fn draw_view_mode_selector(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(selector, area);
 }
 
+fn draw_models_with_inspector(f: &mut Frame, app: &mut App, area: Rect) {
+    if !app.model_inspector.visible {
+        draw_models_list(f, app, area);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(area);
+
+    draw_models_list(f, app, chunks[0]);
+    draw_model_inspector(f, app, chunks[1]);
+}
+
+fn draw_model_inspector(f: &mut Frame, app: &App, area: Rect) {
+    let inspector = &app.model_inspector;
+    let block = Block::default()
+        .title(format!("🔍 {}", inspector.model_name))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::MAGENTA))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+
+    let section = |title: &str| {
+        Line::from(Span::styled(
+            title.to_string(),
+            Style::default()
+                .fg(TokyoNight::MAGENTA)
+                .add_modifier(Modifier::BOLD),
+        ))
+    };
+    let field = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(
+                format!("  {label:<14}"),
+                Style::default().fg(TokyoNight::DARK5),
+            ),
+            Span::styled(value, Style::default().fg(TokyoNight::FG)),
+        ])
+    };
+    let or_dash = |value: &str| {
+        if value.is_empty() {
+            "—".to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    let text_block = |lines: &mut Vec<Line>, text: &str| {
+        if text.trim().is_empty() {
+            lines.push(Line::from(Span::styled(
+                "  (none)",
+                Style::default().fg(TokyoNight::COMMENT),
+            )));
+        }
+        for line in text.lines() {
+            lines.push(Line::from(Span::styled(
+                format!("  {line}"),
+                Style::default().fg(TokyoNight::FG_DARK),
+            )));
+        }
+    };
+
+    let mut lines: Vec<Line> = Vec::new();
+
+    if let Some(error) = &inspector.error {
+        lines.push(Line::from(Span::styled(
+            format!("❌ {error}"),
+            Style::default().fg(TokyoNight::RED),
+        )));
+    } else if let Some(info) = app.model_info_cache.get(&inspector.model_name) {
+        let details = &info.details;
+
+        lines.push(section("Overview"));
+        lines.push(field("Family", or_dash(&details.family)));
+        if let Some(families) = &details.families {
+            lines.push(field("Families", families.join(", ")));
+        }
+        lines.push(field(
+            "Architecture",
+            or_dash(info.architecture().unwrap_or_default()),
+        ));
+        lines.push(field("Parameters", or_dash(&details.parameter_size)));
+        lines.push(field("Quantization", or_dash(&details.quantization_level)));
+        lines.push(field("Format", or_dash(&details.format)));
+        lines.push(field(
+            "Context",
+            info.context_length()
+                .map(|c| format!("{c} tokens"))
+                .unwrap_or_else(|| "—".to_string()),
+        ));
+        if !details.parent_model.is_empty() {
+            lines.push(field("Parent", details.parent_model.clone()));
+        }
+        lines.push(Line::from(""));
+
+        lines.push(section("Capabilities"));
+        let mut chips = vec![Span::raw("  ")];
+        for capability in info.effective_capabilities() {
+            let (label, color) = match capability {
+                Capability::Completion => ("completion", TokyoNight::BLUE),
+                Capability::Tools => ("🛠 tools", TokyoNight::GREEN),
+                Capability::Vision => ("👁 vision", TokyoNight::CYAN),
+                Capability::Embedding => ("🧮 embedding", TokyoNight::YELLOW),
+                Capability::Insert => ("insert", TokyoNight::PURPLE),
+                Capability::Thinking => ("💭 thinking", TokyoNight::MAGENTA),
+                Capability::Other => continue,
+            };
+            chips.push(Span::styled(
+                format!(" {label} "),
+                Style::default().fg(TokyoNight::BG).bg(color),
+            ));
+            chips.push(Span::raw(" "));
+        }
+        lines.push(Line::from(chips));
+        lines.push(Line::from(""));
+
+        lines.push(section("Parameters"));
+        let parameters = info.parameter_list();
+        if parameters.is_empty() {
+            text_block(&mut lines, "");
+        }
+        for (key, value) in parameters {
+            lines.push(field(&key, value));
+        }
+        lines.push(Line::from(""));
+
+        lines.push(section("System Prompt"));
+        text_block(&mut lines, &info.system);
+        lines.push(Line::from(""));
+
+        lines.push(section("Template"));
+        text_block(&mut lines, &info.template);
+        lines.push(Line::from(""));
+
+        lines.push(section("License"));
+        // Licenses are often full legal texts; the Modelfile below has the rest
+        let license: String = info.license.lines().take(8).collect::<Vec<_>>().join("\n");
+        text_block(&mut lines, &license);
+        lines.push(Line::from(""));
+
+        lines.push(section("Modelfile"));
+        text_block(&mut lines, &info.modelfile);
+    } else {
+        lines.push(Line::from(Span::styled(
+            "Loading...",
+            Style::default().fg(TokyoNight::COMMENT),
+        )));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((inspector.scroll, 0));
+
+    f.render_widget(paragraph, area);
+}
+
 fn draw_models_list(f: &mut Frame, app: &mut App, area: Rect) {
     let mut items: Vec<ListItem> = Vec::new();
 
@@ -922,223 +1172,2420 @@ fn draw_models_list(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_stateful_widget(models_list, area, &mut app.models_list_state);
 }
 
-fn draw_logs(f: &mut Frame, app: &App, area: Rect) {
-    let logs: Vec<ListItem> = app
-        .logs
+const MAX_LAYER_ROWS: usize = 6;
+
+const MAX_QUEUE_ROWS: usize = 8;
+
+fn download_status_color(status: &DownloadStatus, tick: usize) -> ratatui::style::Color {
+    match status {
+        DownloadStatus::Active => theme::pulse_color(TokyoNight::CYAN, tick),
+        DownloadStatus::Queued => TokyoNight::BLUE,
+        DownloadStatus::Paused => TokyoNight::YELLOW,
+        DownloadStatus::Completed => TokyoNight::GREEN,
+        DownloadStatus::Failed(_) => TokyoNight::RED,
+        DownloadStatus::Cancelled => TokyoNight::COMMENT,
+    }
+}
+
+fn draw_downloads_panel(f: &mut Frame, downloads: &DownloadManager, tick: usize, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
+
+    let items: Vec<ListItem> = downloads
+        .jobs
         .iter()
-        .rev()
-        .map(|log| {
-            let (symbol, color) = match log.level {
-                LogLevel::Info => ("ℹ", TokyoNight::BLUE),
-                LogLevel::Warning => ("⚠", TokyoNight::YELLOW),
-                LogLevel::Error => ("✖", TokyoNight::RED),
+        .enumerate()
+        .map(|(i, job)| {
+            let (icon, label) = match &job.status {
+                DownloadStatus::Active => ("🔽", format!("{:.0}%", job.progress.ratio() * 100.0)),
+                DownloadStatus::Queued => match job.retry_at {
+                    Some(retry_at) => (
+                        "🔁",
+                        format!(
+                            "retry in {}s",
+                            retry_at
+                                .saturating_duration_since(std::time::Instant::now())
+                                .as_secs()
+                        ),
+                    ),
+                    None => ("⏳", "queued".to_string()),
+                },
+                DownloadStatus::Paused => {
+                    ("⏸️", format!("paused {:.0}%", job.progress.ratio() * 100.0))
+                }
+                DownloadStatus::Completed => ("✅", "done".to_string()),
+                DownloadStatus::Failed(_) => ("❌", "failed".to_string()),
+                DownloadStatus::Cancelled => ("🚫", "cancelled".to_string()),
+            };
+
+            let name_style = if i == downloads.selected {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .bg(TokyoNight::BG_HIGHLIGHT)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
             };
 
             ListItem::new(Line::from(vec![
-                Span::styled(format!("{symbol} "), Style::default().fg(color)),
+                Span::raw(format!("{icon} ")),
+                Span::styled(&job.model_name, name_style),
+                Span::raw(" "),
                 Span::styled(
-                    log.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
-                    Style::default().fg(TokyoNight::DARK5),
+                    label,
+                    Style::default().fg(download_status_color(&job.status, tick)),
                 ),
-                Span::raw(" "),
-                Span::styled(&log.message, Style::default().fg(TokyoNight::FG)),
             ]))
         })
         .collect();
 
-    let logs_list = List::new(logs).block(
+    // Keep the selected job visible when the queue is longer than the panel
+    let visible = chunks[0].height.saturating_sub(2) as usize;
+    let skip = (downloads.selected + 1).saturating_sub(visible);
+
+    let queue = List::new(items.into_iter().skip(skip).collect::<Vec<_>>()).block(
         Block::default()
-            .title("📜 System Logs")
+            .title(format!(
+                "📥 Downloads ({} active / {} pending, max {})",
+                downloads.active_count(),
+                downloads.pending_count(),
+                downloads.max_concurrent
+            ))
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .border_style(Style::default().fg(TokyoNight::TERMINAL_BLACK))
+            .border_style(Style::default().fg(TokyoNight::BLUE))
             .style(Style::default().bg(TokyoNight::BG_DARK)),
     );
+    f.render_widget(queue, chunks[0]);
 
-    f.render_widget(logs_list, area);
+    if let Some(job) = downloads.selected_job() {
+        draw_pull_progress(f, job, tick, chunks[1]);
+    }
 }
 
-fn draw_chat(f: &mut Frame, app: &App, area: Rect) {
-    // Check if models are available
-    if app.models.is_empty() {
-        draw_no_models_message(f, area);
-        return;
-    }
+fn draw_pull_progress(f: &mut Frame, job: &DownloadJob, tick: usize, area: Rect) {
+    let pull = &job.progress;
+    let border_color = download_status_color(&job.status, tick);
 
-    let chunks = Layout::default()
+    let block = Block::default()
+        .title(format!("🔽 {}", job.model_name))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(border_color))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut constraints = vec![
+        Constraint::Length(1), // Status
+        Constraint::Length(1), // Overall gauge
+        Constraint::Length(1), // Rate / ETA
+    ];
+    constraints.extend(std::iter::repeat_n(
+        Constraint::Length(1),
+        pull.layers.len().min(MAX_LAYER_ROWS),
+    ));
+    constraints.push(Constraint::Min(0));
+
+    let rows = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(0),    // Messages area
-            Constraint::Length(3), // Input area
-            Constraint::Length(1), // Status bar
-        ])
-        .split(area);
+        .constraints(constraints)
+        .split(inner);
 
-    // Draw messages area
-    let current_model = if app.chat_state.sessions.is_empty() {
-        "No model".to_string()
+    let status_color = if matches!(job.status, DownloadStatus::Failed(_)) {
+        TokyoNight::RED
     } else {
-        app.chat_state.sessions[app.chat_state.active_session_index]
-            .current_model
-            .clone()
+        TokyoNight::YELLOW
     };
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("Status: ", Style::default().fg(TokyoNight::DARK5)),
+            Span::styled(&pull.status, Style::default().fg(status_color)),
+        ])),
+        rows[0],
+    );
 
-    let messages_block = Block::default()
-        .title(format!(
-            "💬 Chat - {} [Session {}]",
-            current_model,
-            app.chat_state.active_session_index + 1
-        ))
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(TokyoNight::PURPLE))
-        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let total = pull.total_bytes();
+    let completed = pull.completed_bytes();
+    let overall_label = if total > 0 {
+        format!(
+            "{} / {} ({:.1}%)",
+            format_size(completed, BINARY),
+            format_size(total, BINARY),
+            pull.ratio() * 100.0
+        )
+    } else {
+        "waiting for manifest...".to_string()
+    };
+    let overall = Gauge::default()
+        .gauge_style(
+            Style::default()
+                .fg(TokyoNight::CYAN)
+                .bg(TokyoNight::BG_HIGHLIGHT),
+        )
+        .ratio(pull.ratio())
+        .label(overall_label);
+    f.render_widget(overall, rows[1]);
+
+    let rate = format!("{}/s", format_size(pull.bytes_per_sec as u64, BINARY));
+    let eta = pull
+        .eta()
+        .map(format_duration)
+        .unwrap_or_else(|| "--".to_string());
+    let elapsed = format_duration(
+        pull.finished_at
+            .unwrap_or_else(std::time::Instant::now)
+            .duration_since(pull.started_at),
+    );
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("Rate: ", Style::default().fg(TokyoNight::DARK5)),
+            Span::styled(rate, Style::default().fg(TokyoNight::GREEN)),
+            Span::styled("  │  ETA: ", Style::default().fg(TokyoNight::DARK5)),
+            Span::styled(eta, Style::default().fg(TokyoNight::MAGENTA)),
+            Span::styled("  │  Elapsed: ", Style::default().fg(TokyoNight::DARK5)),
+            Span::styled(elapsed, Style::default().fg(TokyoNight::FG_DARK)),
+        ])),
+        rows[2],
+    );
 
-    let messages_inner = messages_block.inner(chunks[0]);
-    f.render_widget(messages_block, chunks[0]);
+    for (i, layer) in pull.layers.iter().take(MAX_LAYER_ROWS).enumerate() {
+        let ratio = if layer.total > 0 {
+            (layer.completed as f64 / layer.total as f64).min(1.0)
+        } else {
+            0.0
+        };
+        let short_digest: String = layer
+            .digest
+            .trim_start_matches("sha256:")
+            .chars()
+            .take(12)
+            .collect();
+        let gauge = LineGauge::default()
+            .filled_style(Style::default().fg(if ratio >= 1.0 {
+                TokyoNight::GREEN
+            } else {
+                TokyoNight::BLUE
+            }))
+            .unfilled_style(Style::default().fg(TokyoNight::TERMINAL_BLACK))
+            .ratio(ratio)
+            .label(format!(
+                "{short_digest} {:>10} ",
+                format_size(layer.total, BINARY)
+            ));
+        f.render_widget(gauge, rows[3 + i]);
+    }
 
-    // Build messages list
-    if app.chat_state.sessions.is_empty() {
-        let welcome = Paragraph::new("Welcome to Chat! Press 'i' to start typing.")
-            .style(Style::default().fg(TokyoNight::FG_DARK))
-            .alignment(Alignment::Center);
-        f.render_widget(welcome, messages_inner);
+    if pull.layers.len() > MAX_LAYER_ROWS {
+        let hidden = pull.layers.len() - MAX_LAYER_ROWS;
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                format!("+{hidden} more layer(s)"),
+                Style::default().fg(TokyoNight::COMMENT),
+            )),
+            rows[3 + MAX_LAYER_ROWS],
+        );
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
     } else {
-        let session = &app.chat_state.sessions[app.chat_state.active_session_index];
+        format!("{secs}s")
+    }
+}
 
-        // Calculate available width for text wrapping
-        let available_width = messages_inner.width.saturating_sub(4) as usize; // Account for padding
+/// Nanoseconds as `350ms` or `1.24s`
+fn format_nanos(nanos: u64) -> String {
+    if nanos < 1_000_000_000 {
+        format!("{}ms", nanos / 1_000_000)
+    } else {
+        format!("{:.2}s", nanos as f64 / 1e9)
+    }
+}
 
-        let mut all_lines: Vec<Line> = Vec::new();
+/// `⚡ 42.1 tok/s · TTFT 350ms · load 1.20s · prompt 850 tok/s` for one reply
+fn format_metrics(metrics: &GenerationMetrics) -> String {
+    let mut parts = Vec::new();
+    if let Some(speed) = metrics.tokens_per_second() {
+        parts.push(format!("⚡ {speed:.1} tok/s"));
+    }
+    if let Some(first_token) = metrics.first_token {
+        parts.push(format!("TTFT {}", format_nanos(first_token)));
+    }
+    if let Some(load) = metrics.load_duration {
+        parts.push(format!("load {}", format_nanos(load)));
+    }
+    if let Some(speed) = metrics.prompt_tokens_per_second() {
+        parts.push(format!("prompt {speed:.0} tok/s"));
+    }
+    parts.join(" · ")
+}
 
-        for msg in &session.messages {
-            let (prefix, style) = match msg.role {
-                MessageRole::System => ("🔧 System", Style::default().fg(TokyoNight::YELLOW)),
-                MessageRole::User => ("👤 You", Style::default().fg(TokyoNight::CYAN)),
-                MessageRole::Assistant => ("🤖 Assistant", Style::default().fg(TokyoNight::GREEN)),
-            };
+/// Averages over every reply in the session that has metrics
+fn session_metrics_summary(session: &ChatSession) -> String {
+    let metrics: Vec<&GenerationMetrics> = session
+        .messages
+        .iter()
+        .filter_map(|m| m.metrics.as_ref())
+        .collect();
 
-            let time = msg.timestamp.format("%H:%M:%S").to_string();
-            let header = format!("{prefix} [{time}]:");
+    // Rates are over all tokens rather than a mean of per-reply rates
+    let rate = |pick: fn(&GenerationMetrics) -> (Option<u32>, Option<u64>)| {
+        let (tokens, nanos) = metrics
+            .iter()
+            .filter_map(|m| match pick(m) {
+                (Some(tokens), Some(nanos)) => Some((tokens as f64, nanos as f64)),
+                _ => None,
+            })
+            .fold((0.0, 0.0), |(t, n), (tokens, nanos)| {
+                (t + tokens, n + nanos)
+            });
+        (nanos > 0.0).then(|| tokens / (nanos / 1e9))
+    };
+    let first_tokens: Vec<u64> = metrics.iter().filter_map(|m| m.first_token).collect();
+    let loads: Vec<u64> = metrics.iter().filter_map(|m| m.load_duration).collect();
 
-            // Add header
-            all_lines.push(Line::from(Span::styled(
-                header,
-                style.add_modifier(Modifier::BOLD),
-            )));
+    let mut parts = Vec::new();
+    if let Some(speed) = rate(|m| (m.eval_count, m.eval_duration)) {
+        parts.push(format!("{speed:.1} tok/s"));
+    }
+    if !first_tokens.is_empty() {
+        let mean = first_tokens.iter().sum::<u64>() / first_tokens.len() as u64;
+        parts.push(format!("TTFT {}", format_nanos(mean)));
+    }
+    if let Some(speed) = rate(|m| (m.prompt_eval_count, m.prompt_eval_duration)) {
+        parts.push(format!("prompt {speed:.0} tok/s"));
+    }
+    if !loads.is_empty() {
+        parts.push(format!("load {}", format_nanos(loads.iter().sum())));
+    }
+    parts.join(" · ")
+}
 
-            // Wrap message content
-            let wrapped_lines = wrap_text(&msg.content, available_width);
-            for line in wrapped_lines {
-                all_lines.push(Line::from(Span::raw(line)));
-            }
+fn instruction_color(keyword: &str) -> ratatui::style::Color {
+    match keyword {
+        "FROM" => TokyoNight::MAGENTA,
+        "PARAMETER" => TokyoNight::BLUE,
+        "SYSTEM" => TokyoNight::GREEN,
+        "TEMPLATE" => TokyoNight::CYAN,
+        "MESSAGE" => TokyoNight::YELLOW,
+        "LICENSE" | "ADAPTER" => TokyoNight::PURPLE,
+        _ => TokyoNight::RED,
+    }
+}
 
-            // Add spacing
-            all_lines.push(Line::from(""));
-        }
+fn draw_modelfile_editor(f: &mut Frame, app: &App, area: Rect) {
+    let Some(editor) = &app.modelfile_editor else {
+        return;
+    };
 
-        // Add current streaming response if any
-        if session.is_streaming && !session.current_response.is_empty() {
-            all_lines.push(Line::from(Span::styled(
-                "🤖 Assistant [streaming...]:",
-                Style::default()
-                    .fg(TokyoNight::GREEN)
-                    .add_modifier(Modifier::BOLD),
-            )));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
 
-            let wrapped_lines = wrap_text(&session.current_response, available_width);
-            for line in wrapped_lines {
-                all_lines.push(Line::from(Span::raw(line)));
-            }
-            all_lines.push(Line::from(""));
-        }
+    draw_modelfile_name_bar(f, editor, app.animation_tick, chunks[0]);
 
-        // Scroll to bottom - show only the last messages that fit
-        let visible_height = messages_inner.height as usize;
-        let start_line = if all_lines.len() > visible_height {
-            all_lines.len() - visible_height
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(62), Constraint::Percentage(38)])
+        .split(chunks[1]);
+
+    let mut textarea = editor.textarea.clone();
+    if let Some(block) = textarea.block().cloned() {
+        let error_count = editor
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        let (row, col) = editor.textarea.cursor();
+        let status = if editor.loading {
+            "Loading...".to_string()
+        } else if error_count > 0 {
+            format!("✖ {error_count} error(s)")
         } else {
-            0
+            "✔ valid".to_string()
         };
+        textarea.set_block(block.title(format!(
+            "📝 Modelfile  Ln {}, Col {}  {status}",
+            row + 1,
+            col + 1,
+        )));
+    }
+    f.render_widget(&textarea, body[0]);
 
-        let visible_lines: Vec<Line> = all_lines
+    let side = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(45),
+            Constraint::Percentage(30),
+            Constraint::Min(5),
+        ])
+        .split(body[1]);
+
+    // Outline of parsed instructions
+    let outline: Vec<ListItem> = editor
+        .outline
+        .iter()
+        .map(|entry| {
+            let has_error = editor
+                .diagnostics
+                .iter()
+                .any(|d| d.line == entry.line && d.severity == Severity::Error);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:>3} ", entry.line),
+                    Style::default().fg(TokyoNight::DARK3),
+                ),
+                Span::styled(
+                    format!("{:<9} ", entry.keyword),
+                    Style::default()
+                        .fg(instruction_color(&entry.keyword))
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    entry.summary.clone(),
+                    Style::default().fg(if has_error {
+                        TokyoNight::RED
+                    } else {
+                        TokyoNight::FG_DARK
+                    }),
+                ),
+            ]))
+        })
+        .collect();
+    f.render_widget(
+        List::new(outline).block(
+            Block::default()
+                .title("🧭 Outline")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(TokyoNight::BLUE))
+                .style(Style::default().bg(TokyoNight::BG_DARK)),
+        ),
+        side[0],
+    );
+
+    // Diagnostics
+    let diagnostics: Vec<ListItem> = if editor.diagnostics.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "✔ No problems found",
+            Style::default().fg(TokyoNight::GREEN),
+        ))]
+    } else {
+        editor
+            .diagnostics
+            .iter()
+            .map(|d| {
+                let (icon, color) = match d.severity {
+                    Severity::Error => ("✖", TokyoNight::RED),
+                    Severity::Warning => ("⚠", TokyoNight::YELLOW),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{icon} "), Style::default().fg(color)),
+                    Span::styled(
+                        format!("{}:{} ", d.line, d.column),
+                        Style::default().fg(TokyoNight::DARK5),
+                    ),
+                    Span::styled(d.message.clone(), Style::default().fg(TokyoNight::FG)),
+                ]))
+            })
+            .collect()
+    };
+    f.render_widget(
+        List::new(diagnostics).block(
+            Block::default()
+                .title("🩺 Problems")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(if editor.has_errors() {
+                    TokyoNight::RED
+                } else {
+                    TokyoNight::GREEN
+                }))
+                .style(Style::default().bg(TokyoNight::BG_DARK)),
+        ),
+        side[1],
+    );
+
+    // Create progress, or contextual help while idle
+    let (title, lines) = if editor.create_status == CreateStatus::Idle {
+        let help = editor
+            .help_for_cursor()
+            .unwrap_or_else(|| "Move the cursor onto an instruction for help".to_string());
+        (
+            "💡 Help",
+            vec![Line::from(Span::styled(
+                help,
+                Style::default().fg(TokyoNight::FG_DARK),
+            ))],
+        )
+    } else {
+        let mut lines: Vec<Line> = editor
+            .create_log
+            .iter()
+            .map(|status| {
+                Line::from(Span::styled(
+                    format!("• {status}"),
+                    Style::default().fg(TokyoNight::FG_DARK),
+                ))
+            })
+            .collect();
+        match &editor.create_status {
+            CreateStatus::Succeeded => lines.push(Line::from(Span::styled(
+                "✅ Model created",
+                Style::default().fg(TokyoNight::GREEN),
+            ))),
+            CreateStatus::Failed(e) => lines.push(Line::from(Span::styled(
+                format!("❌ {e}"),
+                Style::default().fg(TokyoNight::RED),
+            ))),
+            _ => {}
+        }
+        // Keep the latest status in view
+        let visible = side[2].height.saturating_sub(2) as usize;
+        let skip = lines.len().saturating_sub(visible);
+        ("🛠️ Create", lines.into_iter().skip(skip).collect())
+    };
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(TokyoNight::PURPLE))
+                    .style(Style::default().bg(TokyoNight::BG_DARK)),
+            )
+            .wrap(Wrap { trim: false }),
+        side[2],
+    );
+
+    if let Some(library) = &editor.library {
+        draw_modelfile_library(f, editor, library, area);
+    }
+}
+
+fn draw_modelfile_library(
+    f: &mut Frame,
+    editor: &ModelfileEditor,
+    library: &ModelfileLibrary,
+    area: Rect,
+) {
+    let area = centered_rect(85, 80, area);
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
+
+    let items: Vec<ListItem> = library
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let errors = entry.count(Severity::Error);
+            let warnings = entry.count(Severity::Warning);
+            let (badge, color) = if errors > 0 {
+                (format!("✖ {errors}"), TokyoNight::RED)
+            } else if warnings > 0 {
+                (format!("⚠ {warnings}"), TokyoNight::YELLOW)
+            } else {
+                ("✔".to_string(), TokyoNight::GREEN)
+            };
+            let name_style = if i == library.selected {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
+            };
+            let item = ListItem::new(Line::from(vec![
+                Span::styled(format!("{badge:<4} "), Style::default().fg(color)),
+                Span::styled(entry.name.clone(), name_style),
+            ]));
+            if i == library.selected {
+                item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+            } else {
+                item
+            }
+        })
+        .collect();
+
+    let items = if items.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "No *.Modelfile files found",
+            Style::default().fg(TokyoNight::COMMENT),
+        ))]
+    } else {
+        items
+    };
+
+    f.render_widget(
+        List::new(items).block(
+            Block::default()
+                .title(format!("📂 {}", library.dir.display()))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(TokyoNight::CYAN))
+                .style(Style::default().bg(TokyoNight::BG_DARK)),
+        ),
+        chunks[0],
+    );
+
+    let Some(entry) = library.selected_entry() else {
+        return;
+    };
+
+    let (title, lines) = if library.show_diff {
+        let changes = editor.library_diff();
+        let mut lines = Vec::new();
+        if changes.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "✔ Buffer is equivalent to this file",
+                Style::default().fg(TokyoNight::GREEN),
+            )));
+        }
+        for change in changes {
+            let (sign, color) = match change.kind {
+                ChangeKind::Added => ("+", TokyoNight::GREEN),
+                ChangeKind::Removed => ("-", TokyoNight::RED),
+                ChangeKind::Changed => ("~", TokyoNight::YELLOW),
+            };
+            lines.push(Line::from(Span::styled(
+                format!("{sign} {}", change.field),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+            for (prefix, value, color) in [
+                ("  - ", change.old, TokyoNight::RED),
+                ("  + ", change.new, TokyoNight::GREEN),
+            ] {
+                let Some(value) = value else {
+                    continue;
+                };
+                for line in value.lines().take(6) {
+                    lines.push(Line::from(Span::styled(
+                        format!("{prefix}{line}"),
+                        Style::default().fg(color),
+                    )));
+                }
+            }
+        }
+        (format!("🔀 {} → buffer", entry.name), lines)
+    } else {
+        let mut lines = vec![Line::from(vec![
+            Span::styled("FROM ", Style::default().fg(TokyoNight::MAGENTA)),
+            Span::styled(
+                entry.modelfile.from().unwrap_or("?").to_string(),
+                Style::default().fg(TokyoNight::FG),
+            ),
+        ])];
+        for (key, values) in entry.modelfile.parameters() {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            lines.push(Line::from(vec![
+                Span::styled(format!("{key} "), Style::default().fg(TokyoNight::BLUE)),
+                Span::styled(values.join(", "), Style::default().fg(TokyoNight::FG_DARK)),
+            ]));
+        }
+        lines.push(Line::from(""));
+        if entry.diagnostics.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "✔ No problems found",
+                Style::default().fg(TokyoNight::GREEN),
+            )));
+        }
+        for d in &entry.diagnostics {
+            let (icon, color) = match d.severity {
+                Severity::Error => ("✖", TokyoNight::RED),
+                Severity::Warning => ("⚠", TokyoNight::YELLOW),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{icon} "), Style::default().fg(color)),
+                Span::styled(
+                    format!("{}:{} ", d.line, d.column),
+                    Style::default().fg(TokyoNight::DARK5),
+                ),
+                Span::styled(d.message.clone(), Style::default().fg(TokyoNight::FG)),
+            ]));
+        }
+        (format!("🩺 {}", entry.path.display()), lines)
+    };
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(TokyoNight::PURPLE))
+                    .style(Style::default().bg(TokyoNight::BG_DARK)),
+            )
+            .wrap(Wrap { trim: false }),
+        chunks[1],
+    );
+}
+
+fn draw_modelfile_name_bar(f: &mut Frame, editor: &ModelfileEditor, tick: usize, area: Rect) {
+    let name_focused = editor.focus == EditorFocus::Name;
+    let status = match &editor.create_status {
+        CreateStatus::Idle => {
+            Span::styled("Ctrl+S to create", Style::default().fg(TokyoNight::COMMENT))
+        }
+        CreateStatus::Running => Span::styled(
+            "⏳ creating...",
+            Style::default().fg(theme::pulse_color(TokyoNight::YELLOW, tick)),
+        ),
+        CreateStatus::Succeeded => {
+            Span::styled("✅ created", Style::default().fg(TokyoNight::GREEN))
+        }
+        CreateStatus::Failed(_) => Span::styled("❌ failed", Style::default().fg(TokyoNight::RED)),
+    };
+
+    let mut spans = vec![
+        Span::styled("Model name: ", Style::default().fg(TokyoNight::DARK5)),
+        Span::styled(
+            editor.model_name.clone(),
+            Style::default()
+                .fg(TokyoNight::YELLOW)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ];
+    if name_focused {
+        spans.push(Span::styled(
+            "▏",
+            Style::default().fg(theme::pulse_color(TokyoNight::CYAN, tick)),
+        ));
+    }
+    if let Some(source) = &editor.source_model {
+        spans.push(Span::styled(
+            format!("   (from {source})"),
+            Style::default().fg(TokyoNight::COMMENT),
+        ));
+    }
+    spans.push(Span::raw("   "));
+    spans.push(status);
+
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).block(
+            Block::default()
+                .title("🛠️ Modelfile Editor")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(if name_focused {
+                    TokyoNight::CYAN
+                } else {
+                    TokyoNight::TERMINAL_BLACK
+                }))
+                .style(Style::default().bg(TokyoNight::BG_DARK)),
+        ),
+        area,
+    );
+}
+
+fn draw_logs(f: &mut Frame, app: &App, area: Rect) {
+    let logs: Vec<ListItem> = app
+        .logs
+        .iter()
+        .rev()
+        .map(|log| {
+            let (symbol, color) = match log.level {
+                LogLevel::Info => ("ℹ", TokyoNight::BLUE),
+                LogLevel::Warning => ("⚠", TokyoNight::YELLOW),
+                LogLevel::Error => ("✖", TokyoNight::RED),
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{symbol} "), Style::default().fg(color)),
+                Span::styled(
+                    log.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    Style::default().fg(TokyoNight::DARK5),
+                ),
+                Span::raw(" "),
+                Span::styled(&log.message, Style::default().fg(TokyoNight::FG)),
+            ]))
+        })
+        .collect();
+
+    let logs_list = List::new(logs).block(
+        Block::default()
+            .title("📜 System Logs")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(TokyoNight::TERMINAL_BLACK))
+            .style(Style::default().bg(TokyoNight::BG_DARK)),
+    );
+
+    f.render_widget(logs_list, area);
+}
+
+/// Attached images and files as chips, packed into lines of `width`
+fn attachment_chips(images: &[PathBuf], files: &[FileContext], width: usize) -> Vec<Line<'static>> {
+    let mut chips = Vec::new();
+    for path in images {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let color = if path.is_file() {
+            TokyoNight::MAGENTA
+        } else {
+            TokyoNight::RED // Moved or deleted since it was attached
+        };
+        chips.push((format!(" 🖼️ {name} "), color));
+    }
+    for file in files {
+        // Excerpts found by retrieval get a book, files attached by hand a page
+        let icon = if file.lines.is_some() { "📚" } else { "📄" };
+        chips.push((format!(" {icon} {} ", file.label()), TokyoNight::BLUE));
+    }
+
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut used = 0;
+    for (text, color) in chips {
+        let chip_width = text.width() + 1;
+        if used > 0 && used + chip_width > width {
+            lines.push(Line::from(std::mem::take(&mut spans)));
+            used = 0;
+        }
+        used += chip_width;
+        spans.push(Span::styled(
+            text,
+            Style::default().fg(color).bg(TokyoNight::BG_HIGHLIGHT),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// What's waiting to go with the next message, for the input's bottom border
+fn pending_attachments(session: &ChatSession) -> Line<'static> {
+    const SHOWN_FILES: usize = 3;
+    let files = &session.attached_files;
+    let mut line = attachment_chips(
+        &session.attachments,
+        &files[..files.len().min(SHOWN_FILES)],
+        usize::MAX,
+    )
+    .pop()
+    .unwrap_or_default();
+    if files.len() > SHOWN_FILES {
+        line.spans.push(Span::styled(
+            format!("+{} more ", files.len() - SHOWN_FILES),
+            Style::default().fg(TokyoNight::BLUE),
+        ));
+    }
+    if !files.is_empty() {
+        let tokens: usize = files.iter().map(FileContext::tokens).sum();
+        line.spans.push(Span::styled(
+            format!("~{tokens} tokens "),
+            Style::default().fg(TokyoNight::DARK5),
+        ));
+    }
+    let clear = match (session.attachments.is_empty(), files.is_empty()) {
+        (true, true) => return line,
+        (false, true) => "/image clear",
+        (true, false) => "/file clear",
+        (false, false) => "/image clear, /file clear",
+    };
+    line.spans.push(Span::styled(
+        format!("• {clear} to remove "),
+        Style::default().fg(TokyoNight::DARK5),
+    ));
+    line
+}
+
+/// Lines of tool output shown in the transcript
+const TOOL_OUTPUT_LINES: usize = 8;
+
+fn draw_chat(f: &mut Frame, app: &mut App, area: Rect) {
+    // Check if models are available
+    if app.models.is_empty() {
+        draw_no_models_message(f, area);
+        return;
+    }
+
+    let area = if app.chat_state.show_sidebar {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(34), Constraint::Min(0)])
+            .split(area);
+        draw_session_sidebar(f, app, columns[0]);
+        columns[1]
+    } else {
+        area
+    };
+
+    // The input grows with its content, up to 8 lines
+    let input_lines = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .map_or(1, |s| s.input.lines().len());
+    let input_height = input_lines.clamp(1, 8) as u16 + 2;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),               // Messages area
+            Constraint::Length(1),            // Context gauge
+            Constraint::Length(input_height), // Input area
+            Constraint::Length(1),            // Status bar
+        ])
+        .split(area);
+
+    // Draw messages area
+    let current_model = if app.chat_state.sessions.is_empty() {
+        "No model".to_string()
+    } else {
+        app.chat_state.sessions[app.chat_state.active_session_index]
+            .current_model
+            .clone()
+    };
+
+    let session_title = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .map(|s| s.display_title())
+        .unwrap_or_default();
+
+    let messages_block = Block::default()
+        .title(format!(
+            "💬 Chat - {} [Session {}: {}]",
+            current_model,
+            app.chat_state.active_session_index + 1,
+            session_title
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::PURPLE))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+
+    let messages_inner = messages_block.inner(chunks[0]);
+    f.render_widget(messages_block, chunks[0]);
+
+    // Build messages list
+    if app.chat_state.sessions.is_empty() {
+        let welcome = Paragraph::new("Welcome to Chat! Press 'i' to start typing.")
+            .style(Style::default().fg(TokyoNight::FG_DARK))
+            .alignment(Alignment::Center);
+        f.render_widget(welcome, messages_inner);
+    } else {
+        let session = &app.chat_state.sessions[app.chat_state.active_session_index];
+
+        // Calculate available width for text wrapping
+        let available_width = messages_inner.width.saturating_sub(4) as usize; // Account for padding
+
+        let mut all_lines: Vec<Line> = Vec::new();
+
+        if let Some(prompt) = &session.system_prompt {
+            all_lines.push(Line::from(Span::styled(
+                "🧭 System prompt:",
+                Style::default()
+                    .fg(TokyoNight::MAGENTA)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            let wrapped = wrap_text(prompt, available_width);
+            let hidden = wrapped.len().saturating_sub(3);
+            for line in wrapped.into_iter().take(3) {
+                all_lines.push(Line::from(Span::styled(
+                    line,
+                    Style::default().fg(TokyoNight::COMMENT),
+                )));
+            }
+            if hidden > 0 {
+                all_lines.push(Line::from(Span::styled(
+                    format!("… {hidden} more line(s), P to change"),
+                    Style::default().fg(TokyoNight::DARK3),
+                )));
+            }
+            all_lines.push(Line::from(""));
+        }
+
+        // Code blocks are numbered for the `y` picker
+        let mut next_block = 1;
+        let mut message_starts = Vec::with_capacity(session.messages.len());
+        for (index, msg) in session.messages.iter().enumerate() {
+            message_starts.push(all_lines.len());
+            let (prefix, style) = match msg.role {
+                MessageRole::Notice => ("🔔 Notice", Style::default().fg(TokyoNight::YELLOW)),
+                MessageRole::User => ("👤 You", Style::default().fg(TokyoNight::CYAN)),
+                MessageRole::Assistant => ("🤖 Assistant", Style::default().fg(TokyoNight::GREEN)),
+                MessageRole::Tool => ("🛠️ Tool", Style::default().fg(TokyoNight::ORANGE)),
+            };
+
+            let time = msg.timestamp.format("%H:%M:%S").to_string();
+            let header = match &msg.tool_name {
+                Some(name) => format!("{prefix} {name} [{time}]:"),
+                None => format!("{prefix} [{time}]:"),
+            };
+
+            // Add header
+            let mut header_spans = vec![Span::styled(header, style.add_modifier(Modifier::BOLD))];
+            if msg.interrupted {
+                header_spans.push(Span::styled(
+                    " ⏹ interrupted",
+                    Style::default().fg(TokyoNight::YELLOW),
+                ));
+            }
+            let branches = session.branch_count(index);
+            if branches > 1 {
+                header_spans.push(Span::styled(
+                    format!(" 🌿 {}/{branches}", msg.branch + 1),
+                    Style::default().fg(TokyoNight::YELLOW),
+                ));
+            }
+            if let Some(metrics) = &msg.metrics {
+                let summary = format_metrics(metrics);
+                if !summary.is_empty() {
+                    header_spans.push(Span::styled(
+                        format!(" {summary}"),
+                        Style::default().fg(TokyoNight::COMMENT),
+                    ));
+                }
+            }
+            if session.editing_index == Some(index) {
+                header_spans.push(Span::styled(
+                    " ✏️ editing",
+                    Style::default().fg(TokyoNight::ORANGE),
+                ));
+            }
+            all_lines.push(Line::from(header_spans));
+
+            // Assistant replies are Markdown; everything else is shown as typed
+            if msg.role == MessageRole::Assistant {
+                let (lines, next) =
+                    app.chat_state
+                        .render_cache
+                        .get(&msg.content, next_block, available_width);
+                next_block = next;
+                all_lines.extend(lines.iter().cloned());
+            } else if msg.role == MessageRole::Tool {
+                // Tool output can be long; the model sees all of it
+                let lines = wrap_text(&msg.content, available_width);
+                let hidden = lines.len().saturating_sub(TOOL_OUTPUT_LINES);
+                for line in lines.into_iter().take(TOOL_OUTPUT_LINES) {
+                    all_lines.push(Line::from(Span::styled(
+                        line,
+                        Style::default().fg(TokyoNight::COMMENT),
+                    )));
+                }
+                if hidden > 0 {
+                    all_lines.push(Line::from(Span::styled(
+                        format!("… {hidden} more line(s)"),
+                        Style::default().fg(TokyoNight::DARK3),
+                    )));
+                }
+            } else {
+                for line in wrap_text(&msg.content, available_width) {
+                    all_lines.push(Line::from(Span::raw(line)));
+                }
+            }
+            all_lines.extend(attachment_chips(&msg.images, &msg.files, available_width));
+            for call in &msg.tool_calls {
+                let text = format!("🛠️ {} {}", call.function.name, call.function.arguments);
+                for line in wrap_text(&text, available_width) {
+                    all_lines.push(Line::from(Span::styled(
+                        line,
+                        Style::default().fg(TokyoNight::ORANGE),
+                    )));
+                }
+            }
+
+            // Add spacing
+            all_lines.push(Line::from(""));
+        }
+
+        app.chat_state.render_cache.sweep();
+
+        // Add current streaming response if any
+        if session.is_streaming && !session.current_response.is_empty() {
+            all_lines.push(Line::from(Span::styled(
+                "🤖 Assistant [streaming...]:",
+                Style::default()
+                    .fg(TokyoNight::GREEN)
+                    .add_modifier(Modifier::BOLD),
+            )));
+
+            all_lines.extend(markdown::wrap(
+                &session.response_markdown.lines(&session.current_response),
+                available_width,
+            ));
+            all_lines.push(Line::from(""));
+        }
+
+        let visible_height = messages_inner.height as usize;
+        let session_id = session.id.clone();
+
+        let view = &mut app.chat_state.view;
+        if view.session_id != session_id {
+            *view = TranscriptView {
+                session_id,
+                ..TranscriptView::default()
+            };
+        }
+        view.total_lines = all_lines.len();
+        view.page_height = visible_height;
+        view.message_starts = message_starts;
+
+        if let Some(search) = &mut view.search {
+            let matches = find_matches(&all_lines, &search.query);
+            search.match_count = matches.len();
+            if matches.is_empty() {
+                search.current = None;
+            } else {
+                // While typing, pick the last match above the bottom of the view
+                let bottom = view.scroll.map_or(usize::MAX, |top| top + visible_height);
+                let current = search.current.unwrap_or_else(|| {
+                    matches
+                        .iter()
+                        .rposition(|(line, ..)| *line < bottom)
+                        .unwrap_or(matches.len() - 1)
+                });
+                let current = current.min(matches.len() - 1);
+                search.current = Some(current);
+                highlight_matches(&mut all_lines, &matches, current);
+
+                if std::mem::take(&mut search.jump) {
+                    let line = matches[current].0;
+                    view.reveal(line);
+                }
+            }
+        }
+
+        let top = view.top();
+        let hidden_below = all_lines.len().saturating_sub(top + visible_height);
+        let visible_lines: Vec<Line> = all_lines
             .into_iter()
-            .skip(start_line)
+            .skip(top)
             .take(visible_height)
             .collect();
 
-        let messages_paragraph = Paragraph::new(visible_lines)
-            .style(Style::default().fg(TokyoNight::FG))
-            .wrap(Wrap { trim: false });
+        let messages_paragraph = Paragraph::new(visible_lines)
+            .style(Style::default().fg(TokyoNight::FG))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(messages_paragraph, messages_inner);
+
+        if view.scroll.is_some() {
+            let mut scrollbar_state = ScrollbarState::new(view.max_top()).position(top);
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .style(Style::default().fg(TokyoNight::DARK3)),
+                chunks[0].inner(ratatui::layout::Margin::new(0, 1)),
+                &mut scrollbar_state,
+            );
+            let note = format!(" ↓ {hidden_below} more lines • End to follow ");
+            let width = (note.chars().count() as u16).min(messages_inner.width);
+            f.render_widget(
+                Paragraph::new(note).style(
+                    Style::default()
+                        .fg(TokyoNight::BG_DARK)
+                        .bg(TokyoNight::YELLOW),
+                ),
+                Rect::new(
+                    messages_inner.right().saturating_sub(width),
+                    messages_inner.bottom().saturating_sub(1),
+                    width,
+                    1,
+                ),
+            );
+        }
+    }
+
+    if let Some(session) = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+    {
+        draw_context_gauge(
+            f,
+            session,
+            app.context_window(session),
+            app.indexing.as_ref(),
+            app.is_retrieving(&session.id),
+            chunks[1],
+        );
+    }
+
+    // Draw input area
+    let input_style = if app.chat_state.input_mode == InputMode::Editing {
+        Style::default()
+            .fg(TokyoNight::CYAN)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(TokyoNight::FG_DARK)
+    };
+
+    let editing_message = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .is_some_and(|s| s.editing_index.is_some());
+
+    let input_title = match app.chat_state.input_mode {
+        InputMode::Editing if editing_message => {
+            "✏️ Editing message (Enter resends from here, Alt+Enter newline, Esc cancel)"
+        }
+        InputMode::Editing => {
+            "📝 Input (Enter send, Alt+Enter newline, ↑↓ history, Esc exit, Ctrl+C stop)"
+        }
+        InputMode::Normal => "📝 Input (press 'i' to type)",
+        InputMode::ModelSelection => "📝 Input (selecting model...)",
+    };
+
+    if let Some(session) = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+    {
+        let mut input = session.input.clone();
+        input.set_block(
+            Block::default()
+                .title(input_title)
+                .title_bottom(pending_attachments(session))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(input_style)
+                .style(Style::default().bg(TokyoNight::BG_DARK)),
+        );
+        if app.chat_state.input_mode != InputMode::Editing {
+            input.set_cursor_style(Style::default());
+        }
+        f.render_widget(&input, chunks[2]);
+    }
+
+    // Draw status bar
+    let tokens = if !app.chat_state.sessions.is_empty() {
+        app.chat_state.sessions[app.chat_state.active_session_index].total_tokens
+    } else {
+        0
+    };
+
+    let generating = match app.generating_count() {
+        0 => String::new(),
+        n => format!("⏳ Generating: {n} | "),
+    };
+
+    let options = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .map(|s| s.options_summary())
+        .filter(|summary| !summary.is_empty())
+        .map(|summary| format!("⚙️ {summary} | "))
+        .unwrap_or_default();
+
+    let metrics = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .map(session_metrics_summary)
+        .filter(|summary| !summary.is_empty())
+        .map(|summary| format!("📈 {summary} | "))
+        .unwrap_or_default();
+
+    let search_status = |search: &TranscriptSearch| match search.current {
+        Some(current) => format!("{}/{}", current + 1, search.match_count),
+        None if search.query.is_empty() => String::new(),
+        None => "no matches".to_string(),
+    };
+
+    let status_text = match &app.chat_state.view.search {
+        Some(search) if search.editing => format!(
+            " 🔎 /{}▏ {}   (↑↓ jump • Enter keep highlights • Esc cancel)",
+            search.query,
+            search_status(search)
+        ),
+        search => format!(
+            " {generating}{}Tokens: {} | {metrics}{options}Mode: {} | Commands: (i)nput (c)lear (m)odel (n)ew (s)idebar [ ] switch (w) close (d)uplicate J/K move (b)rowse (P)resets (o)ptions (y)ank code (g) regenerate (E)dit , . branches (/) search PgUp/PgDn scroll ",
+            search.as_ref()
+                .map(|s| format!("🔎 {} {} (n/N, Esc clear) | ", s.query, search_status(s)))
+                .unwrap_or_default(),
+            tokens,
+            match app.chat_state.input_mode {
+                InputMode::Normal => "Normal",
+                InputMode::Editing => "Editing",
+                InputMode::ModelSelection => "Model Selection",
+            }
+        ),
+    };
+
+    let status = Paragraph::new(status_text).style(
+        Style::default()
+            .fg(TokyoNight::FG_DARK)
+            .bg(TokyoNight::BG_HIGHLIGHT),
+    );
+
+    f.render_widget(status, chunks[3]);
+
+    // Draw model selector if active
+    if app.chat_state.show_model_selector {
+        draw_model_selector(f, app);
+    }
+
+    if app.session_browser.visible {
+        draw_session_browser(f, app);
+    }
+
+    if app.preset_picker.visible {
+        draw_preset_picker(f, app);
+    }
+
+    if app.options_panel.visible {
+        draw_options_panel(f, app);
+    }
+
+    if app.code_block_picker.visible {
+        draw_code_block_picker(f, app);
+    }
+
+    if app.image_picker.visible {
+        draw_image_picker(f, app);
+    }
+
+    if app.pending_tool_batch().is_some() {
+        draw_tool_approval(f, app);
+    }
+}
+
+/// How much of the context window the conversation fills. Uses the token
+/// counts Ollama reported for the last reply, or our estimate of the last
+/// request when that is larger (cached prompts report fewer tokens).
+fn draw_context_gauge(
+    f: &mut Frame,
+    session: &ChatSession,
+    window: Option<usize>,
+    indexing: Option<&IndexJob>,
+    retrieving: bool,
+    area: Rect,
+) {
+    // Attached files count from the moment they're attached
+    let attached: usize = session.attached_files.iter().map(FileContext::tokens).sum();
+    let used = session.context_used.max(session.context_estimate) + attached;
+    let tokens = |n: usize| match n {
+        0..1000 => n.to_string(),
+        _ => format!("{:.1}k", n as f64 / 1000.0),
+    };
+
+    let mut label = match window {
+        Some(window) => format!("🧠 Context {} / {} tokens", tokens(used), tokens(window)),
+        None => format!("🧠 Context {} tokens, window unknown", tokens(used)),
+    };
+    label.push_str(&format!(" • {}", session.context_strategy.name()));
+    if session.context_dropped > 0 {
+        label.push_str(&format!(
+            " • {} older message(s) left out",
+            session.context_dropped
+        ));
+    }
+    if let Some(summary) = &session.context_summary {
+        label.push_str(&format!(" • {} summarized", summary.covers));
+    }
+    if attached > 0 {
+        label.push_str(&format!(" • {} in attached files", tokens(attached)));
+    }
+    if retrieving {
+        label.push_str(" • 📚 Searching");
+    } else if let Some(job) = indexing {
+        label.push_str(&format!(" • 📚 Indexing {}/{}", job.done, job.total));
+    } else if let Some(source) = &session.rag {
+        let name = source.root.file_name().unwrap_or(source.root.as_os_str());
+        label.push_str(&format!(
+            " • 📚 {} ({})",
+            name.to_string_lossy(),
+            source.model
+        ));
+    }
+    label.push(' ');
+
+    let ratio = window.map_or(0.0, |window| (used as f64 / window.max(1) as f64).min(1.0));
+    let color = if ratio >= 0.85 {
+        TokyoNight::RED
+    } else if ratio >= 0.6 {
+        TokyoNight::YELLOW
+    } else {
+        TokyoNight::GREEN
+    };
+    let gauge = LineGauge::default()
+        .filled_style(Style::default().fg(color))
+        .unfilled_style(Style::default().fg(TokyoNight::TERMINAL_BLACK))
+        .label(Span::styled(
+            label,
+            Style::default().fg(TokyoNight::FG_DARK),
+        ))
+        .ratio(ratio);
+    f.render_widget(gauge, area);
+}
+
+fn draw_compare(f: &mut Frame, app: &App, area: Rect) {
+    let compare = &app.compare;
+    let input_height = compare.input.lines().len().clamp(1, 6) as u16 + 2;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),               // Columns
+            Constraint::Length(input_height), // Prompt
+            Constraint::Length(1),            // Status bar
+        ])
+        .split(area);
+
+    if compare.columns.is_empty() {
+        let empty = Paragraph::new("Press 'm' to choose 2-4 models to compare.")
+            .style(Style::default().fg(TokyoNight::FG_DARK))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title("🆚 Compare")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(TokyoNight::PURPLE))
+                    .style(Style::default().bg(TokyoNight::BG_DARK)),
+            );
+        f.render_widget(empty, chunks[0]);
+    } else {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Ratio(1, compare.columns.len() as u32);
+                compare.columns.len()
+            ])
+            .split(chunks[0]);
+        for (column, area) in compare.columns.iter().zip(columns.iter()) {
+            draw_compare_column(f, column, compare.scroll_back, *area);
+        }
+    }
+
+    let (title, border) = if compare.editing {
+        (
+            "📝 Prompt (Enter send to all, Alt+Enter newline, Esc exit)",
+            Style::default()
+                .fg(TokyoNight::CYAN)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        (
+            "📝 Prompt (press 'i' to type)",
+            Style::default().fg(TokyoNight::FG_DARK),
+        )
+    };
+    let mut input = compare.input.clone();
+    input.set_block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(border)
+            .style(Style::default().bg(TokyoNight::BG_DARK)),
+    );
+    if !compare.editing {
+        input.set_cursor_style(Style::default());
+    }
+    f.render_widget(&input, chunks[1]);
+
+    let status = format!(
+        " 🆚 {} models | Runs: {} | Follow-ups: {}{} ",
+        compare.columns.len(),
+        if compare.sequential {
+            "one at a time"
+        } else {
+            "in parallel"
+        },
+        if compare.multi_turn {
+            "continue each conversation"
+        } else {
+            "start fresh"
+        },
+        if compare.scroll_back > 0 {
+            " | End to follow"
+        } else {
+            ""
+        }
+    );
+    f.render_widget(
+        Paragraph::new(status).style(
+            Style::default()
+                .fg(TokyoNight::FG_DARK)
+                .bg(TokyoNight::BG_HIGHLIGHT),
+        ),
+        chunks[2],
+    );
+
+    if compare.picker.is_some() {
+        draw_compare_picker(f, app);
+    }
+}
+
+fn draw_compare_column(f: &mut Frame, column: &CompareColumn, scroll_back: usize, area: Rect) {
+    let session = &column.session;
+    let state = if column.queued {
+        " ⏳ queued"
+    } else if session.is_streaming && session.current_response.is_empty() {
+        " ⏳ thinking…"
+    } else if session.is_streaming {
+        " ✍️ streaming…"
+    } else {
+        ""
+    };
+
+    // The latest reply's numbers sit on the bottom border
+    let metrics = session
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == MessageRole::Assistant)
+        .and_then(|m| m.metrics.as_ref())
+        .map(format_metrics)
+        .filter(|summary| !summary.is_empty())
+        .map(|summary| format!(" {summary} "))
+        .unwrap_or_default();
+
+    let block = Block::default()
+        .title(format!("🤖 {}{state}", session.current_model))
+        .title_bottom(Line::from(Span::styled(
+            metrics,
+            Style::default().fg(TokyoNight::COMMENT),
+        )))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(if session.is_streaming {
+            TokyoNight::GREEN
+        } else {
+            TokyoNight::PURPLE
+        }))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let width = inner.width.saturating_sub(1) as usize;
+    let mut lines: Vec<Line> = Vec::new();
+    for msg in &session.messages {
+        match msg.role {
+            MessageRole::User => {
+                lines.push(Line::from(Span::styled(
+                    "👤 You:",
+                    Style::default()
+                        .fg(TokyoNight::CYAN)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.extend(wrap_text(&msg.content, width).into_iter().map(Line::from));
+            }
+            MessageRole::Assistant => {
+                let header = if msg.interrupted {
+                    "🤖 Reply ⏹ interrupted:"
+                } else {
+                    "🤖 Reply:"
+                };
+                lines.push(Line::from(Span::styled(
+                    header,
+                    Style::default()
+                        .fg(TokyoNight::GREEN)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.extend(markdown::wrap(&markdown::render(&msg.content), width));
+            }
+            MessageRole::Notice | MessageRole::Tool => {
+                lines.extend(wrap_text(&msg.content, width).into_iter().map(|line| {
+                    Line::from(Span::styled(line, Style::default().fg(TokyoNight::YELLOW)))
+                }));
+            }
+        }
+        lines.push(Line::from(""));
+    }
+    if session.is_streaming && !session.current_response.is_empty() {
+        lines.extend(markdown::wrap(
+            &session.response_markdown.lines(&session.current_response),
+            width,
+        ));
+    }
+
+    // Follow the bottom unless scrolled back
+    let height = inner.height as usize;
+    let max_top = lines.len().saturating_sub(height);
+    let top = max_top.saturating_sub(scroll_back);
+    let visible: Vec<Line> = lines.into_iter().skip(top).take(height).collect();
+    f.render_widget(Paragraph::new(visible), inner);
+}
+
+fn draw_compare_picker(f: &mut Frame, app: &App) {
+    let Some(picker) = &app.compare.picker else {
+        return;
+    };
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(
+            "🆚 Models to Compare ({}/{})",
+            picker.chosen.len(),
+            crate::compare::MAX_MODELS
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::CYAN))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let items: Vec<ListItem> = picker
+        .models
+        .iter()
+        .map(|model| {
+            let (mark, style) = match picker.chosen.iter().position(|m| m == model) {
+                Some(pos) => (
+                    format!("[{}]", pos + 1),
+                    Style::default()
+                        .fg(TokyoNight::GREEN)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                None => ("[ ]".to_string(), Style::default().fg(TokyoNight::FG)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!(" {mark} "), style),
+                Span::styled(model.clone(), style),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(picker.selected));
+    let list = List::new(items).highlight_style(Style::default().bg(TokyoNight::BG_HIGHLIGHT));
+    f.render_stateful_widget(list, rows[0], &mut state);
+
+    let hint = Span::styled(
+        "↑↓ select • Space pick (columns follow pick order) • Enter compare • Esc cancel",
+        Style::default().fg(TokyoNight::DARK5),
+    );
+    f.render_widget(Paragraph::new(Line::from(hint)), rows[1]);
+}
+
+fn draw_code_block_picker(f: &mut Frame, app: &App) {
+    let area = centered_rect(80, 75, f.area());
+    f.render_widget(Clear, area);
+
+    let picker = &app.code_block_picker;
+    let block = Block::default()
+        .title(format!("📋 Code Blocks ({})", picker.blocks.len()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::MAGENTA))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(28), Constraint::Min(0)])
+        .split(rows[0]);
+
+    let items: Vec<ListItem> = picker
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, code)| {
+            let lang = if code.lang.is_empty() {
+                "text"
+            } else {
+                code.lang.as_str()
+            };
+            let style = if i == picker.selected {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
+            };
+            let item = ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("#{:<3}", i + 1),
+                    Style::default().fg(TokyoNight::YELLOW),
+                ),
+                Span::styled(format!("{lang:<12}"), style),
+                Span::styled(
+                    format!("{} lines", code.code.lines().count()),
+                    Style::default().fg(TokyoNight::DARK5),
+                ),
+            ]));
+            if i == picker.selected {
+                item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+            } else {
+                item
+            }
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    f.render_stateful_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::RIGHT)
+                .border_style(Style::default().fg(TokyoNight::TERMINAL_BLACK)),
+        ),
+        columns[0],
+        &mut state,
+    );
+
+    // Preview through the chat renderer so it is highlighted the same way
+    if let Some(code) = picker.blocks.get(picker.selected) {
+        let preview = columns[1].inner(ratatui::layout::Margin::new(1, 0));
+        let source = format!("```{}\n{}```", code.lang, code.code);
+        let lines = markdown::wrap(&markdown::render(&source), preview.width as usize);
+        f.render_widget(Paragraph::new(lines), preview);
+    }
+
+    let hint = if let Some(path) = &picker.path_buffer {
+        Span::styled(
+            format!("Write to: {path}▏  (Enter save • Esc cancel)"),
+            Style::default().fg(TokyoNight::YELLOW),
+        )
+    } else {
+        Span::styled(
+            "↑↓/1-9 select • Enter/y copy • w write to file • Esc close",
+            Style::default().fg(TokyoNight::DARK5),
+        )
+    };
+    f.render_widget(Paragraph::new(Line::from(hint)), rows[1]);
+}
+
+/// Width of each case's column in the tool check matrix
+const TOOL_CASE_WIDTH: usize = 16;
+
+/// Which models call tools properly: one row per model, one column per case,
+/// with the selected model's calls and replies underneath
+fn draw_tool_check(f: &mut Frame, app: &App, area: Rect) {
+    let check = &app.tool_check;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(CASES.len() as u16 * 2 + 2),
+        ])
+        .split(area);
+
+    let status = if let Some((model, case)) = check.current() {
+        format!(
+            " ⏳ {}/{} — {} on {} ",
+            check.finished_cases() + 1,
+            check.models.len() * CASES.len(),
+            CASES[case].name,
+            check.models[model].model
+        )
+    } else if let Some(path) = &check.report_path {
+        format!(" 📝 {} ", path.display())
+    } else {
+        String::new()
+    };
+    let block = Block::default()
+        .title("🧰 Tool Compatibility")
+        .title_bottom(Line::from(Span::styled(
+            status,
+            Style::default().fg(TokyoNight::COMMENT),
+        )))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(if check.is_running() {
+            TokyoNight::GREEN
+        } else {
+            TokyoNight::PURPLE
+        }))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+
+    if check.models.is_empty() {
+        let empty = Paragraph::new("No results yet. Press 'r' to check the installed models.")
+            .style(Style::default().fg(TokyoNight::FG_DARK))
+            .alignment(Alignment::Center)
+            .block(block);
+        f.render_widget(empty, area);
+        return;
+    }
+
+    let name_width = check
+        .models
+        .iter()
+        .map(|m| m.model.width())
+        .max()
+        .unwrap_or(0)
+        .clamp(5, 40)
+        + 2;
+    let current = check.current();
+
+    let mut header = vec![Span::styled(
+        format!("  {:<name_width$}", "Model"),
+        Style::default().fg(TokyoNight::DARK5),
+    )];
+    for case in CASES {
+        header.push(Span::styled(
+            format!("{:<TOOL_CASE_WIDTH$}", case.name),
+            Style::default().fg(TokyoNight::DARK5),
+        ));
+    }
+    header.push(Span::styled(
+        format!("{:<10}{}", "tools", "score"),
+        Style::default().fg(TokyoNight::DARK5),
+    ));
+
+    let mut items = vec![ListItem::new(Line::from(header))];
+    for (m, report) in check.models.iter().enumerate() {
+        let width = name_width - 2;
+        let name: String = report.model.chars().take(width).collect();
+        let mut spans = vec![Span::styled(
+            format!("  {name:<width$}"),
+            Style::default().fg(TokyoNight::FG),
+        )];
+        spans.push(Span::raw("  "));
+        for (c, (case, result)) in CASES.iter().zip(&report.results).enumerate() {
+            let (text, color) = match result {
+                None if report.skipped.is_some() => ("–".to_string(), TokyoNight::DARK3),
+                Some(result) => {
+                    let mark = match result.outcome.points(case) {
+                        2 => "✅",
+                        1 => "🟡",
+                        _ => "❌",
+                    };
+                    let color = match result.outcome.points(case) {
+                        2 => TokyoNight::GREEN,
+                        1 => TokyoNight::YELLOW,
+                        _ => TokyoNight::RED,
+                    };
+                    (format!("{mark} {}", result.outcome.label()), color)
+                }
+                None if current == Some((m, c)) => ("⏳".to_string(), TokyoNight::CYAN),
+                None => ("·".to_string(), TokyoNight::DARK3),
+            };
+            let pad = TOOL_CASE_WIDTH.saturating_sub(text.width());
+            spans.push(Span::styled(
+                format!("{text}{}", " ".repeat(pad)),
+                Style::default().fg(color),
+            ));
+        }
+        let (tools, color) = match report.native_tools {
+            _ if report.skipped.is_some() => ("skipped", TokyoNight::DARK5),
+            Some(true) => ("native", TokyoNight::GREEN),
+            Some(false) => ("prompt", TokyoNight::YELLOW),
+            None => ("?", TokyoNight::DARK3),
+        };
+        spans.push(Span::styled(
+            format!("{tools:<10}"),
+            Style::default().fg(color),
+        ));
+        if report.is_finished() && report.skipped.is_none() {
+            spans.push(Span::styled(
+                format!("{}%", report.score()),
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+
+        let item = ListItem::new(Line::from(spans));
+        items.push(if m == check.selected {
+            item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+        } else {
+            item
+        });
+    }
+    let mut state = ListState::default();
+    state.select(Some(check.selected + 1));
+    f.render_stateful_widget(List::new(items).block(block), chunks[0], &mut state);
+
+    // The selected model's calls and the start of each reply
+    let Some(report) = check.models.get(check.selected) else {
+        return;
+    };
+    let details = Block::default()
+        .title(format!("🔍 {}", report.model))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::TERMINAL_BLACK))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let mut lines = Vec::new();
+    if let Some(reason) = &report.skipped {
+        lines.push(Line::from(Span::styled(
+            format!("Skipped: {reason}"),
+            Style::default().fg(TokyoNight::COMMENT),
+        )));
+    }
+    for (case, result) in CASES.iter().zip(&report.results) {
+        let Some(result) = result else {
+            continue;
+        };
+        let detail = match &result.outcome {
+            Outcome::Native { call }
+            | Outcome::Fallback { call, .. }
+            | Outcome::WrongCall { call } => call.clone(),
+            Outcome::NoCall => String::new(),
+            Outcome::Failed { error } => error.clone(),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:<12}", case.name),
+                Style::default().fg(TokyoNight::YELLOW),
+            ),
+            Span::styled(
+                format!("{:<12}", result.outcome.label()),
+                Style::default().fg(TokyoNight::FG),
+            ),
+            Span::styled(
+                format!("{:.1}s  ", result.duration_ms as f64 / 1000.0),
+                Style::default().fg(TokyoNight::DARK5),
+            ),
+            Span::styled(detail, Style::default().fg(TokyoNight::ORANGE)),
+        ]));
+        let reply = result.reply.lines().find(|l| !l.trim().is_empty());
+        lines.push(Line::from(Span::styled(
+            format!("            {}", reply.unwrap_or("")),
+            Style::default().fg(TokyoNight::COMMENT),
+        )));
+    }
+    f.render_widget(Paragraph::new(lines).block(details), chunks[1]);
+}
+
+fn draw_image_picker(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let picker = &app.image_picker;
+    let block = Block::default()
+        .title(format!("🖼️ Attach Image — {}", picker.dir.display()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::MAGENTA))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    if picker.entries.is_empty() {
+        f.render_widget(
+            Paragraph::new("No images or folders here")
+                .style(Style::default().fg(TokyoNight::DARK5))
+                .alignment(Alignment::Center),
+            rows[0],
+        );
+    } else {
+        let items: Vec<ListItem> = picker
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, (name, is_dir))| {
+                let (icon, color) = if *is_dir {
+                    ("📁", TokyoNight::BLUE)
+                } else {
+                    ("🖼️", TokyoNight::FG)
+                };
+                let item = ListItem::new(Line::from(Span::styled(
+                    format!("{icon} {name}{}", if *is_dir { "/" } else { "" }),
+                    Style::default().fg(color),
+                )));
+                if i == picker.selected {
+                    item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+                } else {
+                    item
+                }
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(picker.selected));
+        f.render_stateful_widget(List::new(items), rows[0], &mut state);
+    }
+
+    f.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "↑↓ select • Enter attach/open • Backspace parent folder • Esc close",
+            Style::default().fg(TokyoNight::DARK5),
+        ))),
+        rows[1],
+    );
+}
 
-        f.render_widget(messages_paragraph, messages_inner);
+/// Ask before running a tool call the model made
+fn draw_tool_approval(f: &mut Frame, app: &App) {
+    let Some(batch) = app.pending_tool_batch() else {
+        return;
+    };
+    let Some(call) = batch.pending() else {
+        return;
+    };
+    let area = centered_rect(70, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let title = app
+        .chat_state
+        .session_index(&batch.session_id)
+        .map(|i| app.chat_state.sessions[i].display_title())
+        .unwrap_or_default();
+    let block = Block::default()
+        .title(format!(
+            "🛠️ Run tool call {}/{}? — {title}",
+            batch.next + 1,
+            batch.calls.len()
+        ))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::ORANGE))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let width = rows[0].width.saturating_sub(2) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            call.function.name.clone(),
+            Style::default()
+                .fg(TokyoNight::ORANGE)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    let arguments = serde_json::to_string_pretty(&call.function.arguments)
+        .unwrap_or_else(|_| call.function.arguments.to_string());
+    for line in wrap_text(&arguments, width) {
+        lines.push(Line::from(Span::styled(
+            line,
+            Style::default().fg(TokyoNight::FG),
+        )));
     }
+    f.render_widget(
+        Paragraph::new(lines),
+        rows[0].inner(ratatui::layout::Margin::new(1, 0)),
+    );
+
+    f.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "y/Enter run • n decline • Esc decline the rest",
+            Style::default().fg(TokyoNight::DARK5),
+        ))),
+        rows[1],
+    );
+}
+
+fn draw_options_panel(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let panel = &app.options_panel;
+    let Some(session) = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+    else {
+        return;
+    };
+    let block = Block::default()
+        .title(format!("⚙️ Generation Options — {}", session.current_model))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::MAGENTA))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let items: Vec<ListItem> = ChatSession::option_keys()
+        .enumerate()
+        .map(|(i, key)| {
+            let selected = i == panel.selected;
+            let value = match (&panel.edit_buffer, session.option_value(key)) {
+                (Some(buffer), _) if selected => Span::styled(
+                    format!("{buffer}▏"),
+                    Style::default().fg(TokyoNight::YELLOW),
+                ),
+                (_, Some(value)) => Span::styled(value, Style::default().fg(TokyoNight::GREEN)),
+                (_, None) => Span::styled("model default", Style::default().fg(TokyoNight::DARK5)),
+            };
+            let key_style = if selected {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
+            };
+            let item = ListItem::new(Line::from(vec![
+                Span::styled(format!(" {key:<16}"), key_style),
+                value,
+            ]));
+            if selected {
+                item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+            } else {
+                item
+            }
+        })
+        .collect();
+    f.render_widget(List::new(items), rows[0]);
 
-    // Draw input area
-    let input_style = if app.chat_state.input_mode == InputMode::Editing {
-        Style::default()
-            .fg(TokyoNight::CYAN)
-            .add_modifier(Modifier::BOLD)
+    let hint = if let Some(error) = &panel.error {
+        Span::styled(format!("❌ {error}"), Style::default().fg(TokyoNight::RED))
+    } else if panel.edit_buffer.is_some() {
+        Span::styled(
+            "Enter save • empty resets • stop takes a comma-separated list • Esc cancel",
+            Style::default().fg(TokyoNight::YELLOW),
+        )
     } else {
-        Style::default().fg(TokyoNight::FG_DARK)
+        Span::styled(
+            "↑↓ select • Enter edit • x reset • R reset all • Esc close",
+            Style::default().fg(TokyoNight::DARK5),
+        )
     };
+    f.render_widget(Paragraph::new(Line::from(hint)), rows[1]);
+}
 
-    let input_title = match app.chat_state.input_mode {
-        InputMode::Editing => "📝 Input (ESC to exit, Enter to send)",
-        InputMode::Normal => "📝 Input (press 'i' to type)",
-        InputMode::ModelSelection => "📝 Input (selecting model...)",
-    };
+fn draw_preset_picker(f: &mut Frame, app: &App) {
+    let area = centered_rect(75, 70, f.area());
+    f.render_widget(Clear, area);
 
-    let input_text = if !app.chat_state.sessions.is_empty() {
-        app.chat_state.sessions[app.chat_state.active_session_index]
-            .input_buffer
-            .as_str()
+    let picker = &app.preset_picker;
+    let block = Block::default()
+        .title("🧭 System Prompt Presets")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::MAGENTA))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(rows[0]);
+
+    let current_prompt = app
+        .chat_state
+        .sessions
+        .get(app.chat_state.active_session_index)
+        .and_then(|s| s.system_prompt.as_deref());
+
+    let items: Vec<ListItem> = app
+        .presets
+        .presets
+        .iter()
+        .enumerate()
+        .map(|(i, preset)| {
+            let in_use = current_prompt == Some(preset.prompt.as_str());
+            let style = if i == picker.selected {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
+            };
+            let item = ListItem::new(Line::from(vec![
+                Span::styled(
+                    if in_use { "● " } else { "  " },
+                    Style::default().fg(TokyoNight::GREEN),
+                ),
+                Span::styled(preset.name.clone(), style),
+            ]));
+            if i == picker.selected {
+                item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+            } else {
+                item
+            }
+        })
+        .collect();
+    f.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::RIGHT)
+                .border_style(Style::default().fg(TokyoNight::TERMINAL_BLACK)),
+        ),
+        columns[0],
+    );
+
+    let preview = app
+        .presets
+        .presets
+        .get(picker.selected)
+        .map(|p| p.prompt.as_str())
+        .unwrap_or("No presets yet. Set a prompt with /system, then press s to save it.");
+    f.render_widget(
+        Paragraph::new(preview)
+            .style(Style::default().fg(TokyoNight::FG_DARK))
+            .wrap(Wrap { trim: false }),
+        columns[1].inner(ratatui::layout::Margin::new(1, 0)),
+    );
+
+    let hint = if picker.confirm_delete {
+        Span::styled(
+            "Delete this preset? (y/N)",
+            Style::default().fg(TokyoNight::RED),
+        )
+    } else if let Some(name) = &picker.name_buffer {
+        Span::styled(
+            format!("Save current prompt as: {name}▏  (Enter save • Esc cancel)"),
+            Style::default().fg(TokyoNight::YELLOW),
+        )
     } else {
-        ""
+        Span::styled(
+            "↑↓ select • Enter apply • c clear prompt • s save current prompt • d delete • Esc close",
+            Style::default().fg(TokyoNight::DARK5),
+        )
     };
+    f.render_widget(Paragraph::new(Line::from(hint)), rows[1]);
+}
 
-    let input = Paragraph::new(input_text)
-        .block(
+fn draw_session_sidebar(f: &mut Frame, app: &App, area: Rect) {
+    let today = chrono::Local::now().date_naive();
+    let width = area.width.saturating_sub(4) as usize;
+
+    let items: Vec<ListItem> = app
+        .chat_state
+        .sessions
+        .iter()
+        .enumerate()
+        .map(|(i, session)| {
+            let active = i == app.chat_state.active_session_index;
+            let marker = if session.is_streaming {
+                Span::styled(
+                    "● ",
+                    Style::default().fg(theme::pulse_color(TokyoNight::GREEN, app.animation_tick)),
+                )
+            } else if active {
+                Span::styled("▶ ", Style::default().fg(TokyoNight::CYAN))
+            } else {
+                Span::raw("  ")
+            };
+
+            let title: String = format!("{}. {}", i + 1, session.display_title())
+                .chars()
+                .take(width.saturating_sub(2))
+                .collect();
+            let title_style = if active {
+                Style::default()
+                    .fg(TokyoNight::CYAN)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(TokyoNight::FG)
+            };
+
+            let last_activity = session.last_activity();
+            let when = if last_activity.date_naive() == today {
+                last_activity.format("%H:%M").to_string()
+            } else {
+                last_activity.format("%b %d").to_string()
+            };
+            let details: String = format!(
+                "{} · {} msgs · {when}",
+                session.current_model,
+                session.messages.len()
+            )
+            .chars()
+            .take(width.saturating_sub(2))
+            .collect();
+
+            let item = ListItem::new(vec![
+                Line::from(vec![marker, Span::styled(title, title_style)]),
+                Line::from(Span::styled(
+                    format!("  {details}"),
+                    Style::default().fg(TokyoNight::COMMENT),
+                )),
+            ]);
+            if active {
+                item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+            } else {
+                item
+            }
+        })
+        .collect();
+
+    f.render_widget(
+        List::new(items).block(
             Block::default()
-                .title(input_title)
+                .title(format!("🗂️ Sessions ({})", app.chat_state.sessions.len()))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(input_style)
+                .border_style(Style::default().fg(TokyoNight::BLUE))
                 .style(Style::default().bg(TokyoNight::BG_DARK)),
-        )
-        .style(Style::default().fg(TokyoNight::FG))
-        .wrap(Wrap { trim: false });
+        ),
+        area,
+    );
+}
 
-    f.render_widget(input, chunks[1]);
+fn draw_session_browser(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 70, f.area());
+    f.render_widget(Clear, area);
 
-    // Draw status bar
-    let tokens = if !app.chat_state.sessions.is_empty() {
-        app.chat_state.sessions[app.chat_state.active_session_index].total_tokens
+    let browser = &app.session_browser;
+    let block = Block::default()
+        .title(format!("🗂️ Saved Sessions ({})", browser.sessions.len()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(TokyoNight::CYAN))
+        .style(Style::default().bg(TokyoNight::BG_DARK));
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let items: Vec<ListItem> = if browser.sessions.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "No saved sessions yet. Conversations are saved as you chat.",
+            Style::default().fg(TokyoNight::COMMENT),
+        ))]
     } else {
-        0
+        browser
+            .sessions
+            .iter()
+            .enumerate()
+            .map(|(i, summary)| {
+                let is_open = app.chat_state.session_index(&summary.id).is_some();
+                let title = match &browser.rename_buffer {
+                    Some(buffer) if i == browser.selected => format!("{buffer}▏"),
+                    _ => summary.title.clone(),
+                };
+                let title_style = if i == browser.selected {
+                    Style::default()
+                        .fg(TokyoNight::CYAN)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(TokyoNight::FG)
+                };
+                let item = ListItem::new(Line::from(vec![
+                    Span::styled(
+                        if is_open { "● " } else { "  " },
+                        Style::default().fg(TokyoNight::GREEN),
+                    ),
+                    Span::styled(title, title_style),
+                    Span::styled(
+                        format!("  {}", summary.model),
+                        Style::default().fg(TokyoNight::PURPLE),
+                    ),
+                    Span::styled(
+                        format!(
+                            "  {} msgs  {}",
+                            summary.message_count,
+                            summary.updated_at.format("%Y-%m-%d %H:%M")
+                        ),
+                        Style::default().fg(TokyoNight::COMMENT),
+                    ),
+                ]));
+                if i == browser.selected {
+                    item.style(Style::default().bg(TokyoNight::BG_HIGHLIGHT))
+                } else {
+                    item
+                }
+            })
+            .collect()
     };
+    f.render_widget(List::new(items), chunks[0]);
 
-    let status_text = format!(
-        " Tokens: {} | Mode: {} | Commands: (i)nput (c)lear (m)odel (n)ew session ",
-        tokens,
-        match app.chat_state.input_mode {
-            InputMode::Normal => "Normal",
-            InputMode::Editing => "Editing",
-            InputMode::ModelSelection => "Model Selection",
-        }
-    );
+    let hint = if browser.confirm_delete {
+        Span::styled(
+            "Delete this session permanently? (y/N)",
+            Style::default().fg(TokyoNight::RED),
+        )
+    } else if browser.rename_buffer.is_some() {
+        Span::styled(
+            "Enter save name • Esc cancel • empty name resets to the first question",
+            Style::default().fg(TokyoNight::YELLOW),
+        )
+    } else {
+        Span::styled(
+            "↑↓ select • Enter reopen • r rename • d delete • Esc close",
+            Style::default().fg(TokyoNight::DARK5),
+        )
+    };
+    f.render_widget(Paragraph::new(Line::from(hint)), chunks[1]);
+}
 
-    let status = Paragraph::new(status_text).style(
-        Style::default()
-            .fg(TokyoNight::FG_DARK)
-            .bg(TokyoNight::BG_HIGHLIGHT),
-    );
+/// Every occurrence of `query` in the transcript as (line, start, end) byte
+/// ranges. Lowercase queries ignore case, like vim's smartcase.
+fn find_matches(lines: &[Line], query: &str) -> Vec<(usize, usize, usize)> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    let query = if ignore_case {
+        query.to_ascii_lowercase()
+    } else {
+        query.to_string()
+    };
+
+    let mut matches = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let mut text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        if ignore_case {
+            text.make_ascii_lowercase();
+        }
+        for (start, found) in text.match_indices(&query) {
+            matches.push((i, start, start + found.len()));
+        }
+    }
+    matches
+}
 
-    f.render_widget(status, chunks[2]);
+fn highlight_matches(lines: &mut [Line], matches: &[(usize, usize, usize)], current: usize) {
+    for (i, &(line_index, start, end)) in matches.iter().enumerate() {
+        let style = if i == current {
+            Style::default()
+                .fg(TokyoNight::BG_DARK)
+                .bg(TokyoNight::ORANGE)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+                .fg(TokyoNight::BG_DARK)
+                .bg(TokyoNight::YELLOW)
+        };
 
-    // Draw model selector if active
-    if app.chat_state.show_model_selector {
-        draw_model_selector(f, app);
+        let line = &mut lines[line_index];
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        for span in line.spans.drain(..) {
+            let span_end = offset + span.content.len();
+            // Split the span into the parts before, inside and after the match
+            let cuts = [start.clamp(offset, span_end), end.clamp(offset, span_end)];
+            let pieces = [
+                (offset, cuts[0], span.style),
+                (cuts[0], cuts[1], style),
+                (cuts[1], span_end, span.style),
+            ];
+            for (from, to, piece_style) in pieces {
+                if from < to {
+                    let text = span.content[from - offset..to - offset].to_string();
+                    spans.push(Span::styled(text, piece_style));
+                }
+            }
+            offset = span_end;
+        }
+        line.spans = spans;
     }
 }
 
@@ -1497,7 +3944,95 @@ fn draw_help(f: &mut Frame, area: Rect) {
             Style::default().fg(TokyoNight::FG),
         )),
         Line::from(Span::styled(
-            "  d              Delete model (in Models tab)",
+            "  Enter          Model details (in Models tab, PgUp/PgDn scroll)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  b              Browse saved chat sessions (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  s / [ / ]      Session sidebar, previous/next session (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  w / d / J / K  Close, duplicate, move session down/up (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  Ctrl-C         Stop generating (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  P              System prompt presets (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  o              Generation options: temperature, num_ctx, seed... (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  y              Copy or save a code block from the replies (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  PgUp/PgDn      Scroll the transcript; Home/End top/follow (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  /  n / N       Search the transcript, next/previous match (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  g  E           Regenerate the last reply, edit a message and resend (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  ,  .           Previous/next branch at the last fork in view (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  C              Compare 2-4 models on the same prompt (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  a              Attach an image to the next message (in Chat tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  y / n / Esc    Run, decline or decline the rest of a tool call (/set tools on)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  e              Edit Modelfile and create a model (in Models tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  T              Check which models can call tools (in Models tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  Ctrl-O         Lint, diff and load files from modelfiles/ (in editor)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  Ctrl-F         Format the Modelfile (in editor)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  D              Delete model (in Models tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  [ / ]          Select download job (in Models tab)",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  x / z / R      Cancel / pause-resume / retry download",
+            Style::default().fg(TokyoNight::FG),
+        )),
+        Line::from(Span::styled(
+            "  + / - / X      Parallel downloads up/down, clear finished",
             Style::default().fg(TokyoNight::FG),
         )),
         Line::from(Span::styled(